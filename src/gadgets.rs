@@ -0,0 +1,205 @@
+//! Standard library of Boolean circuits (gadgets) in `partitioned_gc::Circuit` form
+//!
+//! Gadgets are written against a `Builder` whose bits are either circuit wires or
+//! constants. Constants are folded as gates are added, so parts of a computation
+//! known to the garbler (a public salt, a padding block, a digest to compare with)
+//! cost no gates at all. Gates follow the free-XOR cost model: gadgets spend AND
+//! gates sparingly and XOR/NOT freely.
+//!
+//! - `sha256`: SHA-256 and HMAC-SHA256 over secret message bits
+
+pub mod sha256;
+
+use std::collections::HashSet;
+
+use crate::partitioned_gc::{Circuit, Gate, GateType};
+
+/// A circuit bit: a wire, or a constant folded away at build time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bit {
+    Const(bool),
+    Wire(usize),
+}
+
+/// Incremental circuit construction with constant folding
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    gates: Vec<Gate>,
+    inputs: Vec<usize>,
+    next_wire: usize,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    /// New input wire
+    pub fn input(&mut self) -> Bit {
+        let wire = self.next_wire;
+        self.next_wire += 1;
+        self.inputs.push(wire);
+        Bit::Wire(wire)
+    }
+
+    pub fn inputs(&mut self, n: usize) -> Vec<Bit> {
+        (0..n).map(|_| self.input()).collect()
+    }
+
+    fn emit(&mut self, gate_type: GateType, left: usize, right: Option<usize>) -> Bit {
+        let output_wire = self.next_wire;
+        self.next_wire += 1;
+        self.gates.push(Gate { gate_type, left_wire: left, right_wire: right, output_wire });
+        Bit::Wire(output_wire)
+    }
+
+    pub fn not(&mut self, a: Bit) -> Bit {
+        match a {
+            Bit::Const(value) => Bit::Const(!value),
+            Bit::Wire(wire) => self.emit(GateType::Not, wire, None),
+        }
+    }
+
+    pub fn xor(&mut self, a: Bit, b: Bit) -> Bit {
+        match (a, b) {
+            (Bit::Const(x), Bit::Const(y)) => Bit::Const(x ^ y),
+            (Bit::Const(false), other) | (other, Bit::Const(false)) => other,
+            (Bit::Const(true), other) | (other, Bit::Const(true)) => self.not(other),
+            (Bit::Wire(x), Bit::Wire(y)) if x == y => Bit::Const(false),
+            (Bit::Wire(x), Bit::Wire(y)) => self.emit(GateType::Xor, x, Some(y)),
+        }
+    }
+
+    pub fn and(&mut self, a: Bit, b: Bit) -> Bit {
+        match (a, b) {
+            (Bit::Const(x), Bit::Const(y)) => Bit::Const(x && y),
+            (Bit::Const(false), _) | (_, Bit::Const(false)) => Bit::Const(false),
+            (Bit::Const(true), other) | (other, Bit::Const(true)) => other,
+            (Bit::Wire(x), Bit::Wire(y)) if x == y => a,
+            (Bit::Wire(x), Bit::Wire(y)) => self.emit(GateType::And, x, Some(y)),
+        }
+    }
+
+    pub fn or(&mut self, a: Bit, b: Bit) -> Bit {
+        match (a, b) {
+            (Bit::Const(x), Bit::Const(y)) => Bit::Const(x || y),
+            (Bit::Const(true), _) | (_, Bit::Const(true)) => Bit::Const(true),
+            (Bit::Const(false), other) | (other, Bit::Const(false)) => other,
+            (Bit::Wire(x), Bit::Wire(y)) if x == y => a,
+            (Bit::Wire(x), Bit::Wire(y)) => self.emit(GateType::Or, x, Some(y)),
+        }
+    }
+
+    /// Conjunction of all `bits` as a balanced tree (constant true when empty)
+    pub fn and_all(&mut self, bits: &[Bit]) -> Bit {
+        match bits.len() {
+            0 => Bit::Const(true),
+            1 => bits[0],
+            n => {
+                let left = self.and_all(&bits[..n / 2]);
+                let right = self.and_all(&bits[n / 2..]);
+                self.and(left, right)
+            }
+        }
+    }
+
+    /// Whether `bits` equal the constant `value`
+    pub fn equals_const(&mut self, bits: &[Bit], value: &[bool]) -> Bit {
+        assert_eq!(bits.len(), value.len(), "compared values differ in width");
+        let matches: Vec<Bit> = bits
+            .iter()
+            .zip(value)
+            .map(|(&bit, &expected)| if expected { bit } else { self.not(bit) })
+            .collect();
+        self.and_all(&matches)
+    }
+
+    /// Finish the circuit; constant outputs are derived from the first input wire,
+    /// and gates no output depends on are dropped
+    pub fn finish(mut self, outputs: &[Bit]) -> Circuit {
+        let output_wires: Vec<usize> = outputs
+            .iter()
+            .map(|&bit| match bit {
+                Bit::Wire(wire) => wire,
+                Bit::Const(value) => {
+                    let input = *self.inputs.first().expect("constant circuit needs an input wire");
+                    let zero = self.emit(GateType::Xor, input, Some(input));
+                    let bit = if value { self.not(zero) } else { zero };
+                    match bit {
+                        Bit::Wire(wire) => wire,
+                        Bit::Const(_) => unreachable!("emitted gates drive wires"),
+                    }
+                }
+            })
+            .collect();
+        let mut live: HashSet<usize> = output_wires.iter().copied().collect();
+        for gate in self.gates.iter().rev() {
+            if live.contains(&gate.output_wire) {
+                live.extend(gate.input_wires());
+            }
+        }
+        self.gates.retain(|gate| live.contains(&gate.output_wire));
+        Circuit::new(self.gates, self.inputs, output_wires)
+    }
+}
+
+/// Bits of `bytes`, most significant bit of each byte first
+pub fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes.iter().flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1)).collect()
+}
+
+/// Circuit that is true when every circuit in `circuits` is true on its first
+/// output. Inputs are concatenated in order, the wires of later circuits
+/// renumbered after those of earlier ones.
+pub fn conjoin(circuits: &[&Circuit]) -> Circuit {
+    let mut gates = Vec::new();
+    let mut input_wires = Vec::new();
+    let mut outputs = Vec::new();
+    let mut offset = 0;
+    for circuit in circuits {
+        let shift = |wire: usize| wire + offset;
+        input_wires.extend(circuit.input_wires.iter().map(|&w| shift(w)));
+        gates.extend(circuit.gates.iter().map(|g| Gate {
+            gate_type: g.gate_type,
+            left_wire: shift(g.left_wire),
+            right_wire: g.right_wire.map(shift),
+            output_wire: shift(g.output_wire),
+        }));
+        outputs.push(shift(*circuit.output_wires.first().expect("circuit has an output")));
+        let highest = circuit.gates.iter().flat_map(|g| g.input_wires().chain([g.output_wire]));
+        offset += highest.chain(circuit.input_wires.iter().copied()).max().map_or(0, |m| m + 1);
+    }
+
+    let mut builder = Builder { gates, inputs: input_wires, next_wire: offset };
+    let bits: Vec<Bit> = outputs.into_iter().map(Bit::Wire).collect();
+    let all = builder.and_all(&bits);
+    builder.finish(&[all])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::Aig;
+
+    #[test]
+    fn test_constant_folding_and_conjoin() {
+        let mut builder = Builder::new();
+        let x = builder.input();
+        let t = builder.xor(x, x);
+        assert_eq!(t, Bit::Const(false));
+        let one = builder.not(t);
+        assert_eq!(builder.and(x, one), x);
+        let y = builder.input();
+        let eq = builder.equals_const(&[x, y], &[true, false]);
+        let circuit = builder.finish(&[eq]);
+        assert_eq!(circuit.and_count(), 1);
+
+        let both = conjoin(&[&circuit, &circuit]);
+        assert_eq!(both.input_wires.len(), 4);
+        let aig = Aig::from_circuit(&both);
+        for row in 0..16u32 {
+            let values: Vec<bool> = (0..4).map(|i| row >> i & 1 == 1).collect();
+            assert_eq!(aig.eval(&values), vec![values == [true, false, true, false]]);
+        }
+    }
+}
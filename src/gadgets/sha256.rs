@@ -0,0 +1,182 @@
+//! SHA-256 (FIPS 180-4) and HMAC-SHA256 as Boolean circuits
+//!
+//! Messages are bit vectors, most significant bit of each byte first, and their
+//! length is fixed when the circuit is built: padding and the length block are
+//! constants. Digests come out in the same byte and bit order as `sha2`.
+//!
+//! Additions use the carry `c' = c ^ ((a ^ c) & (b ^ c))` (one AND per bit),
+//! `Ch(e, f, g) = g ^ (e & (f ^ g))` and `Maj(a, b, c) = a ^ ((a ^ b) & (a ^ c))`,
+//! so a compression costs about 22k AND gates. Blocks made only of constants,
+//! such as the padded HMAC key, fold away completely.
+
+use sha2::{Digest, Sha256};
+
+use super::{bytes_to_bits, Bit, Builder};
+
+/// 32-bit word, least significant bit first
+type Word = [Bit; 32];
+
+const BLOCK_BITS: usize = 512;
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const H0: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+fn constant(value: u32) -> Word {
+    std::array::from_fn(|i| Bit::Const(value >> i & 1 == 1))
+}
+
+fn rotr(x: &Word, n: usize) -> Word {
+    std::array::from_fn(|i| x[(i + n) % 32])
+}
+
+fn shr(x: &Word, n: usize) -> Word {
+    std::array::from_fn(|i| if i + n < 32 { x[i + n] } else { Bit::Const(false) })
+}
+
+fn xor3(b: &mut Builder, x: &Word, y: &Word, z: &Word) -> Word {
+    std::array::from_fn(|i| {
+        let xy = b.xor(x[i], y[i]);
+        b.xor(xy, z[i])
+    })
+}
+
+fn add(b: &mut Builder, x: &Word, y: &Word) -> Word {
+    let mut sum = [Bit::Const(false); 32];
+    let mut carry = Bit::Const(false);
+    for i in 0..32 {
+        let xc = b.xor(x[i], carry);
+        let yc = b.xor(y[i], carry);
+        sum[i] = b.xor(xc, y[i]);
+        if i < 31 {
+            let both = b.and(xc, yc);
+            carry = b.xor(carry, both);
+        }
+    }
+    sum
+}
+
+fn add_all(b: &mut Builder, words: &[&Word]) -> Word {
+    words[1..].iter().fold(*words[0], |acc, w| add(b, &acc, w))
+}
+
+fn ch(b: &mut Builder, e: &Word, f: &Word, g: &Word) -> Word {
+    std::array::from_fn(|i| {
+        let fg = b.xor(f[i], g[i]);
+        let select = b.and(e[i], fg);
+        b.xor(g[i], select)
+    })
+}
+
+fn maj(b: &mut Builder, x: &Word, y: &Word, z: &Word) -> Word {
+    std::array::from_fn(|i| {
+        let xy = b.xor(x[i], y[i]);
+        let xz = b.xor(x[i], z[i]);
+        let both = b.and(xy, xz);
+        b.xor(x[i], both)
+    })
+}
+
+/// One compression of a 512-bit block into `state`
+fn compress(b: &mut Builder, state: &[Word; 8], block: &[Bit]) -> [Word; 8] {
+    let mut w: Vec<Word> = block.chunks(32).map(|bits| std::array::from_fn(|i| bits[31 - i])).collect();
+    for t in 16..64 {
+        let s0 = xor3(b, &rotr(&w[t - 15], 7), &rotr(&w[t - 15], 18), &shr(&w[t - 15], 3));
+        let s1 = xor3(b, &rotr(&w[t - 2], 17), &rotr(&w[t - 2], 19), &shr(&w[t - 2], 10));
+        let next = add_all(b, &[&w[t - 16], &s0, &w[t - 7], &s1]);
+        w.push(next);
+    }
+
+    let [mut a, mut bb, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for t in 0..64 {
+        let sigma1 = xor3(b, &rotr(&e, 6), &rotr(&e, 11), &rotr(&e, 25));
+        let choice = ch(b, &e, &f, &g);
+        let t1 = add_all(b, &[&h, &sigma1, &choice, &constant(K[t]), &w[t]]);
+        let sigma0 = xor3(b, &rotr(&a, 2), &rotr(&a, 13), &rotr(&a, 22));
+        let majority = maj(b, &a, &bb, &c);
+        let t2 = add(b, &sigma0, &majority);
+        h = g;
+        g = f;
+        f = e;
+        e = add(b, &d, &t1);
+        d = c;
+        c = bb;
+        bb = a;
+        a = add(b, &t1, &t2);
+    }
+
+    let updated = [a, bb, c, d, e, f, g, h];
+    std::array::from_fn(|i| add(b, &state[i], &updated[i]))
+}
+
+/// SHA-256 of `message` (a whole number of bytes); returns the 256 digest bits
+pub fn sha256(builder: &mut Builder, message: &[Bit]) -> Vec<Bit> {
+    assert!(message.len().is_multiple_of(8), "SHA-256 messages are whole bytes");
+    let mut padded = message.to_vec();
+    padded.push(Bit::Const(true));
+    while padded.len() % BLOCK_BITS != BLOCK_BITS - 64 {
+        padded.push(Bit::Const(false));
+    }
+    padded.extend((0..64).rev().map(|i| Bit::Const((message.len() as u64) >> i & 1 == 1)));
+
+    let mut state = H0.map(constant);
+    for block in padded.chunks(BLOCK_BITS) {
+        state = compress(builder, &state, block);
+    }
+    state.iter().flat_map(|word| word.iter().rev().copied()).collect()
+}
+
+/// HMAC-SHA256 of `message` under a key known when the circuit is built
+pub fn hmac_sha256(builder: &mut Builder, key: &[u8], message: &[Bit]) -> Vec<Bit> {
+    let mut block = if key.len() > 64 { Sha256::digest(key).to_vec() } else { key.to_vec() };
+    block.resize(64, 0);
+    let pad = |byte: u8| -> Vec<Bit> {
+        let padded: Vec<u8> = block.iter().map(|k| k ^ byte).collect();
+        bytes_to_bits(&padded).into_iter().map(Bit::Const).collect()
+    };
+
+    let mut inner = pad(0x36);
+    inner.extend_from_slice(message);
+    let inner_digest = sha256(builder, &inner);
+    let mut outer = pad(0x5c);
+    outer.extend(inner_digest);
+    sha256(builder, &outer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::Aig;
+    use hmac::{Hmac, Mac};
+
+    fn digest_circuit(len: usize, hash: impl Fn(&mut Builder, &[Bit]) -> Vec<Bit>) -> Aig {
+        let mut builder = Builder::new();
+        let message = builder.inputs(8 * len);
+        let digest = hash(&mut builder, &message);
+        Aig::from_circuit(&builder.finish(&digest))
+    }
+
+    #[test]
+    fn test_digests_match_sha2() {
+        for message in [&b"start"[..], &[0xa5; 64], &[0x3c; 70]] {
+            let aig = digest_circuit(message.len(), sha256);
+            let expected = bytes_to_bits(&Sha256::digest(message));
+            assert_eq!(aig.eval(&bytes_to_bits(message)), expected, "message of {} bytes", message.len());
+        }
+
+        let key = b"verifier key";
+        let aig = digest_circuit(5, |b, m| hmac_sha256(b, key, m));
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+        mac.update(b"brake");
+        assert_eq!(aig.eval(&bytes_to_bits(b"brake")), bytes_to_bits(&mac.finalize().into_bytes()));
+    }
+}
@@ -0,0 +1,11 @@
+//! zk-fabric: a Polylithic Syntax Zero Knowledge Joint Proof System
+//!
+//! - `psg`: Polylithic Syntax Generation (composite statement -> Boolean circuit)
+//! - `partitioned_gc`: Partitioned Garbled Circuit scheme
+//! - `public_repo`: simulated DLT publishing of garbled circuits
+//! - `gadgets`: standard-library Boolean circuits (SHA-256, HMAC-SHA256)
+
+pub mod psg;
+pub mod partitioned_gc;
+pub mod public_repo;
+pub mod gadgets;
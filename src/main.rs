@@ -2,50 +2,38 @@
 5th Iteration
 */
 
-mod psg;
-mod partitioned_gc;
-mod public_repo;
-
-use public_repo::publish_to_public_repo;
-use crate::psg::polylithic_syntax_gen;
-use crate::partitioned_gc::{Circuit, Gate, GateType, PartitionedGCScheme};
+use zk_fabric::public_repo::publish_to_public_repo;
+use zk_fabric::psg::polylithic_syntax_gen;
+use zk_fabric::partitioned_gc::PartitionedGCScheme;
 
 fn main() {
     // Step 1: Input logical statement (your example)
     let input = r#"The car only starts if the "start" button is pressed and the "brake" pedal is pressed"#;
 
     // Step 2: Generate BooleanCircuit from PSG module
-    let boolean_circuit = polylithic_syntax_gen(input);
+    let boolean_circuit = polylithic_syntax_gen(input).expect("PSG failed to parse statement");
     println!("\n[Main] PSG generated boolean circuit:\n{:#?}", boolean_circuit);
 
-    // Step 3: Convert to partitioned_gc::Circuit (hardcoded for now)
-    let circuit = Circuit {
-        depth: 2,
-        width: 2,
-        gates: vec![
-            Gate { gate_type: GateType::And, left_wire: 0, right_wire: 1, output_wire: 2 },
-        ],
-        input_wires: vec![0, 1],
-        output_wires: vec![2],
-    };
+    // Step 3: Take the compiled partitioned_gc::Circuit
+    let circuit = boolean_circuit.circuit.clone();
     println!("\n[Main] Converted to partitioned_gc::Circuit:\n{:#?}", circuit);
 
-    // Step 4: Prepare inputs (e.g. start and brake both pressed)
-    let inputs = vec![1, 1];
-    let paired_inputs = PartitionedGCScheme::prepare_inputs(inputs);
+    // Step 4: Prepare inputs (car starts, start and brake all true)
+    let inputs = vec![1, 1, 1];
+    let _paired_inputs = PartitionedGCScheme::prepare_inputs(inputs);
 
     // Step 5: Garble the circuit (returns GarbledCircuit struct)
-    let garbled_circuit = PartitionedGCScheme::garble_circuit(&circuit);
+    let garbled_circuit = PartitionedGCScheme::garble_circuit(&circuit).expect("PSG generated a malformed circuit");
 
     // Destructure garbled circuit into components
     let wire_keys = &garbled_circuit.wire_keys;
     let garbled_gates = &garbled_circuit.garbled_gates;
 
     // Step 6: Publish circuit and encrypted data to simulated DLT
-    publish_to_public_repo(garbled_gates, wire_keys, &circuit).unwrap();
+    publish_to_public_repo(garbled_gates, wire_keys, &circuit, &boolean_circuit.hashing).unwrap();
 
-    // Step 7: Partition garbled circuit (1 gate per partition)
-    let partitions = PartitionedGCScheme::partition_garbled_circuit(&garbled_circuit, 1);
+    // Step 7: Partition garbled circuit (1 layer per partition)
+    let partitions = PartitionedGCScheme::partition_garbled_circuit_by_layer(&garbled_circuit, 1);
 
     // Step 8: Run protocol iterations over each partition
     PartitionedGCScheme::run_protocol_iterations(&partitions);
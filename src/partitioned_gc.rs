@@ -10,16 +10,21 @@
 //!
 //! Steps implemented here:
 //! 1) Input preparation: pairing inputs and auxiliary randomness
-//! 2) Garbled circuit construction (keys, ciphertext tables)
+//! 2) Garbled circuit construction (keys, point-and-permute ciphertext tables;
+//!    free-XOR: every wire's keys differ by one global offset, so XOR gates are
+//!    evaluated by XORing labels and NOT gates by swapping keys, both without tables)
 //! 3) Partitioning of garbled circuit matrix into independent circuits
 //! 4) Running protocol iterations per partition
 //! 5) Aggregating final verification output
 
 use serde::{Serialize, Deserialize};
 
-use rand::Rng;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Wire label length in bytes (128-bit keys)
+const KEY_LEN: usize = 16;
 
 /// Wire key pair: keys corresponding to 0 and 1 values on a wire
 // #[derive(Debug, Clone)]
@@ -29,14 +34,89 @@ pub struct WireKeys {
     pub key1: Vec<u8>, // cryptographic key representing 1
 }
 
+impl WireKeys {
+    /// Fresh random key pair with `key1 = key0 ^ delta`
+    fn random(delta: &[u8]) -> Self {
+        let key0: Vec<u8> = (0..KEY_LEN).map(|_| rand::random::<u8>()).collect();
+        Self::from_key0(key0, delta)
+    }
+
+    fn from_key0(key0: Vec<u8>, delta: &[u8]) -> Self {
+        let key1 = xor_bytes(&key0, delta);
+        WireKeys { key0, key1 }
+    }
+
+    /// Key encoding `bit` on this wire
+    pub fn key(&self, bit: bool) -> &[u8] {
+        if bit { &self.key1 } else { &self.key0 }
+    }
+
+    /// Bit encoded by `label`, or `None` if it is neither key of this wire
+    pub fn decode(&self, label: &[u8]) -> Option<u8> {
+        if label == self.key0.as_slice() {
+            Some(0)
+        } else if label == self.key1.as_slice() {
+            Some(1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Point-and-permute bit of a wire label
+fn permute_bit(label: &[u8]) -> usize {
+    (label[0] & 1) as usize
+}
+
+/// Row mask for a garbled table entry, tweaked by the gate's output wire
+fn gate_hash(left: &[u8], right: &[u8], tweak: usize) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.update((tweak as u64).to_le_bytes());
+    hasher.finalize()[..KEY_LEN].to_vec()
+}
+
+fn xor_bytes(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect()
+}
+
+/// Global free-XOR offset; its point-and-permute bit is set so the two keys of
+/// every wire have different permute bits
+fn random_delta() -> Vec<u8> {
+    let mut delta: Vec<u8> = (0..KEY_LEN).map(|_| rand::random::<u8>()).collect();
+    delta[0] |= 1;
+    delta
+}
+
 /// Logical gate types supported (for simplicity)
 // #[derive(Debug, Clone, Copy)]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum GateType {
     And,
     Or,
     Xor,
-    Not,
+    Not, // unary
+}
+
+impl GateType {
+    /// Number of input wires
+    pub fn arity(&self) -> usize {
+        match self {
+            GateType::Not => 1,
+            _ => 2,
+        }
+    }
+
+    /// Truth table of the gate (`right` is ignored for unary gates)
+    pub fn eval(&self, left: bool, right: bool) -> bool {
+        match self {
+            GateType::And => left & right,
+            GateType::Or => left | right,
+            GateType::Xor => left ^ right,
+            GateType::Not => !left,
+        }
+    }
 }
 
 /// A Boolean gate in the circuit
@@ -45,11 +125,31 @@ pub enum GateType {
 pub struct Gate {
     pub gate_type: GateType,
     pub left_wire: usize,
-    pub right_wire: usize,
+    pub right_wire: Option<usize>, // None for unary gates
     pub output_wire: usize,
 }
 
+impl Gate {
+    /// Two-input gate
+    pub fn binary(gate_type: GateType, left_wire: usize, right_wire: usize, output_wire: usize) -> Self {
+        Gate { gate_type, left_wire, right_wire: Some(right_wire), output_wire }
+    }
+
+    /// Unary NOT gate
+    pub fn not(input_wire: usize, output_wire: usize) -> Self {
+        Gate { gate_type: GateType::Not, left_wire: input_wire, right_wire: None, output_wire }
+    }
+
+    /// Wires read by this gate
+    pub fn input_wires(&self) -> impl Iterator<Item = usize> {
+        std::iter::once(self.left_wire).chain(self.right_wire)
+    }
+}
+
 /// The Boolean circuit represented as layers (depth × width matrix)
+///
+/// `gates` is stored flat in topological order; `depth` and `width` are the
+/// dimensions of its layered view (`Circuit::layers`).
 // #[derive(Debug)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Circuit {
@@ -60,16 +160,282 @@ pub struct Circuit {
     pub output_wires: Vec<usize>,
 }
 
+/// Layered view of a circuit: the rows of the depth × width matrix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layers {
+    pub levels: Vec<Vec<usize>>, // levels[d]: indices of the gates at level d + 1, in circuit order
+    pub wire_count: usize,       // distinct wires: inputs, gate inputs and gate outputs
+}
+
+impl Layers {
+    /// Number of levels (longest gate path from an input to an output)
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Number of gates on each level
+    pub fn widths(&self) -> Vec<usize> {
+        self.levels.iter().map(Vec::len).collect()
+    }
+
+    /// Most gates on one level
+    pub fn width(&self) -> usize {
+        self.levels.iter().map(Vec::len).max().unwrap_or(0)
+    }
+}
+
+/// Evaluate `gates` in the clear over the known wire `values` (0 or 1), inserting
+/// the value of every gate output; returns the gate outputs in evaluation order
+fn evaluate_gates(gates: &[Gate], values: &mut HashMap<usize, u8>) -> Vec<(usize, u8)> {
+    let mut trace = Vec::with_capacity(gates.len());
+    for gate in gates {
+        let value = |wire: usize| values.get(&wire).map(|&v| v != 0)
+            .unwrap_or_else(|| panic!("wire {} has no value", wire));
+        let right = gate.right_wire.is_some_and(value);
+        let output = gate.gate_type.eval(value(gate.left_wire), right) as u8;
+        values.insert(gate.output_wire, output);
+        trace.push((gate.output_wire, output));
+    }
+    trace
+}
+
+/// Topological levels of gates in topological order: wires no gate drives are
+/// level 0 and a gate is one level above its deepest input; `levels[d]` holds
+/// the indices of the gates at level d + 1
+fn levels(gates: &[Gate]) -> Vec<Vec<usize>> {
+    let mut wire_levels: HashMap<usize, usize> = HashMap::new();
+    let mut levels: Vec<Vec<usize>> = Vec::new();
+    for (i, gate) in gates.iter().enumerate() {
+        let level = 1 + gate.input_wires().map(|w| wire_levels.get(&w).copied().unwrap_or(0)).max().unwrap_or(0);
+        wire_levels.insert(gate.output_wire, level);
+        if levels.len() < level {
+            levels.resize(level, Vec::new());
+        }
+        levels[level - 1].push(i);
+    }
+    levels
+}
+
+/// Why a circuit is malformed or cannot be evaluated; `gate` is the index of the
+/// offending gate in `Circuit::gates`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitError {
+    /// The circuit has no output wire
+    NoOutputs,
+    /// A wire is listed twice in `input_wires`
+    DuplicateInput { wire: usize },
+    /// A NOT gate with a right wire, or a binary gate without one
+    Arity { gate: usize },
+    /// A gate drives a circuit input
+    DrivesInput { gate: usize, wire: usize },
+    /// Two gates drive the same wire
+    MultipleDrivers { gate: usize, wire: usize, first: usize },
+    /// A gate reads a wire that is neither an input nor driven by any gate
+    UndrivenWire { gate: usize, wire: usize },
+    /// A gate reads a wire driven by a later gate (`driver`), which does not depend on it
+    UsedBeforeDriven { gate: usize, wire: usize, driver: usize },
+    /// A gate reads a wire that depends on the gate's own output
+    Cycle { gate: usize, wire: usize },
+    /// A wire is listed twice in `output_wires`
+    DuplicateOutput { wire: usize },
+    /// An output wire is neither an input nor driven by any gate
+    UndrivenOutput { wire: usize },
+    /// A gate whose output no gate reads and that is not a circuit output
+    DanglingGate { gate: usize, wire: usize },
+    /// The stated depth and width differ from those of the gates' layered view
+    Dimensions { depth: usize, width: usize, actual_depth: usize, actual_width: usize },
+    /// Serialized circuit that cannot be read
+    Json(String),
+    /// Evaluation given a different number of input bits than the circuit has input wires
+    InputCount { expected: usize, actual: usize },
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::NoOutputs => write!(f, "circuit has no output wire"),
+            CircuitError::DuplicateInput { wire } => write!(f, "input wire {} is listed twice", wire),
+            CircuitError::Arity { gate } => write!(f, "gate {} has the wrong number of input wires", gate),
+            CircuitError::DrivesInput { gate, wire } => write!(f, "gate {} drives input wire {}", gate, wire),
+            CircuitError::MultipleDrivers { gate, wire, first } => {
+                write!(f, "gate {} drives wire {}, already driven by gate {}", gate, wire, first)
+            }
+            CircuitError::UndrivenWire { gate, wire } => {
+                write!(f, "gate {} reads wire {}, which is neither an input nor driven by a gate", gate, wire)
+            }
+            CircuitError::UsedBeforeDriven { gate, wire, driver } => {
+                write!(f, "gate {} reads wire {} before gate {} drives it", gate, wire, driver)
+            }
+            CircuitError::Cycle { gate, wire } => write!(f, "gate {} reads wire {}, which depends on the gate itself", gate, wire),
+            CircuitError::DuplicateOutput { wire } => write!(f, "output wire {} is listed twice", wire),
+            CircuitError::UndrivenOutput { wire } => {
+                write!(f, "output wire {} is neither an input nor driven by a gate", wire)
+            }
+            CircuitError::DanglingGate { gate, wire } => {
+                write!(f, "gate {} drives wire {}, which is neither read nor an output", gate, wire)
+            }
+            CircuitError::Dimensions { depth, width, actual_depth, actual_width } => write!(
+                f,
+                "circuit states depth {} and width {}, but its gates have depth {} and width {}",
+                depth, width, actual_depth, actual_width
+            ),
+            CircuitError::Json(message) => write!(f, "invalid circuit JSON: {}", message),
+            CircuitError::InputCount { expected, actual } => {
+                write!(f, "circuit has {} input wires, got {} input bits", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CircuitError {}
+
+impl Circuit {
+    /// Circuit over topologically ordered `gates`, with its depth and width
+    /// derived from the gates
+    pub fn new(gates: Vec<Gate>, input_wires: Vec<usize>, output_wires: Vec<usize>) -> Self {
+        let levels = levels(&gates);
+        let depth = levels.len();
+        let width = levels.iter().map(Vec::len).max().unwrap_or(0);
+        Circuit { depth, width, gates, input_wires, output_wires }
+    }
+
+    /// Layered view of the gates: topological levels and the wire count
+    pub fn layers(&self) -> Layers {
+        let wires: HashSet<usize> = self.input_wires.iter().copied()
+            .chain(self.gates.iter().flat_map(|g| g.input_wires().chain(std::iter::once(g.output_wire))))
+            .collect();
+        Layers { levels: levels(&self.gates), wire_count: wires.len() }
+    }
+
+    /// Gates that need a garbled table (AND and OR); XOR and NOT gates are free
+    pub fn and_count(&self) -> usize {
+        self.gates.iter().filter(|g| matches!(g.gate_type, GateType::And | GateType::Or)).count()
+    }
+
+    /// Check that the gates form a well-formed circuit in topological order: each
+    /// wire is an input or driven by exactly one gate, is driven before it is read,
+    /// every gate output is read or is a circuit output, and `depth` and `width`
+    /// are those of the layered view
+    pub fn validate(&self) -> Result<(), CircuitError> {
+        if self.output_wires.is_empty() {
+            return Err(CircuitError::NoOutputs);
+        }
+        // Driver of every wire: None for inputs, the gate index otherwise
+        let mut drivers: HashMap<usize, Option<usize>> = HashMap::new();
+        for &wire in &self.input_wires {
+            if drivers.insert(wire, None).is_some() {
+                return Err(CircuitError::DuplicateInput { wire });
+            }
+        }
+        for (i, gate) in self.gates.iter().enumerate() {
+            if gate.right_wire.is_some() != (gate.gate_type.arity() == 2) {
+                return Err(CircuitError::Arity { gate: i });
+            }
+            match drivers.insert(gate.output_wire, Some(i)) {
+                None => {}
+                Some(None) => return Err(CircuitError::DrivesInput { gate: i, wire: gate.output_wire }),
+                Some(Some(first)) => return Err(CircuitError::MultipleDrivers { gate: i, wire: gate.output_wire, first }),
+            }
+        }
+
+        // Whether gate `from` depends on the output of gate `on`
+        let depends = |from: usize, on: usize| {
+            let mut stack = vec![from];
+            let mut seen = HashSet::new();
+            while let Some(g) = stack.pop() {
+                if g == on {
+                    return true;
+                }
+                if seen.insert(g) {
+                    stack.extend(self.gates[g].input_wires().filter_map(|w| drivers.get(&w).copied().flatten()));
+                }
+            }
+            false
+        };
+        let mut read = HashSet::new();
+        for (i, gate) in self.gates.iter().enumerate() {
+            for wire in gate.input_wires() {
+                match drivers.get(&wire) {
+                    None => return Err(CircuitError::UndrivenWire { gate: i, wire }),
+                    Some(&Some(driver)) if driver >= i => {
+                        return Err(if depends(driver, i) {
+                            CircuitError::Cycle { gate: i, wire }
+                        } else {
+                            CircuitError::UsedBeforeDriven { gate: i, wire, driver }
+                        });
+                    }
+                    Some(_) => {}
+                }
+                read.insert(wire);
+            }
+        }
+
+        let mut outputs = HashSet::new();
+        for &wire in &self.output_wires {
+            if !outputs.insert(wire) {
+                return Err(CircuitError::DuplicateOutput { wire });
+            }
+            if !drivers.contains_key(&wire) {
+                return Err(CircuitError::UndrivenOutput { wire });
+            }
+        }
+        if let Some(i) = self.gates.iter().position(|g| !read.contains(&g.output_wire) && !outputs.contains(&g.output_wire)) {
+            return Err(CircuitError::DanglingGate { gate: i, wire: self.gates[i].output_wire });
+        }
+        let layers = self.layers();
+        if (self.depth, self.width) != (layers.depth(), layers.width()) {
+            return Err(CircuitError::Dimensions {
+                depth: self.depth,
+                width: self.width,
+                actual_depth: layers.depth(),
+                actual_width: layers.width(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Evaluate the circuit in the clear; `inputs[i]` is the bit on `input_wires[i]`
+    ///
+    /// Returns the value of every wire in evaluation order: the inputs, then each
+    /// gate output. This is the reference the garbled evaluation is checked against.
+    pub fn trace(&self, inputs: &[u8]) -> Result<Vec<(usize, u8)>, CircuitError> {
+        self.validate()?;
+        if inputs.len() != self.input_wires.len() {
+            return Err(CircuitError::InputCount { expected: self.input_wires.len(), actual: inputs.len() });
+        }
+        let mut trace: Vec<(usize, u8)> =
+            self.input_wires.iter().zip(inputs).map(|(&wire, &bit)| (wire, (bit != 0) as u8)).collect();
+        let mut values: HashMap<usize, u8> = trace.iter().copied().collect();
+        trace.extend(evaluate_gates(&self.gates, &mut values));
+        Ok(trace)
+    }
+
+    /// Output bits of the circuit evaluated in the clear, in `output_wires` order
+    pub fn evaluate(&self, inputs: &[u8]) -> Result<Vec<u8>, CircuitError> {
+        let values: HashMap<usize, u8> = self.trace(inputs)?.into_iter().collect();
+        Ok(self.output_wires.iter().map(|wire| values[wire]).collect())
+    }
+
+    /// Read a circuit serialized as JSON (as published by `public_repo`), refusing
+    /// malformed circuits
+    pub fn from_json(json: &str) -> Result<Circuit, CircuitError> {
+        let circuit: Circuit = serde_json::from_str(json).map_err(|e| CircuitError::Json(e.to_string()))?;
+        circuit.validate()?;
+        Ok(circuit)
+    }
+}
+
 /// A garbled gate: the encrypted truth table for the gate outputs
 // #[derive(Debug, Clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GarbledGate {
-    pub ciphertexts: Vec<Vec<u8>>, // 4 ciphertexts per 2-input gate
+    pub ciphertexts: Vec<Vec<u8>>, // 4 ciphertexts per AND/OR gate, none for XOR and NOT
 }
 
 /// A garbled circuit consists of garbled gates and wire keys
 #[derive(Debug)]
 pub struct GarbledCircuit {
+    pub gates: Vec<Gate>, // wiring of each garbled gate
     pub garbled_gates: Vec<GarbledGate>,
     pub wire_keys: HashMap<usize, WireKeys>, // wire id -> WireKeys
     pub input_wires: Vec<usize>,
@@ -91,10 +457,8 @@ impl PartitionedGCScheme {
     /// # Returns
     /// - new input vector after pairing and auxiliary random bits if needed
     pub fn prepare_inputs(mut inputs: Vec<u8>) -> Vec<(u8, u8)> {
-        let mut rng = rand::rng();
-
         // If odd number of inputs, add auxiliary random bits a0 and a1, then append a0 XOR a1
-        if inputs.len() % 2 != 0 {
+        if !inputs.len().is_multiple_of(2) {
             let a0: u8 = rand::random::<u8>() % 2 ;
             let a1: u8 = rand::random::<u8>() % 2 ;
             inputs.push(a0 ^ a1);
@@ -116,40 +480,114 @@ impl PartitionedGCScheme {
     /// - circuit: Boolean circuit to garble
     ///
     /// # Returns
-    /// - garbled circuit with wire keys and garbled gates
-    pub fn garble_circuit(circuit: &Circuit) -> GarbledCircuit {
-        let mut rng = rand::rng();
+    /// - garbled circuit with wire keys and garbled gates, or why the circuit is malformed
+    pub fn garble_circuit(circuit: &Circuit) -> Result<GarbledCircuit, CircuitError> {
+        circuit.validate()?;
 
-        // Assign random wire keys for each wire in circuit
+        // Assign random wire keys to each input wire; gate outputs get keys as they are garbled
+        let delta = random_delta();
         let mut wire_keys = HashMap::new();
-        let wire_count = circuit.width * circuit.depth; // approximation; could be refined
-        for wire_id in 0..wire_count {
-          let key0: Vec<u8> = (0..16).map(|_| rand::random::<u8>()).collect(); // 128-bit keys
-          let key1: Vec<u8> = (0..16).map(|_| rand::random::<u8>()).collect();
-          wire_keys.insert(wire_id, WireKeys { key0, key1 });
-      }
-      
+        for &wire_id in &circuit.input_wires {
+            wire_keys.insert(wire_id, WireKeys::random(&delta));
+        }
 
         // Garble each gate by encrypting output keys under input keys according to truth table
         let mut garbled_gates = Vec::new();
         for gate in &circuit.gates {
-            // For simplicity, create dummy ciphertexts (in practice, encrypt output keys)
-            let ciphertexts = (0..4)
-            .map(|_| (0..32).map(|_| rand::random::<u8>()).collect())
-            .collect();
+            if gate.gate_type == GateType::Not {
+                // Free NOT: the output keys are the input keys swapped, no table needed
+                let input = &wire_keys[&gate.left_wire];
+                let output = WireKeys { key0: input.key1.clone(), key1: input.key0.clone() };
+                wire_keys.insert(gate.output_wire, output);
+                garbled_gates.push(GarbledGate { ciphertexts: Vec::new() });
+                continue;
+            }
+
+            let right_wire = gate.right_wire.expect("binary gate has a right wire");
+            if gate.gate_type == GateType::Xor {
+                // Free XOR: key0 of the output is the XOR of the inputs' key0s
+                let key0 = xor_bytes(&wire_keys[&gate.left_wire].key0, &wire_keys[&right_wire].key0);
+                wire_keys.insert(gate.output_wire, WireKeys::from_key0(key0, &delta));
+                garbled_gates.push(GarbledGate { ciphertexts: Vec::new() });
+                continue;
+            }
+
+            let output = WireKeys::random(&delta);
+            let mut ciphertexts = vec![Vec::new(); 4];
+            for left_bit in [false, true] {
+                for right_bit in [false, true] {
+                    let left = wire_keys[&gate.left_wire].key(left_bit);
+                    let right = wire_keys[&right_wire].key(right_bit);
+                    let row = 2 * permute_bit(left) + permute_bit(right);
+                    let out = output.key(gate.gate_type.eval(left_bit, right_bit));
+                    ciphertexts[row] = xor_bytes(&gate_hash(left, right, gate.output_wire), out);
+                }
+            }
+            wire_keys.insert(gate.output_wire, output);
             garbled_gates.push(GarbledGate { ciphertexts });
         }
 
         println!("[Garble Circuit] Assigned keys to wires and garbled {} gates.", circuit.gates.len());
 
-        GarbledCircuit {
+        Ok(GarbledCircuit {
+            gates: circuit.gates.clone(),
             garbled_gates,
             wire_keys,
             input_wires: circuit.input_wires.clone(),
             output_wires: circuit.output_wires.clone(),
+        })
+    }
+
+    /// Select the input wire labels for the given input bits
+    ///
+    /// The OT phase is assumed complete (see README), so keys are picked directly.
+    pub fn encode_inputs(garbled_circuit: &GarbledCircuit, inputs: &[u8]) -> HashMap<usize, Vec<u8>> {
+        garbled_circuit.input_wires.iter().zip(inputs)
+            .map(|(wire_id, &bit)| (*wire_id, garbled_circuit.wire_keys[wire_id].key(bit != 0).to_vec()))
+            .collect()
+    }
+
+    /// Evaluate the garbled gates of a (partitioned) garbled circuit
+    ///
+    /// `labels` holds the known wire labels and receives the label of every gate output,
+    /// so partitions can be evaluated one after another over the same map.
+    pub fn evaluate_garbled(garbled_circuit: &GarbledCircuit, labels: &mut HashMap<usize, Vec<u8>>) {
+        for (gate, garbled_gate) in garbled_circuit.gates.iter().zip(&garbled_circuit.garbled_gates) {
+            let left = labels.get(&gate.left_wire)
+                .unwrap_or_else(|| panic!("wire {} has no label", gate.left_wire));
+            let output = match gate.right_wire {
+                None => left.clone(),
+                Some(right_wire) => {
+                    let right = labels.get(&right_wire)
+                        .unwrap_or_else(|| panic!("wire {} has no label", right_wire));
+                    if gate.gate_type == GateType::Xor {
+                        labels.insert(gate.output_wire, xor_bytes(left, right));
+                        continue;
+                    }
+                    let row = 2 * permute_bit(left) + permute_bit(right);
+                    xor_bytes(&gate_hash(left, right, gate.output_wire), &garbled_gate.ciphertexts[row])
+                }
+            };
+            labels.insert(gate.output_wire, output);
         }
     }
 
+    /// Evaluate the gates of a (partitioned) garbled circuit in the clear
+    ///
+    /// The plaintext counterpart of `evaluate_garbled`: `values` holds the known wire
+    /// bits and receives every gate output, and the partition's gate outputs are
+    /// returned in evaluation order.
+    pub fn evaluate_plain(garbled_circuit: &GarbledCircuit, values: &mut HashMap<usize, u8>) -> Vec<(usize, u8)> {
+        evaluate_gates(&garbled_circuit.gates, values)
+    }
+
+    /// Decode output wire labels into bits, `None` if any label is missing or invalid
+    pub fn decode_outputs(garbled_circuit: &GarbledCircuit, labels: &HashMap<usize, Vec<u8>>) -> Option<Vec<u8>> {
+        garbled_circuit.output_wires.iter()
+            .map(|wire_id| garbled_circuit.wire_keys.get(wire_id)?.decode(labels.get(wire_id)?))
+            .collect()
+    }
+
     /// Step 5: Partition the garbled circuit horizontally
     ///
     /// # Arguments
@@ -167,11 +605,13 @@ impl PartitionedGCScheme {
         for start in (0..total_gates).step_by(partition_size) {
             let end = std::cmp::min(start + partition_size, total_gates);
             let gates_slice = &garbled_circuit.garbled_gates[start..end];
+            let wiring_slice = &garbled_circuit.gates[start..end];
 
             // Extract all wires involved in these gates (for demo, just use all wires - refinement needed)
             let wire_keys = garbled_circuit.wire_keys.clone();
 
             partitions.push(GarbledCircuit {
+                gates: wiring_slice.to_vec(),
                 garbled_gates: gates_slice.to_vec(),
                 wire_keys: wire_keys.clone(),
                 input_wires: garbled_circuit.input_wires.clone(),   // in practice, only relevant inputs
@@ -184,6 +624,35 @@ impl PartitionedGCScheme {
         partitions
     }
 
+    /// Step 5 (by layer): Partition the garbled circuit along its levels
+    ///
+    /// Each partition holds `layers_per_partition` consecutive levels of the gate DAG,
+    /// so a partition only reads wires of the inputs and of earlier partitions.
+    /// Gates keep their circuit order within a partition.
+    pub fn partition_garbled_circuit_by_layer(garbled_circuit: &GarbledCircuit, layers_per_partition: usize) -> PartitionedGC {
+        assert!(layers_per_partition > 0, "a partition holds at least one layer");
+        let levels = levels(&garbled_circuit.gates);
+
+        let mut partitions = Vec::new();
+        for (i, chunk) in levels.chunks(layers_per_partition).enumerate() {
+            let mut indices: Vec<usize> = chunk.concat();
+            indices.sort_unstable();
+
+            partitions.push(GarbledCircuit {
+                gates: indices.iter().map(|&g| garbled_circuit.gates[g].clone()).collect(),
+                garbled_gates: indices.iter().map(|&g| garbled_circuit.garbled_gates[g].clone()).collect(),
+                wire_keys: garbled_circuit.wire_keys.clone(),
+                input_wires: garbled_circuit.input_wires.clone(),
+                output_wires: garbled_circuit.output_wires.clone(),
+            });
+
+            let first = i * layers_per_partition + 1;
+            println!("[Partition] Created partition with levels {} to {} ({} gates)", first, first + chunk.len() - 1, indices.len());
+        }
+
+        partitions
+    }
+
     /// Step 6: Run multiple iterations of protocol per partition
     ///
     /// For demonstration, this is a stub.
@@ -199,7 +668,7 @@ impl PartitionedGCScheme {
     /// Step 7: Aggregate the final verification from all partitions
     ///
     /// Returns a combined verification output (stub)
-    pub fn aggregate_verification(partitions: &PartitionedGC) -> Vec<u8> {
+    pub fn aggregate_verification(_partitions: &PartitionedGC) -> Vec<u8> {
         println!("[Aggregate Verification] Combining outputs from partitions.");
         // Placeholder: in practice, combine cryptographic proofs from all partitions
         vec![1u8] // dummy success flag
@@ -227,21 +696,207 @@ mod tests {
     #[test]
     fn test_partition_and_garble() {
         // Dummy circuit with 4 gates
-        let circuit = Circuit {
-            depth: 2,
-            width: 2,
-            gates: vec![
-                Gate { gate_type: GateType::And, left_wire: 0, right_wire: 1, output_wire: 2 },
-                Gate { gate_type: GateType::Xor, left_wire: 2, right_wire: 3, output_wire: 4 },
-                Gate { gate_type: GateType::Or, left_wire: 4, right_wire: 5, output_wire: 6 },
-                Gate { gate_type: GateType::And, left_wire: 6, right_wire: 7, output_wire: 8 },
+        let circuit = Circuit::new(
+            vec![
+                Gate { gate_type: GateType::And, left_wire: 0, right_wire: Some(1), output_wire: 2 },
+                Gate { gate_type: GateType::Xor, left_wire: 2, right_wire: Some(3), output_wire: 4 },
+                Gate { gate_type: GateType::Or, left_wire: 4, right_wire: Some(5), output_wire: 6 },
+                Gate { gate_type: GateType::And, left_wire: 6, right_wire: Some(7), output_wire: 8 },
             ],
-            input_wires: vec![0, 1, 3, 5],
-            output_wires: vec![8],
-        };
+            vec![0, 1, 3, 5, 7],
+            vec![8],
+        );
 
-        let gc = PartitionedGCScheme::garble_circuit(&circuit);
+        let gc = PartitionedGCScheme::garble_circuit(&circuit).unwrap();
         let partitions = PartitionedGCScheme::partition_garbled_circuit(&gc, 2);
         assert_eq!(partitions.len(), 2);
     }
+
+    #[test]
+    fn test_validate_reports_offending_gate() {
+        let circuit = Circuit::new;
+        let and = |l, r, o| Gate::binary(GateType::And, l, r, o);
+        use CircuitError::*;
+        let cases = [
+            (circuit(vec![and(0, 1, 2), and(2, 3, 4)], vec![0, 1], vec![4]), UndrivenWire { gate: 1, wire: 3 }),
+            (circuit(vec![and(0, 3, 2), Gate::not(1, 3)], vec![0, 1], vec![2]), UsedBeforeDriven { gate: 0, wire: 3, driver: 1 }),
+            (circuit(vec![and(0, 3, 2), Gate::not(2, 3)], vec![0, 1], vec![2]), Cycle { gate: 0, wire: 3 }),
+            (circuit(vec![and(0, 1, 2), Gate::not(0, 2)], vec![0, 1], vec![2]), MultipleDrivers { gate: 1, wire: 2, first: 0 }),
+            (circuit(vec![and(0, 1, 1)], vec![0, 1], vec![1]), DrivesInput { gate: 0, wire: 1 }),
+            (circuit(vec![and(0, 1, 2), Gate::not(0, 3)], vec![0, 1], vec![2]), DanglingGate { gate: 1, wire: 3 }),
+            (circuit(vec![and(0, 1, 2)], vec![0, 1], vec![2, 2]), DuplicateOutput { wire: 2 }),
+            (circuit(vec![and(0, 1, 2)], vec![0, 1], vec![2, 5]), UndrivenOutput { wire: 5 }),
+            (circuit(vec![and(0, 1, 2)], vec![0, 1, 0], vec![2]), DuplicateInput { wire: 0 }),
+            (circuit(vec![Gate { right_wire: None, ..and(0, 1, 2) }], vec![0, 1], vec![2]), Arity { gate: 0 }),
+            (circuit(vec![], vec![0], vec![]), NoOutputs),
+        ];
+        for (circuit, error) in cases {
+            assert_eq!(circuit.validate(), Err(error.clone()));
+            assert_eq!(PartitionedGCScheme::garble_circuit(&circuit).unwrap_err(), error);
+        }
+
+        // An input wire may be an output on its own
+        assert_eq!(circuit(vec![], vec![0], vec![0]).validate(), Ok(()));
+
+        // Loading a serialized circuit validates it
+        let json = serde_json::to_string(&circuit(vec![and(0, 1, 2), and(2, 3, 4)], vec![0, 1], vec![4])).unwrap();
+        assert_eq!(Circuit::from_json(&json).unwrap_err(), UndrivenWire { gate: 1, wire: 3 });
+        assert!(matches!(Circuit::from_json("{"), Err(Json(_))));
+        let json = serde_json::to_string(&circuit(vec![and(0, 1, 2)], vec![0, 1], vec![2])).unwrap();
+        assert_eq!(Circuit::from_json(&json).unwrap().gates.len(), 1);
+
+        // Stale dimensions are refused, not trusted
+        let stale = Circuit { depth: 2, ..circuit(vec![and(0, 1, 2)], vec![0, 1], vec![2]) };
+        let error = Dimensions { depth: 2, width: 1, actual_depth: 1, actual_width: 1 };
+        assert_eq!(stale.validate(), Err(error.clone()));
+        assert_eq!(Circuit::from_json(&serde_json::to_string(&stale).unwrap()).unwrap_err(), error);
+    }
+
+    #[test]
+    fn test_garble_and_evaluate_with_not_gates() {
+        // (NOT a AND b) OR NOT (b XOR c)
+        let circuit = Circuit::new(
+            vec![
+                Gate::not(0, 3),
+                Gate::binary(GateType::And, 3, 1, 4),
+                Gate::binary(GateType::Xor, 1, 2, 5),
+                Gate::not(5, 6),
+                Gate::binary(GateType::Or, 4, 6, 7),
+            ],
+            vec![0, 1, 2],
+            vec![7],
+        );
+
+        let gc = PartitionedGCScheme::garble_circuit(&circuit).unwrap();
+        assert!(gc.garbled_gates[0].ciphertexts.is_empty());
+        assert!(gc.garbled_gates[2].ciphertexts.is_empty());
+        assert!(gc.garbled_gates[3].ciphertexts.is_empty());
+        assert_eq!(gc.garbled_gates[1].ciphertexts.len(), 4);
+        assert_eq!(circuit.and_count(), 2);
+
+        let partitions = PartitionedGCScheme::partition_garbled_circuit(&gc, 2);
+        let by_layer = PartitionedGCScheme::partition_garbled_circuit_by_layer(&gc, 1);
+        assert_eq!(by_layer.len(), 3);
+        for x in 0..8u8 {
+            let inputs = [x & 1, (x >> 1) & 1, (x >> 2) & 1];
+            let (a, b, c) = (inputs[0] == 1, inputs[1] == 1, inputs[2] == 1);
+            let expected = ((!a && b) || !(b ^ c)) as u8;
+
+            let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+            PartitionedGCScheme::evaluate_garbled(&gc, &mut labels);
+            assert_eq!(PartitionedGCScheme::decode_outputs(&gc, &labels), Some(vec![expected]));
+
+            let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+            for partition in &partitions {
+                PartitionedGCScheme::evaluate_garbled(partition, &mut labels);
+            }
+            assert_eq!(PartitionedGCScheme::decode_outputs(&gc, &labels), Some(vec![expected]));
+
+            let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+            for partition in &by_layer {
+                PartitionedGCScheme::evaluate_garbled(partition, &mut labels);
+            }
+            assert_eq!(PartitionedGCScheme::decode_outputs(&gc, &labels), Some(vec![expected]));
+        }
+    }
+
+    /// Random valid circuit: every gate reads earlier wires, and the wires no gate
+    /// reads are the outputs
+    fn random_circuit(inputs: usize, gates: usize) -> Circuit {
+        let pick = |below: usize| rand::random_range(0..below);
+        let mut list = Vec::new();
+        for output in inputs..inputs + gates {
+            list.push(match pick(4) {
+                0 => Gate::not(pick(output), output),
+                t => {
+                    let gate_type = [GateType::And, GateType::Or, GateType::Xor][t - 1];
+                    Gate::binary(gate_type, pick(output), pick(output), output)
+                }
+            });
+        }
+        let read: HashSet<usize> = list.iter().flat_map(|g: &Gate| g.input_wires()).collect();
+        let outputs = (inputs..inputs + gates).filter(|w| !read.contains(w)).collect();
+        Circuit::new(list, (0..inputs).collect(), outputs)
+    }
+
+    #[test]
+    fn test_plaintext_evaluation_is_the_garbling_oracle() {
+        for _ in 0..20 {
+            let circuit = random_circuit(5, 30);
+            let gc = PartitionedGCScheme::garble_circuit(&circuit).unwrap();
+            let partitionings = [
+                PartitionedGCScheme::partition_garbled_circuit(&gc, 7),
+                PartitionedGCScheme::partition_garbled_circuit_by_layer(&gc, 2),
+            ];
+            for x in 0..32u8 {
+                let inputs: Vec<u8> = (0..5).map(|i| (x >> i) & 1).collect();
+                let expected = circuit.evaluate(&inputs).unwrap();
+                let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+                PartitionedGCScheme::evaluate_garbled(&gc, &mut labels);
+                assert_eq!(PartitionedGCScheme::decode_outputs(&gc, &labels), Some(expected.clone()));
+
+                // Every wire of every partition carries the label of its plaintext value
+                let trace = circuit.trace(&inputs).unwrap();
+                for partitions in &partitionings {
+                    let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+                    let mut values: HashMap<usize, u8> = trace[..inputs.len()].iter().copied().collect();
+                    let mut partition_trace = Vec::new();
+                    for partition in partitions {
+                        PartitionedGCScheme::evaluate_garbled(partition, &mut labels);
+                        for (wire, bit) in PartitionedGCScheme::evaluate_plain(partition, &mut values) {
+                            assert_eq!(gc.wire_keys[&wire].decode(&labels[&wire]), Some(bit), "wire {}", wire);
+                            partition_trace.push((wire, bit));
+                        }
+                    }
+                    partition_trace.sort_unstable();
+                    let mut gate_trace = trace[inputs.len()..].to_vec();
+                    gate_trace.sort_unstable();
+                    assert_eq!(partition_trace, gate_trace);
+                    assert_eq!(PartitionedGCScheme::decode_outputs(&gc, &labels), Some(expected.clone()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_trace_lists_every_wire() {
+        // (a AND b) XOR NOT c
+        let circuit = Circuit::new(
+            vec![Gate::binary(GateType::And, 0, 1, 3), Gate::not(2, 4), Gate::binary(GateType::Xor, 3, 4, 5)],
+            vec![0, 1, 2],
+            vec![5],
+        );
+        let trace = circuit.trace(&[1, 1, 1]).unwrap();
+        assert_eq!(trace, vec![(0, 1), (1, 1), (2, 1), (3, 1), (4, 0), (5, 1)]);
+        assert_eq!(circuit.evaluate(&[1, 0, 1]).unwrap(), vec![0]);
+        assert_eq!(circuit.evaluate(&[1, 0]), Err(CircuitError::InputCount { expected: 3, actual: 2 }));
+    }
+
+    #[test]
+    fn test_layers_from_gate_dag() {
+        // Two independent chains of different lengths joined by a last gate:
+        // level 1: NOT a, b AND c; level 2: NOT (b AND c); level 3: the OR
+        let circuit = Circuit::new(
+            vec![
+                Gate::not(0, 3),
+                Gate::binary(GateType::And, 1, 2, 4),
+                Gate::not(4, 5),
+                Gate::binary(GateType::Or, 3, 5, 6),
+            ],
+            vec![0, 1, 2, 7],
+            vec![6],
+        );
+        let layers = circuit.layers();
+        assert_eq!(layers.levels, vec![vec![0, 1], vec![2], vec![3]]);
+        assert_eq!(layers.widths(), vec![2, 1, 1]);
+        assert_eq!((layers.depth(), layers.width()), (3, 2));
+        assert_eq!((circuit.depth, circuit.width), (3, 2));
+        assert_eq!(layers.wire_count, 8, "an unused input wire still counts");
+
+        // Gates stored out of level order are grouped by level, in circuit order
+        let gc = PartitionedGCScheme::garble_circuit(&circuit).unwrap();
+        let partitions = PartitionedGCScheme::partition_garbled_circuit_by_layer(&gc, 2);
+        let outputs: Vec<Vec<usize>> = partitions.iter().map(|p| p.gates.iter().map(|g| g.output_wire).collect()).collect();
+        assert_eq!(outputs, vec![vec![3, 4, 5], vec![6]]);
+    }
 }
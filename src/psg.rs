@@ -14,125 +14,795 @@ It can be logically modeled as:
 depending on direction of implication.
 
 Extracting [quoted strings] as variables and logic keywords like [and] as Boolean operators.
-*/ 
+The statement is tokenized (`lexer`) and parsed into a typed syntax tree (`parser`)
+respecting parentheses. Chains mixing `and`, `or` and `xor` without parentheses
+are refused unless `PsgOptions::disambiguation` picks a reading, such as
+precedence (NOT > AND > XOR > OR) under `Disambiguation::Precedence`.
+*/
 
-// use std::collections::HashMap;
-use sha2::{Sha256, Digest};
+mod aig;
+mod compile;
+mod controlled;
+mod dsl;
+mod equiv;
+mod espresso;
+mod hashing;
+mod integer;
+mod kmap;
+mod lexer;
+mod lexicon;
+mod normal;
+mod parser;
+mod pretty;
+mod ruleset;
+mod sat;
+mod snort;
+mod xag;
+
+pub use aig::{Aig, Lit, Node};
+pub use compile::CompiledCircuit;
+pub use controlled::ControlledGrammar;
+pub use dsl::parse_policy;
+pub use equiv::{
+    check_circuits, check_expressions, check_statements, Counterexample, Equivalence, EquivalenceError, Method,
+    TruthTable, MAX_TRUTH_TABLE_VARIABLES,
+};
+pub use espresso::{Cover, Cube, MAX_COVER_CUBES};
+pub use kmap::{Implicant, Minimized, MAX_KMAP_VARIABLES};
+pub use lexer::{Span, Token, TokenKind};
+pub use hashing::{HashKey, HashManifest, ManifestError, VariableHasher, VariableHashing};
+pub use integer::{Comparison, VarType, MAX_INTEGER_WIDTH};
+pub use lexicon::{CorrelativePair, Keyword, Lexicon};
+pub use normal::{Clausal, Literal, NormalForm};
+pub use parser::{Disambiguation, MAX_READINGS};
+pub use ruleset::{parse_rule_set, CompiledRule, Rule, RuleOutputs};
+pub use sat::{satisfiability, solve, solve_clauses, Satisfiability};
+pub use snort::{parse_snort_rule, RuleAction, SnortRule, SNORT_FIELDS};
+pub use xag::minimize_and_gates;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::partitioned_gc::Circuit;
 
 /// Logical variable
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Variable(String);
+///
+/// Two variables are the same variable when their names (and bits) match; the
+/// span only records where this occurrence was read from, for diagnostics.
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub span: Span,
+    /// Bit (least significant first) of an integer variable, `None` for a Boolean
+    pub bit: Option<usize>,
+}
+
+impl Variable {
+    /// Variable that does not come from a source statement
+    pub fn new(name: impl Into<String>) -> Self {
+        Variable { name: name.into(), span: Span::default(), bit: None }
+    }
+}
+
+impl PartialEq for Variable {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.bit == other.bit
+    }
+}
+
+impl Eq for Variable {}
+
+impl std::hash::Hash for Variable {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.bit.hash(state);
+    }
+}
+
+/// The name, with the bit index of an integer bit (`speed[3]`)
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bit {
+            Some(bit) => write!(f, "{}[{}]", self.name, bit),
+            None => f.write_str(&self.name),
+        }
+    }
+}
 
 /// Logical operator enum
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Operator {
     And,
     Or,
     Xor,
-    // Not,
+    /// `a -> b`: "a only if b", "if a then b"
+    Implies,
+    /// `a <- b`: "a if b", "a whenever b"
+    Converse,
+    /// `a <-> b`: "a if and only if b"
+    Iff,
+    /// Unary negation: "not", "never", "isn't", "unless"
+    Not,
     // Extendable
 }
 
+impl Operator {
+    /// Symbol used when rendering expressions
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::And => "&&",
+            Operator::Or => "||",
+            Operator::Xor => "^",
+            Operator::Implies => "->",
+            Operator::Converse => "<-",
+            Operator::Iff => "<->",
+            Operator::Not => "!",
+        }
+    }
+}
+
+/// English connective introducing a conditional statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Connective {
+    If,
+    OnlyIf,
+    Iff,
+    Then,
+    Whenever,
+    Unless,
+}
+
+/// Typed syntax tree of a composite statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Var(Variable),
+    /// Constant produced by optimization (e.g. a minimized tautology)
+    Const(bool),
+    Not(Box<Expr>),
+    Binary {
+        op: Operator,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// Cardinality constraint: the number of true `operands` compared with `k`
+    /// ("at least 2 of ...", "exactly 1 of ...")
+    Threshold {
+        op: Comparison,
+        k: usize,
+        operands: Vec<Expr>,
+    },
+}
+
+impl Expr {
+    pub fn binary(op: Operator, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) }
+    }
+
+    /// Render with every nested binary or cardinality sub-expression
+    /// parenthesized, naming each variable through `name`. Cardinality
+    /// constraints read `#(a, b, c) >= 2`.
+    pub fn render(&self, name: &dyn Fn(&Variable) -> String) -> String {
+        let nested = |e: &Expr| match e {
+            Expr::Binary { .. } | Expr::Threshold { .. } => format!("({})", e.render(name)),
+            _ => e.render(name),
+        };
+        match self {
+            Expr::Var(var) => name(var),
+            Expr::Const(value) => value.to_string(),
+            Expr::Not(inner) => format!("{}{}", Operator::Not.symbol(), nested(inner)),
+            Expr::Binary { op, lhs, rhs } => format!("{} {} {}", nested(lhs), op.symbol(), nested(rhs)),
+            Expr::Threshold { op, k, operands } => {
+                format!("#({}) {} {}", operands.iter().map(nested).collect::<Vec<_>>().join(", "), op, k)
+            }
+        }
+    }
+
+    /// Truth value under an assignment of the variables
+    pub fn eval(&self, value: &dyn Fn(&Variable) -> bool) -> bool {
+        match self {
+            Expr::Var(var) => value(var),
+            Expr::Const(constant) => *constant,
+            Expr::Not(inner) => !inner.eval(value),
+            Expr::Binary { op, lhs, rhs } => {
+                let (a, b) = (lhs.eval(value), rhs.eval(value));
+                match op {
+                    Operator::And => a && b,
+                    Operator::Or => a || b,
+                    Operator::Xor => a ^ b,
+                    Operator::Implies => !a || b,
+                    Operator::Converse => a || !b,
+                    Operator::Iff => a == b,
+                    Operator::Not => unreachable!("negation is parsed as Expr::Not"),
+                }
+            }
+            Expr::Threshold { op, k, operands } => {
+                let count = operands.iter().filter(|e| e.eval(value)).count();
+                op.eval(count as u64, *k as u64)
+            }
+        }
+    }
+
+    /// Visit every variable occurrence, left to right
+    pub fn for_each_var<'a>(&'a self, f: &mut dyn FnMut(&'a Variable)) {
+        match self {
+            Expr::Var(var) => f(var),
+            Expr::Const(_) => {}
+            Expr::Not(inner) => inner.for_each_var(f),
+            Expr::Binary { lhs, rhs, .. } => {
+                lhs.for_each_var(f);
+                rhs.for_each_var(f);
+            }
+            Expr::Threshold { operands, .. } => {
+                for operand in operands {
+                    operand.for_each_var(f);
+                }
+            }
+        }
+    }
+}
+
 /// Internal representation of a parsed logical rule
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LogicExpression {
-    variables: Vec<String>,
-    operators: Vec<Operator>,
+    /// Distinct variables, in order of first appearance
+    pub variables: Vec<Variable>,
+    /// Syntax tree of the rule
+    pub root: Expr,
+    /// Main clause of a conditional statement when it is a single variable,
+    /// e.g. `car starts` in `The car only starts if ...`
+    pub consequent: Option<Variable>,
 }
 
-/// Boolean circuit representation (placeholder)
-#[derive(Debug)]
+impl LogicExpression {
+    pub fn new(root: Expr) -> Self {
+        let mut variables: Vec<Variable> = Vec::new();
+        root.for_each_var(&mut |var| {
+            if !variables.contains(var) {
+                variables.push(var.clone());
+            }
+        });
+        LogicExpression { variables, root, consequent: None }
+    }
+
+    /// Same statement with the syntax tree replaced, keeping variable order
+    fn with_root(&self, root: Expr) -> Self {
+        LogicExpression { variables: self.variables.clone(), root, consequent: self.consequent.clone() }
+    }
+
+    /// Negation normal form (only `&&`, `||` and negated variables)
+    pub fn to_nnf(&self) -> LogicExpression {
+        self.with_root(normal::nnf(&self.root))
+    }
+
+    /// Conjunctive normal form; Tseitin-encoded when the equivalent CNF is too large
+    pub fn to_cnf(&self) -> Clausal {
+        normal::cnf(self)
+    }
+
+    /// Tseitin-encoded conjunctive normal form
+    pub fn to_tseitin_cnf(&self) -> Clausal {
+        normal::tseitin(self)
+    }
+
+    /// Disjunctive normal form, `None` when it exceeds `MAX_COVER_CUBES` terms
+    pub fn to_dnf(&self) -> Option<Clausal> {
+        normal::dnf(self)
+    }
+
+    /// Compile into a circuit whose variables hash to `hashed` (same order as `variables`)
+    pub fn compile(&self, hashed: &[String]) -> CompiledCircuit {
+        compile::compile(self, hashed)
+    }
+
+    /// Statement computed by the first output of a circuit, `variables[i]` entering
+    /// on `circuit.input_wires[i]`
+    pub fn from_circuit(circuit: &Circuit, variables: &[Variable]) -> Self {
+        pretty::from_circuit(circuit, variables)
+    }
+
+    /// Normalized English with quoted variable names, which the parser reads back
+    /// without disambiguation
+    pub fn to_english(&self) -> String {
+        pretty::english(self)
+    }
+}
+
+impl fmt::Display for LogicExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.root.render(&|var| var.to_string()))
+    }
+}
+
+/// Error raised while lexing or parsing a composite statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        ParseError { message: message.into(), span }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Options of the PSG pipeline
+#[derive(Debug, Clone, Default)]
+pub struct PsgOptions {
+    /// Keyword table of the statement's language
+    pub lexicon: Lexicon,
+    /// Normal form shown next to the hashed expression
+    pub normal_form: NormalForm,
+    /// Keyed (default) or legacy hashing of variable names
+    pub hashing: VariableHashing,
+    /// Declared types by variable name; undeclared variables are Boolean
+    pub types: HashMap<String, VarType>,
+    /// Reading of statements mixing `and`, `or` and `xor` without parentheses
+    pub disambiguation: Disambiguation,
+    /// Controlled-English grammar recognizing unquoted noun phrases and references
+    /// as variables; only quoted variables when `None`
+    pub grammar: Option<ControlledGrammar>,
+}
+
+/// Boolean circuit generated from a composite statement
+#[derive(Debug, Clone)]
 pub struct BooleanCircuit {
-    gates: Vec<String>, // Could be a graph or logic gate tree
-}
-
-/// === Step 1: Extractor_v ===
-fn extractor_v(input: &str) -> Vec<Variable> {
-  let vars: Vec<Variable> = input
-  .split_whitespace()
-  .filter(|s| s.starts_with('"') && s.ends_with('"'))
-  .map(|s| Variable(s.trim_matches('"').to_string()))
-  .collect();
-  println!("[Extractor_v] Extracted Variables: {:?}", vars);
-  vars    
-}
-
-/// === Step 2: Extractor_o ===
-fn extractor_o(input: &str) -> Vec<Operator> {
-  let mut ops = Vec::new();
-  if input.contains("and") {
-      ops.push(Operator::And);
-      println!("[Extractor_o] Found Operator: AND");
-  }
-  if input.contains("or") {
-      ops.push(Operator::Or);
-      println!("[Extractor_o] Found Operator: OR");
-  }
-  if input.contains("xor") {
-      ops.push(Operator::Xor);
-      println!("[Extractor_o] Found Operator: XOR");
-  }
-  ops
+    pub expression: String, // hashed expression built by build_regexp
+    pub hashing: HashManifest, // public parameters of the variable hashes, published with the circuit
+    pub hash_key: HashKey, // secret hashing key, to be shared with verifiers only (empty in legacy mode; redacted in Debug)
+    pub normal_form: String, // hashed expression in the normal form selected by PsgOptions
+    pub circuit: Circuit, // ready for PartitionedGCScheme::garble_circuit
+    pub wire_map: HashMap<String, usize>, // hashed variable -> input wire
+    pub consequent: Option<String>, // hashed consequent variable of a conditional statement
+    pub optimization: Option<KmapReport>, // gate-count reduction achieved by karnaugh_optimize
+    pub multilevel: Option<MultilevelReport>, // gate-count and depth reduction achieved by multilevel_optimize
+    pub free_xor: Option<FreeXorReport>, // AND-count reduction achieved by free_xor_optimize
+    pub satisfiability: Option<Satisfiability>, // set by check_satisfiability; witnesses are over input wires
+}
+
+/// Rule set compiled into one circuit (see `polylithic_rule_set_gen`)
+#[derive(Debug, Clone)]
+pub struct RuleSetCircuit {
+    pub hashing: HashManifest, // public parameters of the variable hashes, shared by all rules
+    pub hash_key: HashKey, // secret hashing key, to be shared with verifiers only (empty in legacy mode; redacted in Debug)
+    pub circuit: Circuit, // ready for PartitionedGCScheme::garble_circuit
+    pub wire_map: HashMap<String, usize>, // hashed variable -> input wire, one per variable of the whole set
+    pub rules: Vec<CompiledRule>, // rule IDs and their output wires, in rule order
+    pub satisfiability: Option<Satisfiability>, // of all rules together; Unsatisfiable when they contradict each other
+}
+
+impl BooleanCircuit {
+    /// The optimized circuit as a statement over the prover's variables, `variables[i]`
+    /// entering on input wire `i` (the order of `LogicExpression::variables`)
+    pub fn to_expression(&self, variables: &[Variable]) -> LogicExpression {
+        LogicExpression::from_circuit(&self.circuit, variables)
+    }
+
+    /// The optimized circuit as a statement over the hashed variables, for the public
+    pub fn hashed_expression(&self) -> LogicExpression {
+        let mut hashed = vec![Variable::new(""); self.circuit.input_wires.len()];
+        for (hash, &wire) in &self.wire_map {
+            let i = self.circuit.input_wires.iter().position(|&w| w == wire).expect("wire_map names input wires");
+            hashed[i] = Variable::new(hash.clone());
+        }
+        LogicExpression::from_circuit(&self.circuit, &hashed)
+    }
+}
+
+impl RuleSetCircuit {
+    /// Output wire of the rule with ID `id`
+    pub fn output_wire(&self, id: &str) -> Option<usize> {
+        self.rules.iter().find(|rule| rule.id == id).map(|rule| rule.output_wire)
+    }
+}
+
+/// Outcome of the K-map optimization step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KmapReport {
+    pub prime_implicants: usize,
+    pub terms: usize, // product terms of the minimized sum of products
+    pub literals: usize,
+    pub gates_before: usize,
+    pub gates_after: usize, // equals gates_before when neither candidate circuit was smaller
+    pub threshold: Option<String>, // hashed cardinality constraint with the same truth table, if any
+}
+
+/// Outcome of the multi-level optimization step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultilevelReport {
+    pub cubes: Option<(usize, usize)>, // cover size before -> after Espresso, None when it did not run
+    pub gates_before: usize,
+    pub gates_after: usize,
+    pub depth_before: usize,
+    pub depth_after: usize,
+}
+
+/// Outcome of the free-XOR optimization step; AND gates (including ORs) are the
+/// only gates that cost garbled tables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeXorReport {
+    pub and_gates_before: usize,
+    pub and_gates_after: usize,
+    pub gates_before: usize,
+    pub gates_after: usize, // includes the free XOR and NOT gates
 }
 
 /// === Step 3: Hash variables ===
-fn hash_variables(vars: &[Variable]) -> Vec<String> {
+fn hash_variables(vars: &[Variable], hasher: &VariableHasher) -> Vec<String> {
+  let manifest = hasher.manifest();
+  println!("[Hash] Scheme {}, salt '{}', key commitment '{}'", manifest.scheme, manifest.salt, manifest.key_commitment);
   let hashed: Vec<String> = vars.iter().map(|var| {
-      let mut hasher = Sha256::new();
-      hasher.update(var.0.as_bytes());
-      let result = format!("{:x}", hasher.finalize());
-      println!("[Hash] '{}' => {}", var.0, result);
+      let result = match var.bit {
+          Some(bit) => format!("{}[{}]", hasher.hash(&var.name), bit),
+          None => hasher.hash(&var.name),
+      };
+      println!("[Hash] '{}' => {}", var, result);
       result
   }).collect();
   println!("[HashVariables](To hide actual variable values) Hashed Variables: {:?}", hashed);
   hashed
 }
 
-/// === Step 4: Regexp(variables, ops) ===
-fn build_regexp(vars: &[String], ops: &[Operator]) -> String {
-  let mut pattern = String::new();
-  for (i, var) in vars.iter().enumerate() {
-      pattern.push_str(&format!("({})", var));
-      if let Some(op) = ops.get(i) {
-          let sym = match op {
-              Operator::And => "&&",
-              Operator::Or => "||",
-              Operator::Xor => "^",
-              // Operator::Not => "!",
-          };
-          pattern.push_str(&format!(" {} ", sym));
-      }
-  }
+/// === Step 4: Regexp(expression, hashed variables) ===
+fn build_regexp(expr: &LogicExpression, hashed: &[String]) -> String {
+  let pattern = expr.root.render(&|var| {
+      let i = expr.variables.iter().position(|v| v == var).expect("variable collected by LogicExpression");
+      format!("({})", hashed[i])
+  });
   println!("[Regexp](Form a regular expression from variable and ops) Built Expression: {}", pattern);
   pattern
 }
 
+/// === Step 4b: Normal form(expression, hashed variables) ===
+fn normal_form(expr: &LogicExpression, hashed: &[String], form: NormalForm) -> String {
+  let name = |var: &Variable| {
+      let i = expr.variables.iter().position(|v| v == var).expect("variable collected by LogicExpression");
+      format!("({})", hashed[i])
+  };
+  let rendered = match form {
+      NormalForm::Nnf => expr.to_nnf().root.render(&name),
+      NormalForm::Cnf => {
+          let cnf = expr.to_cnf();
+          if cnf.auxiliary() > 0 {
+              println!("[NormalForm] CNF too large, using Tseitin encoding with {} auxiliary variables", cnf.auxiliary());
+          }
+          cnf.render(&name)
+      }
+      NormalForm::Dnf => match expr.to_dnf() {
+          Some(dnf) => dnf.render(&name),
+          None => {
+              println!("[NormalForm] DNF exceeds {} terms, showing NNF instead", MAX_COVER_CUBES);
+              expr.to_nnf().root.render(&name)
+          }
+      },
+  };
+  println!("[NormalForm] {:?}: {}", form, rendered);
+  rendered
+}
+
 /// === Step 5: CircuitGen ===
-fn circuit_gen(expression: &str) -> BooleanCircuit {
-  println!("[CircuitGen](Compile the regular expression into boolean circuits) Generating circuit for: {}", expression);
+fn circuit_gen(
+    expr: &LogicExpression,
+    hashed: &[String],
+    hasher: &VariableHasher,
+    regexp: String,
+    normal_form: String,
+    consequent: Option<String>,
+) -> BooleanCircuit {
+  println!("[CircuitGen](Compile the regular expression into boolean circuits) Generating circuit for: {}", regexp);
+  let CompiledCircuit { circuit, wire_map } = compile::compile(expr, hashed);
+  println!(
+      "[CircuitGen] {} input wires, {} gates, depth {}, width {}, output wire {:?}",
+      circuit.input_wires.len(), circuit.gates.len(), circuit.depth, circuit.width, circuit.output_wires
+  );
   BooleanCircuit {
-      gates: vec![format!("circuit({})", expression)],
+      expression: regexp,
+      hashing: hasher.manifest().clone(),
+      hash_key: hasher.key().clone(),
+      normal_form,
+      circuit,
+      wire_map,
+      consequent,
+      optimization: None,
+      multilevel: None,
+      free_xor: None,
+      satisfiability: None,
   }
 }
 
 /// === Step 6: K-map Optimization ===
-fn karnaugh_optimize(circuit: BooleanCircuit) -> BooleanCircuit {
-  println!("[KarnaughOptimize] Optimizing circuit (placeholder)");
+fn karnaugh_optimize(mut circuit: BooleanCircuit, expr: &LogicExpression, hashed: &[String]) -> BooleanCircuit {
+  let minimized = match kmap::minimize(expr) {
+      Some(minimized) => minimized,
+      None => {
+          println!("[KarnaughOptimize] {} variables exceed the exact limit of {}, skipping", expr.variables.len(), MAX_KMAP_VARIABLES);
+          return circuit;
+      }
+  };
+  let sop = compile::compile(&minimized.expression, hashed).circuit;
+  let gates_before = circuit.circuit.gates.len();
+  let mut best = sop;
+
+  // Symmetric functions (majority, exactly-k) are far smaller as adder trees
+  let threshold = kmap::threshold(expr);
+  if let Some(root) = &threshold {
+      let counted = LogicExpression { variables: expr.variables.clone(), root: root.clone(), consequent: expr.consequent.clone() };
+      let candidate = compile::compile(&counted, hashed).circuit;
+      if candidate.gates.len() < best.gates.len() {
+          best = candidate;
+      }
+  }
+  let threshold = threshold.map(|root| {
+      root.render(&|var| {
+          let i = expr.variables.iter().position(|v| v == var).expect("variable collected by LogicExpression");
+          format!("({})", hashed[i])
+      })
+  });
+
+  let report = KmapReport {
+      prime_implicants: minimized.prime_implicants,
+      terms: minimized.terms.len(),
+      literals: minimized.terms.iter().map(|t| t.literals(expr.variables.len())).sum(),
+      gates_before,
+      gates_after: best.gates.len().min(gates_before),
+      threshold,
+  };
+  println!("[KarnaughOptimize] Minimized sum of products: {}", minimized.expression);
+  if let Some(threshold) = &report.threshold {
+      println!("[KarnaughOptimize] Recognized cardinality constraint: {}", threshold);
+  }
+  println!(
+      "[KarnaughOptimize] {} prime implicants, {} terms, {} literals; gates {} -> {}",
+      report.prime_implicants, report.terms, report.literals, report.gates_before, report.gates_after
+  );
+  if best.gates.len() < gates_before {
+      circuit.circuit = best;
+  }
+  circuit.optimization = Some(report);
+  circuit
+}
+
+/// === Step 7: Multi-level Optimization ===
+fn multilevel_optimize(mut circuit: BooleanCircuit, expr: &LogicExpression, hashed: &[String]) -> BooleanCircuit {
+  // Never trade gates for depth: fewer gates wins, depth breaks ties
+  let cost = |c: &Circuit| (c.gates.len(), c.depth);
+  let (gates_before, depth_before) = cost(&circuit.circuit);
+  let mut best = circuit.circuit.clone();
+
+  // Espresso + factoring where the exact K-map step gave up
+  let mut cubes = None;
+  if expr.variables.len() > MAX_KMAP_VARIABLES {
+      match espresso::cover(expr) {
+          Some(cover) => {
+              let minimized = espresso::espresso(&cover);
+              cubes = Some((cover.cubes.len(), minimized.cubes.len()));
+              println!(
+                  "[MultilevelOptimize] Espresso: {} cubes / {} literals -> {} cubes / {} literals",
+                  cover.cubes.len(), cover.literals(), minimized.cubes.len(), minimized.literals()
+              );
+              let factored = LogicExpression {
+                  variables: expr.variables.clone(),
+                  root: espresso::factor(&minimized, &expr.variables),
+                  consequent: expr.consequent.clone(),
+              };
+              let candidate = compile::compile(&factored, hashed).circuit;
+              if cost(&candidate) < cost(&best) {
+                  best = candidate;
+              }
+          }
+          None => println!("[MultilevelOptimize] Cover exceeds {} cubes, skipping Espresso", MAX_COVER_CUBES),
+      }
+  }
+
+  // Structural hashing (common subexpressions) and AND/XOR tree balancing
+  let balanced = Aig::from_circuit(&best).balance().to_circuit();
+  if cost(&balanced) < cost(&best) {
+      best = balanced;
+  }
+
+  let report = MultilevelReport {
+      cubes,
+      gates_before,
+      gates_after: best.gates.len(),
+      depth_before,
+      depth_after: best.depth,
+  };
+  println!(
+      "[MultilevelOptimize] gates {} -> {}, depth {} -> {}",
+      report.gates_before, report.gates_after, report.depth_before, report.depth_after
+  );
+  circuit.circuit = best;
+  circuit.multilevel = Some(report);
+  circuit
+}
+
+/// === Step 8: Free-XOR Optimization ===
+fn free_xor_optimize(mut circuit: BooleanCircuit) -> BooleanCircuit {
+  // Only garbled tables count; free gates break ties
+  let cost = |c: &Circuit| (c.and_count(), c.gates.len());
+  let xag = minimize_and_gates(&circuit.circuit);
+  let best = if cost(&xag) < cost(&circuit.circuit) { xag } else { circuit.circuit.clone() };
+  let report = FreeXorReport {
+      and_gates_before: circuit.circuit.and_count(),
+      and_gates_after: best.and_count(),
+      gates_before: circuit.circuit.gates.len(),
+      gates_after: best.gates.len(),
+  };
+  println!(
+      "[FreeXorOptimize] AND gates {} -> {} (gates {} -> {}, depth {})",
+      report.and_gates_before, report.and_gates_after, report.gates_before, report.gates_after, best.depth
+  );
+  circuit.circuit = best;
+  circuit.free_xor = Some(report);
+  circuit
+}
+
+/// === Step 9: Satisfiability check ===
+fn check_satisfiability(mut circuit: BooleanCircuit) -> BooleanCircuit {
+  let hashed_of = |wire: usize| {
+      circuit.wire_map.iter().find(|(_, w)| **w == wire).map_or_else(|| format!("wire {}", wire), |(h, _)| h.clone())
+  };
+  let show = |witness: &[(usize, bool)]| {
+      witness.iter().map(|&(w, b)| format!("{}={}", hashed_of(w), b as u8)).collect::<Vec<_>>().join(", ")
+  };
+  let result = sat::satisfiability(&circuit.circuit);
+  match &result {
+      Satisfiability::Contingent { satisfying, falsifying } => {
+          println!("[Satisfiability] Satisfiable by [{}], falsifiable by [{}]", show(satisfying), show(falsifying));
+      }
+      Satisfiability::Tautology { witness } => {
+          println!("[Satisfiability] WARNING: statement is a tautology (always true), e.g. [{}]; proving it is meaningless", show(witness));
+      }
+      Satisfiability::Unsatisfiable { witness } => {
+          println!("[Satisfiability] WARNING: statement is unsatisfiable (always false), e.g. [{}]; proving it is meaningless", show(witness));
+      }
+      Satisfiability::Unknown { .. } => {
+          println!("[Satisfiability] Gave up after {} conflicts; the statement was not checked", sat::CONFLICT_BUDGET);
+      }
+  }
+  circuit.satisfiability = Some(result);
   circuit
 }
 
 /// === Main Driver ===
-pub fn polylithic_syntax_gen(input: &str) -> crate::psg::BooleanCircuit {
+pub fn polylithic_syntax_gen(input: &str) -> Result<crate::psg::BooleanCircuit, ParseError> {
+  polylithic_syntax_gen_with(input, &PsgOptions::default())
+}
+
+/// Main driver with explicit options (e.g. a non-English lexicon)
+pub fn polylithic_syntax_gen_with(input: &str, options: &PsgOptions) -> Result<BooleanCircuit, ParseError> {
   println!("--- Polylithic Syntax Generation Start ---");
   println!("[Input] Composite Statement: {}", input);
 
-  let vars = extractor_v(input);
-  let ops = extractor_o(input);
-  let hashed_vars = hash_variables(&vars);
-  let regexp_expr = build_regexp(&hashed_vars, &ops);
-  let raw_circuit = circuit_gen(&regexp_expr);
-  let final_circuit = karnaugh_optimize(raw_circuit);
+  // Steps 1 and 2: lexer and parser
+  let tokens = controlled::tokenize(input, options)?;
+  let expression = parser::parse_with(&tokens, input.len(), options)?;
+  println!("[Parser] Parsed Expression: {}", expression);
+  Ok(polylithic_syntax_gen_from(&expression, options))
+}
+
+/// Every reading of an English statement (at most `MAX_READINGS`); more than one
+/// when it mixes `and`, `or` and `xor` without parentheses
+pub fn statement_readings(input: &str, options: &PsgOptions) -> Result<Vec<LogicExpression>, ParseError> {
+  let tokens = controlled::tokenize(input, options)?;
+  parser::readings(&tokens, input.len(), options)
+}
+
+/// Statement with quoted variables parsed under `Disambiguation::Precedence`, for
+/// tests written against the NOT > AND > XOR > OR grammar
+#[cfg(test)]
+pub(crate) fn parse_precedence(input: &str) -> Result<LogicExpression, ParseError> {
+  let options = PsgOptions { disambiguation: Disambiguation::Precedence, ..PsgOptions::default() };
+  parser::parse_with(&lexer::tokenize(input, &options.lexicon)?, input.len(), &options)
+}
+
+/// Driver for a statement parsed by another front end (`parse_policy`, `parse_snort_rule`),
+/// running the pipeline from variable hashing on
+pub fn polylithic_syntax_gen_from(expression: &LogicExpression, options: &PsgOptions) -> BooleanCircuit {
+  let hasher = VariableHasher::new(&options.hashing);
+  let hashed_vars = hash_variables(&expression.variables, &hasher);
+  let regexp_expr = build_regexp(expression, &hashed_vars);
+  let normal_form_expr = normal_form(expression, &hashed_vars, options.normal_form);
+  let consequent = expression.consequent.as_ref().map(|var| {
+      let i = expression.variables.iter().position(|v| v == var).expect("consequent is a variable of the expression");
+      println!("[Conditional] Consequent Variable: '{}' => {}", var.name, hashed_vars[i]);
+      hashed_vars[i].clone()
+  });
+  let raw_circuit = circuit_gen(expression, &hashed_vars, &hasher, regexp_expr, normal_form_expr, consequent);
+  let kmap_circuit = karnaugh_optimize(raw_circuit, expression, &hashed_vars);
+  let multilevel_circuit = multilevel_optimize(kmap_circuit, expression, &hashed_vars);
+  let xag_circuit = free_xor_optimize(multilevel_circuit);
+  let final_circuit = check_satisfiability(xag_circuit);
 
   println!("--- Polylithic Syntax Generation Complete ---");
   final_circuit
-}
\ No newline at end of file
+}
+
+/// === Rule Set Driver ===
+pub fn polylithic_rule_set_gen(input: &str, options: &PsgOptions, outputs: RuleOutputs) -> Result<RuleSetCircuit, ParseError> {
+  println!("--- Polylithic Rule Set Generation Start ---");
+  let rules = parse_rule_set(input, options)?;
+  for rule in &rules {
+      println!("[Parser] Rule '{}': {}", rule.id, rule.expression);
+  }
+  Ok(polylithic_rule_set_gen_from(&rules, options, outputs))
+}
+
+/// Rule set driver for rules parsed by any front end; variables named alike in
+/// several rules are hashed once and share an input wire
+pub fn polylithic_rule_set_gen_from(rules: &[Rule], options: &PsgOptions, outputs: RuleOutputs) -> RuleSetCircuit {
+  assert!(!rules.is_empty(), "rule set has at least one rule");
+  let hasher = VariableHasher::new(&options.hashing);
+  let variables = ruleset::shared_variables(rules);
+  let hashed_vars = hash_variables(&variables, &hasher);
+  let hashed_of = |var: &Variable| {
+      hashed_vars[variables.iter().position(|v| v == var).expect("variable collected from the rules")].clone()
+  };
+
+  let mut roots = Vec::new();
+  let mut compiled_rules = Vec::new();
+  for rule in rules {
+      let hashed: Vec<String> = rule.expression.variables.iter().map(hashed_of).collect();
+      let expression = build_regexp(&rule.expression, &hashed);
+      let consequent = rule.expression.consequent.as_ref().map(hashed_of);
+      // Keep the minimized sum of products of a rule when it compiles smaller on its own
+      let gates = |root: &Expr| compile::compile(&rule.expression.with_root(root.clone()), &hashed).circuit.gates.len();
+      let root = match kmap::minimize(&rule.expression) {
+          Some(minimized) if gates(&minimized.expression.root) < gates(&rule.expression.root) => minimized.expression.root,
+          _ => rule.expression.root.clone(),
+      };
+      println!("[RuleSet] Rule '{}' => {}", rule.id, expression);
+      roots.push(root);
+      compiled_rules.push(CompiledRule { id: rule.id.clone(), expression, consequent, output_wire: 0 });
+  }
+  let conjunction = roots.iter().cloned().reduce(|a, b| Expr::binary(Operator::And, a, b)).expect("rule set has rules");
+  let outputs = match outputs {
+      RuleOutputs::PerRule => roots,
+      RuleOutputs::Conjunction => vec![conjunction.clone()],
+  };
+
+  let CompiledCircuit { circuit, wire_map } = compile::compile_outputs(&variables, &outputs, &hashed_vars);
+  let gates_before = circuit.gates.len();
+  let balanced = Aig::from_circuit(&circuit).balance().to_circuit();
+  let best = if balanced.gates.len() < circuit.gates.len() { balanced } else { circuit };
+  let minimized = minimize_and_gates(&best);
+  let cost = |c: &Circuit| (c.and_count(), c.gates.len());
+  let best = if cost(&minimized) < cost(&best) { minimized } else { best };
+  let circuit = compile::distinct_outputs(best);
+  println!(
+      "[RuleSet] {} rules, {} shared input wires, gates {} -> {} ({} AND), output wires {:?}",
+      rules.len(), circuit.input_wires.len(), gates_before, circuit.gates.len(), circuit.and_count(), circuit.output_wires
+  );
+  for (i, rule) in compiled_rules.iter_mut().enumerate() {
+      rule.output_wire = circuit.output_wires[i.min(circuit.output_wires.len() - 1)];
+  }
+
+  let joint = compile::compile_outputs(&variables, &[conjunction], &hashed_vars).circuit;
+  let result = sat::satisfiability(&joint);
+  match &result {
+      Satisfiability::Contingent { .. } => println!("[Satisfiability] Rules are jointly satisfiable"),
+      Satisfiability::Tautology { .. } => println!("[Satisfiability] WARNING: every rule is a tautology (always true)"),
+      Satisfiability::Unsatisfiable { .. } => println!("[Satisfiability] WARNING: the rules contradict each other (never all true)"),
+      Satisfiability::Unknown { .. } => {
+          println!("[Satisfiability] Gave up after {} conflicts; the rules were not checked", sat::CONFLICT_BUDGET)
+      }
+  }
+
+  println!("--- Polylithic Rule Set Generation Complete ---");
+  RuleSetCircuit {
+      hashing: hasher.manifest().clone(),
+      hash_key: hasher.key().clone(),
+      circuit,
+      wire_map,
+      rules: compiled_rules,
+      satisfiability: Some(result),
+  }
+}
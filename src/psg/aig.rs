@@ -0,0 +1,498 @@
+//! AND/XOR-inverter graph (AIG) used by the circuit-level optimizations
+//!
+//! Nodes are two-input ANDs and XORs; negation lives on the edges (`Lit`), so NOT
+//! gates cost nothing until the graph is turned back into a `Circuit`. Nodes are
+//! structurally hashed on creation, which merges common subexpressions, and the
+//! usual local rules (`a & a = a`, `a & !a = 0`, `a ^ a = 0`, ...) are applied.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::normal::Literal;
+use crate::partitioned_gc::{Circuit, Gate, GateType};
+
+/// Edge to a node, possibly complemented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub const FALSE: Lit = Lit(0);
+    pub const TRUE: Lit = Lit(1);
+
+    fn new(node: usize, complement: bool) -> Lit {
+        Lit((node as u32) << 1 | complement as u32)
+    }
+
+    pub fn node(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_complement(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    /// The edge without its complement
+    pub fn regular(self) -> Lit {
+        Lit(self.0 & !1)
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// Graph node; node 0 is the constant false
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Node {
+    Const,
+    /// Index into `Aig::inputs`
+    Input(usize),
+    And(Lit, Lit),
+    Xor(Lit, Lit),
+}
+
+#[derive(Debug, Clone)]
+pub struct Aig {
+    pub nodes: Vec<Node>,
+    /// Logic level of every node (inputs and constant are level 0)
+    pub levels: Vec<usize>,
+    /// Circuit wire of every input, in input order
+    pub inputs: Vec<usize>,
+    pub outputs: Vec<Lit>,
+    strash: HashMap<Node, usize>,
+}
+
+impl Default for Aig {
+    fn default() -> Self {
+        Aig::new()
+    }
+}
+
+impl Aig {
+    pub fn new() -> Self {
+        Aig {
+            nodes: vec![Node::Const],
+            levels: vec![0],
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            strash: HashMap::new(),
+        }
+    }
+
+    /// Graph with the given input wires and no logic
+    pub fn with_inputs(wires: &[usize]) -> Self {
+        let mut aig = Aig::new();
+        for &wire in wires {
+            aig.add_input(wire);
+        }
+        aig
+    }
+
+    pub fn add_input(&mut self, wire: usize) -> Lit {
+        self.inputs.push(wire);
+        self.nodes.push(Node::Input(self.inputs.len() - 1));
+        self.levels.push(0);
+        Lit::new(self.nodes.len() - 1, false)
+    }
+
+    /// Edge to the `index`-th input
+    pub fn input(&self, index: usize) -> Lit {
+        let node = self
+            .nodes
+            .iter()
+            .position(|n| *n == Node::Input(index))
+            .expect("input exists");
+        Lit::new(node, false)
+    }
+
+    pub fn level(&self, lit: Lit) -> usize {
+        self.levels[lit.node()]
+    }
+
+    fn intern(&mut self, node: Node) -> Lit {
+        if let Some(&index) = self.strash.get(&node) {
+            return Lit::new(index, false);
+        }
+        let level = match node {
+            Node::And(a, b) | Node::Xor(a, b) => 1 + self.level(a).max(self.level(b)),
+            _ => 0,
+        };
+        self.nodes.push(node);
+        self.levels.push(level);
+        self.strash.insert(node, self.nodes.len() - 1);
+        Lit::new(self.nodes.len() - 1, false)
+    }
+
+    pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        if a == Lit::FALSE || b == Lit::FALSE || a == !b {
+            return Lit::FALSE;
+        }
+        if a == Lit::TRUE || a == b {
+            return b;
+        }
+        if b == Lit::TRUE {
+            return a;
+        }
+        self.intern(Node::And(a.min(b), a.max(b)))
+    }
+
+    pub fn or(&mut self, a: Lit, b: Lit) -> Lit {
+        !self.and(!a, !b)
+    }
+
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        // Complements are pulled out of XOR inputs onto its output
+        let flip = a.is_complement() ^ b.is_complement();
+        let (a, b) = (a.regular(), b.regular());
+        let out = if a == b {
+            Lit::FALSE
+        } else if a == Lit::FALSE {
+            b
+        } else if b == Lit::FALSE {
+            a
+        } else {
+            self.intern(Node::Xor(a.min(b), a.max(b)))
+        };
+        if flip { !out } else { out }
+    }
+
+    /// Build a graph from a circuit. Wires read without being driven become inputs.
+    pub fn from_circuit(circuit: &Circuit) -> Self {
+        let mut aig = Aig::new();
+        let inputs: Vec<Lit> = circuit.input_wires.iter().map(|&wire| aig.add_input(wire)).collect();
+        aig.outputs = aig.add_circuit(circuit, &inputs);
+        aig
+    }
+
+    /// Add the logic of `circuit` with its input wires driven by `inputs` (in
+    /// `input_wires` order) and return its outputs; undriven wires become new inputs
+    pub fn add_circuit(&mut self, circuit: &Circuit, inputs: &[Lit]) -> Vec<Lit> {
+        let mut lits: HashMap<usize, Lit> = circuit.input_wires.iter().copied().zip(inputs.iter().copied()).collect();
+        let lit_of = |aig: &mut Aig, lits: &mut HashMap<usize, Lit>, wire: usize| {
+            *lits.entry(wire).or_insert_with(|| aig.add_input(wire))
+        };
+        for gate in &circuit.gates {
+            let left = lit_of(self, &mut lits, gate.left_wire);
+            let right = gate.right_wire.map(|w| lit_of(self, &mut lits, w));
+            let out = match (gate.gate_type, right) {
+                (GateType::Not, _) => !left,
+                (GateType::And, Some(right)) => self.and(left, right),
+                (GateType::Or, Some(right)) => self.or(left, right),
+                (GateType::Xor, Some(right)) => self.xor(left, right),
+                (_, None) => panic!("binary gate driving wire {} has no right wire", gate.output_wire),
+            };
+            lits.insert(gate.output_wire, out);
+        }
+        circuit.output_wires.iter().map(|&w| lit_of(self, &mut lits, w)).collect()
+    }
+
+    /// Nodes reachable from the outputs
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = self.outputs.iter().map(|l| l.node()).collect();
+        while let Some(node) = stack.pop() {
+            if std::mem::replace(&mut seen[node], true) {
+                continue;
+            }
+            if let Node::And(a, b) | Node::Xor(a, b) = self.nodes[node] {
+                stack.push(a.node());
+                stack.push(b.node());
+            }
+        }
+        seen
+    }
+
+    /// Number of reachable references to every node (outputs count as references)
+    pub fn fanouts(&self) -> Vec<usize> {
+        let reachable = self.reachable();
+        let mut fanout = vec![0; self.nodes.len()];
+        for (node, n) in self.nodes.iter().enumerate() {
+            if let (true, Node::And(a, b) | Node::Xor(a, b)) = (reachable[node], n) {
+                fanout[a.node()] += 1;
+                fanout[b.node()] += 1;
+            }
+        }
+        for out in &self.outputs {
+            fanout[out.node()] += 1;
+        }
+        fanout
+    }
+
+    /// Reachable AND and XOR node counts
+    pub fn counts(&self) -> (usize, usize) {
+        let reachable = self.reachable();
+        let mut counts = (0, 0);
+        for (_, node) in self.nodes.iter().enumerate().filter(|(i, _)| reachable[*i]) {
+            match node {
+                Node::And(..) => counts.0 += 1,
+                Node::Xor(..) => counts.1 += 1,
+                _ => {}
+            }
+        }
+        counts
+    }
+
+    /// Tseitin clauses asserting `root` over the cone of `root`, with one solver
+    /// variable per node (variable 0 is the constant)
+    pub fn clauses(&self, root: Lit) -> Vec<Vec<Literal>> {
+        let lit = |l: Lit| Literal::new(l.node(), !l.is_complement());
+        let mut cone = vec![false; self.nodes.len()];
+        let mut stack = vec![root.node()];
+        while let Some(node) = stack.pop() {
+            if std::mem::replace(&mut cone[node], true) {
+                continue;
+            }
+            if let Node::And(a, b) | Node::Xor(a, b) = self.nodes[node] {
+                stack.extend([a.node(), b.node()]);
+            }
+        }
+        let mut clauses = vec![vec![Literal::new(0, false)], vec![lit(root)]];
+        for (i, node) in self.nodes.iter().enumerate().filter(|(i, _)| cone[*i]) {
+            let t = Literal::new(i, true);
+            match *node {
+                Node::And(a, b) => {
+                    let (a, b) = (lit(a), lit(b));
+                    clauses.extend([vec![!t, a], vec![!t, b], vec![t, !a, !b]]);
+                }
+                Node::Xor(a, b) => {
+                    let (a, b) = (lit(a), lit(b));
+                    clauses.extend([vec![!t, a, b], vec![!t, !a, !b], vec![t, !a, b], vec![t, a, !b]]);
+                }
+                _ => {}
+            }
+        }
+        clauses
+    }
+
+    /// Input values (in input order) of a solver model of `clauses`
+    pub fn input_values(&self, model: &[bool]) -> Vec<bool> {
+        let mut values = vec![false; self.inputs.len()];
+        for (node, n) in self.nodes.iter().enumerate() {
+            if let Node::Input(k) = n {
+                values[*k] = model[node];
+            }
+        }
+        values
+    }
+
+    /// Evaluate the outputs for input values given in input order
+    pub fn eval(&self, inputs: &[bool]) -> Vec<bool> {
+        let mut values = vec![false; self.nodes.len()];
+        let lit = |values: &[bool], l: Lit| values[l.node()] ^ l.is_complement();
+        for (i, node) in self.nodes.iter().enumerate() {
+            values[i] = match *node {
+                Node::Const => false,
+                Node::Input(k) => inputs[k],
+                Node::And(a, b) => lit(&values, a) && lit(&values, b),
+                Node::Xor(a, b) => lit(&values, a) ^ lit(&values, b),
+            };
+        }
+        self.outputs.iter().map(|&l| lit(&values, l)).collect()
+    }
+
+    /// Rebuild every AND and XOR tree (a node together with its single-fanout,
+    /// uncomplemented fanins of the same kind) as a tree balanced by logic level
+    pub fn balance(&self) -> Aig {
+        let fanout = self.fanouts();
+        let mut new = Aig::with_inputs(&self.inputs);
+        let mut map: Vec<Lit> = vec![Lit::FALSE; self.nodes.len()];
+        let mapped = |map: &[Lit], l: Lit| if l.is_complement() { !map[l.node()] } else { map[l.node()] };
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            map[i] = match *node {
+                Node::Const => Lit::FALSE,
+                Node::Input(k) => new.input(k),
+                Node::And(..) | Node::Xor(..) => {
+                    let is_and = matches!(node, Node::And(..));
+                    let mut leaves = Vec::new();
+                    let mut stack = vec![Lit::new(i, false)];
+                    while let Some(l) = stack.pop() {
+                        let expandable = l.node() == i
+                            || (!l.is_complement()
+                                && fanout[l.node()] == 1
+                                && matches!(
+                                    (is_and, self.nodes[l.node()]),
+                                    (true, Node::And(..)) | (false, Node::Xor(..))
+                                ));
+                        match self.nodes[l.node()] {
+                            Node::And(a, b) | Node::Xor(a, b) if expandable => {
+                                stack.push(a);
+                                stack.push(b);
+                            }
+                            _ => leaves.push(mapped(&map, l)),
+                        }
+                    }
+                    let mut heap: BinaryHeap<Reverse<(usize, Lit)>> =
+                        leaves.into_iter().map(|l| Reverse((new.level(l), l))).collect();
+                    while heap.len() > 1 {
+                        let Reverse((_, a)) = heap.pop().expect("two leaves");
+                        let Reverse((_, b)) = heap.pop().expect("two leaves");
+                        let l = if is_and { new.and(a, b) } else { new.xor(a, b) };
+                        heap.push(Reverse((new.level(l), l)));
+                    }
+                    heap.pop().map(|Reverse((_, l))| l).expect("at least one leaf")
+                }
+            };
+        }
+        new.outputs = self.outputs.iter().map(|&l| mapped(&map, l)).collect();
+        new
+    }
+
+    /// Emit the reachable graph as a circuit. Input wires keep their ids; an AND is
+    /// emitted as an OR of the complements (De Morgan) when that needs fewer NOT gates,
+    /// so balanced OR trees stay OR trees; remaining complements become NOT gates.
+    pub fn to_circuit(&self) -> Circuit {
+        let reachable = self.reachable();
+        let mut emitter = Emitter {
+            gates: Vec::new(),
+            next_wire: self.inputs.iter().max().map_or(0, |m| m + 1),
+            real: HashMap::new(),
+            negated: HashMap::new(),
+        };
+
+        for (i, node) in self.nodes.iter().enumerate().filter(|(i, _)| reachable[*i]) {
+            let realized = match *node {
+                Node::Input(k) => (self.inputs[k], false),
+                Node::Const => {
+                    let wire = *self.inputs.first().expect("constant circuit needs an input wire");
+                    (emitter.emit(GateType::Xor, wire, Some(wire)), false)
+                }
+                Node::And(a, b) if emitter.nots(!a, !b) < emitter.nots(a, b) => {
+                    let (left, right) = (emitter.wire(!a), emitter.wire(!b));
+                    (emitter.emit(GateType::Or, left, Some(right)), true)
+                }
+                Node::And(a, b) | Node::Xor(a, b) => {
+                    let gate_type = if matches!(node, Node::And(..)) { GateType::And } else { GateType::Xor };
+                    let (left, right) = (emitter.wire(a), emitter.wire(b));
+                    (emitter.emit(gate_type, left, Some(right)), false)
+                }
+            };
+            emitter.real.insert(i, realized);
+        }
+        let output_wires = self.outputs.iter().map(|&l| emitter.wire(l)).collect();
+
+        Circuit::new(emitter.gates, self.inputs.clone(), output_wires)
+    }
+}
+
+/// Gate list under construction in `Aig::to_circuit`
+struct Emitter {
+    gates: Vec<Gate>,
+    next_wire: usize,
+    /// Node -> (wire, whether the wire carries the node's complement)
+    real: HashMap<usize, (usize, bool)>,
+    /// Wire -> wire of its NOT gate
+    negated: HashMap<usize, usize>,
+}
+
+impl Emitter {
+    fn emit(&mut self, gate_type: GateType, left: usize, right: Option<usize>) -> usize {
+        let output = self.next_wire;
+        self.next_wire += 1;
+        self.gates.push(Gate { gate_type, left_wire: left, right_wire: right, output_wire: output });
+        output
+    }
+
+    /// Whether a wire carrying `lit` exists without a new NOT gate
+    fn available(&self, lit: Lit) -> bool {
+        let (wire, flipped) = self.real[&lit.node()];
+        flipped == lit.is_complement() || self.negated.contains_key(&wire)
+    }
+
+    /// NOT gates needed to get wires for both `a` and `b`
+    fn nots(&self, a: Lit, b: Lit) -> usize {
+        !self.available(a) as usize + !self.available(b) as usize
+    }
+
+    /// Wire carrying `lit`, adding a NOT gate when only its complement exists
+    fn wire(&mut self, lit: Lit) -> usize {
+        let (wire, flipped) = self.real[&lit.node()];
+        if flipped == lit.is_complement() {
+            return wire;
+        }
+        if let Some(&negated) = self.negated.get(&wire) {
+            return negated;
+        }
+        let negated = self.emit(GateType::Not, wire, None);
+        self.negated.insert(wire, negated);
+        negated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strash_and_simplification() {
+        let mut aig = Aig::with_inputs(&[0, 1]);
+        let (a, b) = (aig.input(0), aig.input(1));
+        assert_eq!(aig.and(a, b), aig.and(b, a));
+        assert_eq!(aig.and(a, !a), Lit::FALSE);
+        assert_eq!(aig.xor(a, a), Lit::FALSE);
+        assert_eq!(aig.xor(!a, b), !aig.xor(a, b));
+        assert_eq!(aig.or(a, Lit::TRUE), Lit::TRUE);
+    }
+
+    #[test]
+    fn test_balance_chain_and_round_trip() {
+        // ((((x0 & x1) & x2) & x3) & x4) & x5 | (x0 ^ x1 ^ x2 ^ x3)
+        let mut gates = vec![Gate::binary(GateType::And, 0, 1, 6)];
+        for (i, input) in (2..6).enumerate() {
+            gates.push(Gate::binary(GateType::And, 6 + i, input, 7 + i));
+        }
+        gates.push(Gate::binary(GateType::Xor, 0, 1, 20));
+        gates.push(Gate::binary(GateType::Xor, 20, 2, 21));
+        gates.push(Gate::binary(GateType::Xor, 21, 3, 22));
+        gates.push(Gate::binary(GateType::Or, 10, 22, 23));
+        let circuit = Circuit::new(gates, (0..6).collect(), vec![23]);
+
+        let aig = Aig::from_circuit(&circuit);
+        let balanced = aig.balance();
+        let optimized = balanced.to_circuit();
+        assert_eq!(optimized.input_wires, circuit.input_wires);
+        assert!(optimized.depth < circuit.depth, "{} < {}", optimized.depth, circuit.depth);
+        assert_eq!(optimized.gates.len(), circuit.gates.len());
+
+        let round_trip = Aig::from_circuit(&optimized);
+        for x in 0..64u32 {
+            let inputs: Vec<bool> = (0..6).map(|i| x >> i & 1 == 1).collect();
+            assert_eq!(aig.eval(&inputs), round_trip.eval(&inputs));
+        }
+    }
+
+    #[test]
+    fn test_pipeline_keeps_shallower_rebalanced_chain() {
+        // 20 products ORed in a chain; the balanced tree stays an OR tree without NOT gates
+        let terms: Vec<String> = (0..20).map(|i| format!(r#"("a{}" and "b{}")"#, i, i)).collect();
+        let circuit = crate::psg::polylithic_syntax_gen(&terms.join(" or ")).unwrap();
+        let report = circuit.multilevel.unwrap();
+        assert_eq!(report.depth_before, 20);
+        assert!(report.depth_after <= 8, "depth {} -> {}", report.depth_before, report.depth_after);
+        assert_eq!(report.gates_after, report.gates_before);
+        assert_eq!(circuit.circuit.and_count(), 39);
+    }
+
+    #[test]
+    fn test_multilevel_never_grows_circuits() {
+        let chain = |op: &str, n: usize| (0..n).map(|i| format!(r#""v{}""#, i)).collect::<Vec<_>>().join(op);
+        let statements = [
+            chain(" or ", 200),
+            chain(" and ", 200),
+            chain(" xor ", 50),
+            r#"("a" or "b") and ("a" or "c") and not ("b" and "d")"#.to_string(),
+            r#"if "a" and not "b" then "c" or ("d" xor "e")"#.to_string(),
+        ];
+        for statement in &statements {
+            let report = crate::psg::polylithic_syntax_gen(statement).unwrap().multilevel.unwrap();
+            assert!(report.gates_after <= report.gates_before, "{}: {:?}", statement, report);
+            if report.gates_after == report.gates_before {
+                assert!(report.depth_after <= report.depth_before, "{}: {:?}", statement, report);
+            }
+        }
+    }
+}
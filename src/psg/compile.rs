@@ -0,0 +1,359 @@
+//! Compiler from the PSG syntax tree to `partitioned_gc::Circuit`
+//!
+//! Every distinct (hashed) variable gets one input wire, numbered from 0 in order
+//! of first appearance; gates follow in topological order, each driving a fresh
+//! wire. Operators without a gate of their own are lowered to AND/OR/XOR/NOT:
+//!
+//! - `a -> b`  = `!a || b`
+//! - `a <- b`  = `a || !b`
+//! - `a <-> b` = `!(a ^ b)`
+//!
+//! Constants are derived from the first input wire: `false = x ^ x`, `true = !(x ^ x)`.
+//! Identical gates are emitted once (structural hashing), so a literal such as
+//! `!a` used in several product terms costs a single NOT gate.
+//!
+//! A cardinality constraint `#(x1, ..., xn) op k` adds its operands up in an adder
+//! tree (full adders `s = a ^ b ^ c`, `c' = c ^ ((a ^ c) & (b ^ c))`, one AND each,
+//! compressing a column of equal weight at a time) and compares the binary count
+//! with `k`, one AND or OR per count bit. That is about `n` AND gates, against the
+//! `C(n, k)` products of a sum of products. Gates no output depends on are dropped.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{Comparison, Expr, LogicExpression, Operator, Variable};
+use crate::partitioned_gc::{Circuit, Gate, GateType};
+
+/// A compiled statement: the circuit and where each hashed variable enters it
+#[derive(Debug, Clone)]
+pub struct CompiledCircuit {
+    pub circuit: Circuit,
+    /// Hashed variable -> input wire
+    pub wire_map: HashMap<String, usize>,
+}
+
+struct Compiler<'a> {
+    variables: &'a [Variable],
+    gates: Vec<Gate>,
+    next_wire: usize,
+    /// Output wire of every emitted gate, keyed by its type and inputs
+    emitted: HashMap<(GateType, usize, Option<usize>), usize>,
+}
+
+impl Compiler<'_> {
+    fn input_wire(&self, expr: &Expr) -> Option<usize> {
+        match expr {
+            Expr::Var(var) => self.variables.iter().position(|v| v == var),
+            _ => None,
+        }
+    }
+
+    fn emit(&mut self, gate_type: GateType, left: usize, right: Option<usize>) -> usize {
+        if let Some(&output) = self.emitted.get(&(gate_type, left, right)) {
+            return output;
+        }
+        let output = self.next_wire;
+        self.emitted.insert((gate_type, left, right), output);
+        self.next_wire += 1;
+        self.gates.push(Gate { gate_type, left_wire: left, right_wire: right, output_wire: output });
+        output
+    }
+
+    fn not(&mut self, wire: usize) -> usize {
+        self.emit(GateType::Not, wire, None)
+    }
+
+    fn constant(&mut self, value: bool) -> usize {
+        assert!(!self.variables.is_empty(), "constant circuit needs an input wire");
+        let zero = self.emit(GateType::Xor, 0, Some(0));
+        if value { self.not(zero) } else { zero }
+    }
+
+    /// Binary number of true wires, least significant bit first
+    fn count(&mut self, wires: &[usize]) -> Vec<usize> {
+        let mut columns: Vec<VecDeque<usize>> = vec![wires.iter().copied().collect()];
+        let mut bits = Vec::new();
+        let mut weight = 0;
+        while weight < columns.len() {
+            while columns[weight].len() > 1 {
+                let a = columns[weight].pop_front().expect("column has two wires");
+                let b = columns[weight].pop_front().expect("column has two wires");
+                let (sum, carry) = match columns[weight].pop_front() {
+                    Some(c) => {
+                        let ac = self.emit(GateType::Xor, a, Some(c));
+                        let bc = self.emit(GateType::Xor, b, Some(c));
+                        let sum = self.emit(GateType::Xor, ac, Some(b));
+                        let both = self.emit(GateType::And, ac, Some(bc));
+                        (sum, self.emit(GateType::Xor, both, Some(c)))
+                    }
+                    None => (self.emit(GateType::Xor, a, Some(b)), self.emit(GateType::And, a, Some(b))),
+                };
+                columns[weight].push_back(sum);
+                if columns.len() == weight + 1 {
+                    columns.push(VecDeque::new());
+                }
+                columns[weight + 1].push_back(carry);
+            }
+            bits.extend(columns[weight].front().copied());
+            weight += 1;
+        }
+        bits
+    }
+
+    /// Whether the binary number `bits` is at least `k` (which fits in `bits`):
+    /// scanning up from the least significant bit, `ge = bit && ge` where `k` has
+    /// a one and `ge = bit || ge` where it has a zero
+    fn at_least(&mut self, bits: &[usize], k: usize) -> usize {
+        let mut ge: Option<usize> = None; // None: true
+        for (i, &bit) in bits.iter().enumerate() {
+            ge = match (k >> i & 1 == 1, ge) {
+                (true, None) => Some(bit),
+                (true, Some(ge)) => Some(self.emit(GateType::And, bit, Some(ge))),
+                (false, None) => None,
+                (false, Some(ge)) => Some(self.emit(GateType::Or, bit, Some(ge))),
+            };
+        }
+        ge.expect("k is positive")
+    }
+
+    fn threshold(&mut self, op: Comparison, k: usize, wires: &[usize]) -> usize {
+        let n = wires.len();
+        let holds = |count: usize| op.eval(count as u64, k as u64);
+        if (0..=n).all(|count| holds(count) == holds(0)) {
+            return self.constant(holds(0));
+        }
+        let bits = self.count(wires);
+        match op {
+            Comparison::Ge => self.at_least(&bits, k),
+            Comparison::Gt => self.at_least(&bits, k + 1),
+            Comparison::Lt => {
+                let ge = self.at_least(&bits, k);
+                self.not(ge)
+            }
+            Comparison::Le => {
+                let gt = self.at_least(&bits, k + 1);
+                self.not(gt)
+            }
+            Comparison::Eq | Comparison::Ne => {
+                let literals: Vec<usize> = bits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &bit)| if k >> i & 1 == 1 { bit } else { self.not(bit) })
+                    .collect();
+                let equal = literals
+                    .into_iter()
+                    .reduce(|acc, literal| self.emit(GateType::And, acc, Some(literal)))
+                    .expect("count has a bit");
+                if op == Comparison::Eq { equal } else { self.not(equal) }
+            }
+        }
+    }
+
+    fn compile(&mut self, expr: &Expr) -> usize {
+        match expr {
+            Expr::Var(_) => self.input_wire(expr).expect("variable collected by LogicExpression"),
+            Expr::Const(value) => self.constant(*value),
+            Expr::Not(inner) => {
+                let wire = self.compile(inner);
+                self.not(wire)
+            }
+            Expr::Binary { op, lhs, rhs } => {
+                let left = self.compile(lhs);
+                let right = self.compile(rhs);
+                match op {
+                    Operator::And => self.emit(GateType::And, left, Some(right)),
+                    Operator::Or => self.emit(GateType::Or, left, Some(right)),
+                    Operator::Xor => self.emit(GateType::Xor, left, Some(right)),
+                    Operator::Implies => {
+                        let not_left = self.not(left);
+                        self.emit(GateType::Or, not_left, Some(right))
+                    }
+                    Operator::Converse => {
+                        let not_right = self.not(right);
+                        self.emit(GateType::Or, left, Some(not_right))
+                    }
+                    Operator::Iff => {
+                        let xor = self.emit(GateType::Xor, left, Some(right));
+                        self.not(xor)
+                    }
+                    Operator::Not => unreachable!("negation is parsed as Expr::Not"),
+                }
+            }
+            Expr::Threshold { op, k, operands } => {
+                let wires: Vec<usize> = operands.iter().map(|e| self.compile(e)).collect();
+                self.threshold(*op, *k, &wires)
+            }
+        }
+    }
+
+    /// Drop the gates no output depends on, renumbering the rest after the inputs
+    fn prune(&mut self, outputs: &[usize], inputs: usize) -> Vec<usize> {
+        let mut live = vec![false; self.next_wire];
+        for &output in outputs {
+            live[output] = true;
+        }
+        for gate in self.gates.iter().rev() {
+            if live[gate.output_wire] {
+                for wire in gate.input_wires() {
+                    live[wire] = true;
+                }
+            }
+        }
+        self.gates.retain(|gate| live[gate.output_wire]);
+        let mut renumbered: Vec<usize> = (0..inputs).collect();
+        renumbered.resize(self.next_wire, usize::MAX);
+        for (i, gate) in self.gates.iter_mut().enumerate() {
+            renumbered[gate.output_wire] = inputs + i;
+            gate.left_wire = renumbered[gate.left_wire];
+            gate.right_wire = gate.right_wire.map(|w| renumbered[w]);
+            gate.output_wire = inputs + i;
+        }
+        outputs.iter().map(|&output| renumbered[output]).collect()
+    }
+}
+
+/// Give every output its own wire: an output wire listed again (outputs of equal
+/// functions, merged by structural hashing) is copied through two NOT gates,
+/// which are free to garble
+pub(crate) fn distinct_outputs(mut circuit: Circuit) -> Circuit {
+    let mut next_wire = circuit.input_wires.iter().chain(circuit.gates.iter().map(|g| &g.output_wire))
+        .max().map_or(0, |&w| w + 1);
+    let mut seen = HashSet::new();
+    for i in 0..circuit.output_wires.len() {
+        let wire = circuit.output_wires[i];
+        if !seen.insert(wire) {
+            circuit.gates.push(Gate::not(wire, next_wire));
+            circuit.gates.push(Gate::not(next_wire, next_wire + 1));
+            circuit.output_wires[i] = next_wire + 1;
+            next_wire += 2;
+        }
+    }
+    Circuit::new(circuit.gates, circuit.input_wires, circuit.output_wires)
+}
+
+/// Compile a parsed statement whose variables hash to `hashed` (same order as
+/// `expression.variables`)
+pub fn compile(expression: &LogicExpression, hashed: &[String]) -> CompiledCircuit {
+    compile_outputs(&expression.variables, std::slice::from_ref(&expression.root), hashed)
+}
+
+/// Compile several syntax trees over the same `variables` into one circuit with an
+/// output wire per tree, in order; gates common to several trees are shared
+pub fn compile_outputs(variables: &[Variable], roots: &[Expr], hashed: &[String]) -> CompiledCircuit {
+    let inputs = variables.len();
+    let mut compiler = Compiler {
+        variables,
+        gates: Vec::new(),
+        next_wire: inputs,
+        emitted: HashMap::new(),
+    };
+    let outputs: Vec<usize> = roots.iter().map(|root| compiler.compile(root)).collect();
+    let output_wires = compiler.prune(&outputs, inputs);
+
+    CompiledCircuit {
+        circuit: Circuit::new(compiler.gates, (0..inputs).collect(), output_wires),
+        wire_map: hashed.iter().cloned().zip(0..inputs).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partitioned_gc::PartitionedGCScheme;
+    use crate::psg::{lexer, parse_precedence, parser};
+
+    fn compile_str(input: &str) -> (LogicExpression, CompiledCircuit) {
+        let expression = parse_precedence(input).unwrap();
+        let hashed: Vec<String> = expression.variables.iter().map(|v| format!("h({})", v.name)).collect();
+        let compiled = compile(&expression, &hashed);
+        (expression, compiled)
+    }
+
+    #[test]
+    fn test_compile_car_example() {
+        let (expression, compiled) = compile_str(
+            r#"The car only starts if the "start" button is pressed and the "brake" pedal is pressed"#,
+        );
+        let circuit = &compiled.circuit;
+        assert_eq!(circuit.input_wires, vec![0, 1, 2]);
+        assert_eq!(compiled.wire_map["h(car starts)"], 0);
+        assert_eq!(compiled.wire_map["h(brake)"], 2);
+        // AND(start, brake), NOT(car starts), OR
+        assert_eq!(circuit.gates.len(), 3);
+        assert_eq!(circuit.depth, 2);
+        assert_eq!(circuit.width, 2);
+        assert_eq!(circuit.output_wires, vec![5]);
+        assert_eq!(expression.variables.len(), 3);
+    }
+
+    #[test]
+    fn test_compile_integer_comparisons() {
+        use crate::psg::{PsgOptions, VarType};
+        let input = r#"the "speed" is greater than 60 and the "gear" equals 2"#;
+        let types = [("speed", 8), ("gear", 4)].map(|(n, width)| (n.to_string(), VarType::UInt { width })).into();
+        let options = PsgOptions { types, ..PsgOptions::default() };
+        let tokens = lexer::tokenize(input, &options.lexicon).unwrap();
+        let expression = parser::parse_with(&tokens, input.len(), &options).unwrap();
+        let hashed: Vec<String> = expression.variables.iter().map(|v| format!("h({})", v)).collect();
+        let compiled = compile(&expression, &hashed);
+        // Both integers enter as contiguous bit vectors, least significant bit first
+        assert_eq!(compiled.circuit.input_wires, (0..12).collect::<Vec<_>>());
+        assert_eq!(compiled.wire_map["h(speed[0])"], 0);
+        assert_eq!(compiled.wire_map["h(gear[0])"], 8);
+
+        let gc = PartitionedGCScheme::garble_circuit(&compiled.circuit).unwrap();
+        for (speed, gear) in [(61u32, 2u32), (60, 2), (255, 2), (61, 3), (0, 0), (100, 10)] {
+            let inputs: Vec<u8> = (0..8).map(|i| (speed >> i & 1) as u8).chain((0..4).map(|i| (gear >> i & 1) as u8)).collect();
+            let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+            PartitionedGCScheme::evaluate_garbled(&gc, &mut labels);
+            let expected = (speed > 60 && gear == 2) as u8;
+            assert_eq!(PartitionedGCScheme::decode_outputs(&gc, &labels), Some(vec![expected]), "{} {}", speed, gear);
+        }
+    }
+
+    #[test]
+    fn test_compile_cardinality_constraints() {
+        use crate::psg::{Aig, Comparison, Variable};
+        let variables: Vec<Variable> = (0..6).map(|i| Variable::new(format!("x{}", i))).collect();
+        let hashed: Vec<String> = variables.iter().map(|v| v.name.clone()).collect();
+        let operands: Vec<Expr> = variables.iter().cloned().map(Expr::Var).collect();
+        let ops = [Comparison::Lt, Comparison::Le, Comparison::Eq, Comparison::Ne, Comparison::Gt, Comparison::Ge];
+        for op in ops {
+            for k in 0..=7 {
+                let root = Expr::Threshold { op, k, operands: operands.clone() };
+                let expression = LogicExpression { variables: variables.clone(), root, consequent: None };
+                let circuit = compile(&expression, &hashed).circuit;
+                // 3 full adders and a half adder, then at most one gate per count bit
+                assert!(circuit.and_count() <= 4 + 3, "{} {}: {} AND gates", op, k, circuit.and_count());
+                let aig = Aig::from_circuit(&circuit);
+                for x in 0..64u32 {
+                    let values: Vec<bool> = (0..6).map(|i| x >> i & 1 == 1).collect();
+                    assert_eq!(aig.eval(&values), vec![op.eval(x.count_ones() as u64, k as u64)], "{} {} {}", op, k, x);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_compiled_circuit_matches_expression() {
+        for input in [
+            r#"if not ("a" xor "b") then "c" or "a""#,
+            r#""a" if and only if not "b" and ("c" or "d")"#,
+            r#""a" is set unless the "b" is not set"#,
+            r#""door" opens if at least 2 of "badge", "pin", not "face" or "card" are valid"#,
+        ] {
+            let (expression, compiled) = compile_str(input);
+            let gc = PartitionedGCScheme::garble_circuit(&compiled.circuit).unwrap();
+            let n = expression.variables.len();
+            for x in 0..(1u32 << n) {
+                let inputs: Vec<u8> = (0..n).map(|i| ((x >> i) & 1) as u8).collect();
+                let expected = expression
+                    .root
+                    .eval(&|var| inputs[expression.variables.iter().position(|v| v == var).unwrap()] == 1);
+
+                let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+                PartitionedGCScheme::evaluate_garbled(&gc, &mut labels);
+                assert_eq!(PartitionedGCScheme::decode_outputs(&gc, &labels), Some(vec![expected as u8]));
+            }
+        }
+    }
+}
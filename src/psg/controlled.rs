@@ -0,0 +1,347 @@
+//! Controlled English: unquoted noun phrases and references
+//!
+//! With a `ControlledGrammar` in `PsgOptions::grammar`, variables need no quotes.
+//! A noun phrase is a determiner followed by words up to its predicate (`the
+//! brake pedal is pressed`), or up to an operator, connective, comparison or
+//! punctuation (`the brake pedal and ...`). Its words, without stop words, name
+//! the variable. A clause without any variable becomes one variable named after
+//! its words (`The car only starts` is `car starts`), as in the parser. Recognized
+//! phrases are rewritten into `Variable` tokens, so the parser sees them like
+//! quoted variables.
+//!
+//! Every variable mentioned is remembered with the words describing it:
+//! `the "start" button` is `start`, described as `start button`. A later noun
+//! phrase refers to an earlier variable when it ends in the same head noun and
+//! all its words describe that variable. So `the button` is `start` and `that
+//! pedal` is `brake pedal`, and the statement has one variable for each. A
+//! demonstrative (`that pedal`) must refer to an earlier variable. A pronoun
+//! (`it`) stands for the most recently mentioned one, and stays a plain word
+//! when nothing was mentioned yet (`if it rains`).
+//!
+//! The predicate of a phrase is discarded, so a reference always means the same
+//! proposition as the variable it refers to: after `the brake pedal is pressed`,
+//! `that pedal is released` is `brake pedal` again, not its negation. Only `not`
+//! and negated predicates (`is not`, `isn't`) negate it.
+
+use super::lexer::{self, Span, Token, TokenKind};
+use super::lexicon::Lexicon;
+use super::{Operator, ParseError, PsgOptions};
+
+/// Word classes of a controlled-English grammar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlledGrammar {
+    /// Words opening a noun phrase that introduces a variable or refers to one (`the`, `a`)
+    pub determiners: Vec<String>,
+    /// Words opening a noun phrase that must refer to an earlier variable (`that`, `this`)
+    pub demonstratives: Vec<String>,
+    /// Words standing for the most recently mentioned variable (`it`)
+    pub pronouns: Vec<String>,
+    /// Words ending a noun phrase and starting its predicate (`is`, `are`); words
+    /// with a negative suffix of the lexicon (`isn't`) end it too
+    pub predicates: Vec<String>,
+}
+
+impl ControlledGrammar {
+    /// Grammar without any word, to be filled in for another language
+    pub fn new() -> Self {
+        ControlledGrammar { determiners: Vec::new(), demonstratives: Vec::new(), pronouns: Vec::new(), predicates: Vec::new() }
+    }
+
+    pub fn english() -> Self {
+        let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect();
+        ControlledGrammar {
+            determiners: words(&["the", "a", "an"]),
+            demonstratives: words(&["that", "this", "said"]),
+            pronouns: words(&["it"]),
+            predicates: words(&[
+                "is", "are", "was", "were", "be", "been", "has", "have", "had", "does", "do", "must", "can", "will",
+            ]),
+        }
+    }
+}
+
+impl Default for ControlledGrammar {
+    fn default() -> Self {
+        ControlledGrammar::new()
+    }
+}
+
+/// A variable mentioned so far and the words describing it
+#[derive(Debug, Clone)]
+struct Referent {
+    name: String,
+    /// Lowercased, the head noun last
+    words: Vec<String>,
+}
+
+/// Variables mentioned so far, the most recent last; shared by the sentences of
+/// a rule set so references reach across sentences
+#[derive(Debug, Clone, Default)]
+pub(super) struct Discourse {
+    referents: Vec<Referent>,
+}
+
+impl Discourse {
+    fn mention(&mut self, name: &str, words: &[String]) {
+        let mut referent = match self.referents.iter().position(|r| r.name == name) {
+            Some(i) => self.referents.remove(i),
+            None => Referent { name: name.to_string(), words: Vec::new() },
+        };
+        for word in words {
+            if !referent.words.contains(word) {
+                referent.words.push(word.clone());
+            }
+        }
+        self.referents.push(referent);
+    }
+
+    /// Most recent variable with head noun `words.last()` described by all `words`
+    fn resolve(&self, words: &[String]) -> Option<String> {
+        let head = words.last()?;
+        self.referents
+            .iter()
+            .rev()
+            .find(|r| r.words.last() == Some(head) && words.iter().all(|w| r.words.contains(w)))
+            .map(|r| r.name.clone())
+    }
+}
+
+struct Recognizer<'a> {
+    grammar: &'a ControlledGrammar,
+    lexicon: &'a Lexicon,
+}
+
+impl Recognizer<'_> {
+    fn lowered(token: &Token) -> Option<String> {
+        match &token.kind {
+            TokenKind::Word(word) => Some(word.to_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn is_predicate(&self, word: &str) -> bool {
+        self.grammar.predicates.iter().any(|p| p == word)
+            || self.lexicon.negative_suffixes.iter().any(|suffix| word.ends_with(suffix.as_str()))
+    }
+
+    fn opens_phrase(&self, word: &str) -> bool {
+        self.grammar.determiners.iter().chain(&self.grammar.demonstratives).any(|d| d == word)
+    }
+
+    /// Whether a word may be part of a noun phrase
+    fn is_noun_word(&self, word: &str) -> bool {
+        !self.is_predicate(word) && !self.opens_phrase(word) && !self.grammar.pronouns.iter().any(|p| p == word)
+    }
+
+    /// Words describing a quoted variable: its name and the noun words after it
+    fn description(&self, name: &str, after: &[Token]) -> Vec<String> {
+        let mut words: Vec<String> = name.split_whitespace().map(str::to_lowercase).collect();
+        words.extend(
+            after
+                .iter()
+                .map_while(Self::lowered)
+                .take_while(|w| self.is_noun_word(w))
+                .filter(|w| !self.lexicon.is_stop_word(w)),
+        );
+        words
+    }
+
+    fn variable(name: String, span: Span) -> Token {
+        Token { kind: TokenKind::Variable(name), span }
+    }
+
+    /// Rewrite the noun phrases and pronouns of one clause
+    fn clause(&self, clause: &[Token], discourse: &mut Discourse) -> Result<Vec<Token>, ParseError> {
+        let mut out = Vec::with_capacity(clause.len());
+        let mut i = 0;
+        while i < clause.len() {
+            let token = &clause[i];
+            if let TokenKind::Variable(name) = &token.kind {
+                discourse.mention(name, &self.description(name, &clause[i + 1..]));
+                out.push(token.clone());
+                i += 1;
+                continue;
+            }
+            let Some(word) = Self::lowered(token) else {
+                out.push(token.clone());
+                i += 1;
+                continue;
+            };
+
+            if self.grammar.pronouns.contains(&word) {
+                match discourse.referents.last().cloned() {
+                    Some(referent) => {
+                        discourse.mention(&referent.name, &[]);
+                        out.push(Self::variable(referent.name, token.span));
+                    }
+                    None => out.push(token.clone()),
+                }
+                i += 1;
+                continue;
+            }
+
+            if self.opens_phrase(&word) {
+                let len = clause[i + 1..]
+                    .iter()
+                    .take_while(|t| Self::lowered(t).is_some_and(|w| self.is_noun_word(&w)))
+                    .count();
+                let phrase = &clause[i + 1..i + 1 + len];
+                let (content, stop): (Vec<&Token>, Vec<&Token>) = phrase
+                    .iter()
+                    .partition(|t| Self::lowered(t).is_some_and(|w| !self.lexicon.is_stop_word(&w)));
+                let next = clause.get(i + 1 + len);
+                let before_operand = next.is_some_and(|t| matches!(t.kind, TokenKind::Variable(_) | TokenKind::LParen));
+                if !content.is_empty() && !before_operand {
+                    let words: Vec<String> = content.iter().filter_map(|t| Self::lowered(t)).collect();
+                    let span = phrase[0].span.join(phrase[len - 1].span);
+                    let name = match discourse.resolve(&words) {
+                        Some(name) => name,
+                        None if self.grammar.demonstratives.contains(&word) => {
+                            let message = format!("'{} {}' refers to no earlier variable", word, words.join(" "));
+                            return Err(ParseError::new(message, token.span.join(span)));
+                        }
+                        None => words.join(" "),
+                    };
+                    discourse.mention(&name, &words);
+                    out.push(token.clone());
+                    out.push(Self::variable(name, span));
+                    out.extend(stop.into_iter().cloned());
+                    i += 1 + len;
+                    continue;
+                }
+            }
+            out.push(token.clone());
+            i += 1;
+        }
+
+        // A clause without variables is one variable, named as by the parser
+        let has_operand = out.iter().any(|t| matches!(t.kind, TokenKind::Variable(_) | TokenKind::LParen));
+        let has_operator = out.iter().any(|t| matches!(t.kind, TokenKind::Operator(op) if op != Operator::Not));
+        let content: Vec<usize> = (0..out.len())
+            .filter(|&i| Self::lowered(&out[i]).is_some_and(|w| !self.lexicon.is_stop_word(&w)))
+            .collect();
+        if !has_operand && !has_operator && !content.is_empty() {
+            let words: Vec<String> = content.iter().filter_map(|&i| Self::lowered(&out[i])).collect();
+            let name = words.join(" ");
+            let span = out[content[0]].span.join(out[content[content.len() - 1]].span);
+            discourse.mention(&name, &words);
+            let variable = Self::variable(name, span);
+            out = out
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, token)| match content.iter().position(|&c| c == i) {
+                    Some(0) => Some(variable.clone()),
+                    Some(_) => None,
+                    None => Some(token),
+                })
+                .collect();
+        }
+        Ok(out)
+    }
+}
+
+/// Rewrite the noun phrases, references and variable-free clauses of a tokenized
+/// statement into `Variable` tokens
+pub(super) fn recognize(
+    tokens: &[Token],
+    grammar: &ControlledGrammar,
+    lexicon: &Lexicon,
+    discourse: &mut Discourse,
+) -> Result<Vec<Token>, ParseError> {
+    let recognizer = Recognizer { grammar, lexicon };
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if matches!(token.kind, TokenKind::Connective(_)) {
+            out.extend(recognizer.clause(&tokens[start..i], discourse)?);
+            out.push(token.clone());
+            start = i + 1;
+        }
+    }
+    out.extend(recognizer.clause(&tokens[start..], discourse)?);
+    Ok(out)
+}
+
+/// Tokens of a statement, with noun phrases recognized when `options` has a grammar
+pub(super) fn tokenize(input: &str, options: &PsgOptions) -> Result<Vec<Token>, ParseError> {
+    let tokens = lexer::tokenize(input, &options.lexicon)?;
+    match &options.grammar {
+        Some(grammar) => recognize(&tokens, grammar, &options.lexicon, &mut Discourse::default()),
+        None => Ok(tokens),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::{parse_rule_set, polylithic_syntax_gen_with, statement_readings, Disambiguation};
+
+    fn controlled() -> PsgOptions {
+        PsgOptions { grammar: Some(ControlledGrammar::english()), ..PsgOptions::default() }
+    }
+
+    fn parse(input: &str) -> Result<String, ParseError> {
+        let readings = statement_readings(input, &controlled())?;
+        Ok(readings[0].to_string())
+    }
+
+    #[test]
+    fn test_unquoted_noun_phrases_are_variables() {
+        let input = r#"The car only starts if the "start" button and the brake pedal are pressed"#;
+        let tokens = tokenize(input, &controlled()).unwrap();
+        let names: Vec<&str> = tokens
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::Variable(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["car starts", "start", "brake pedal"]);
+        assert_eq!(parse(input).unwrap(), "car starts -> (start && brake pedal)");
+        assert_eq!(&input[tokens[1].span.start..tokens[1].span.end], "car only starts");
+
+        // Same circuit as the fully quoted statement
+        let quoted = r#"The "car starts" only if the "start" button and the "brake pedal" are pressed"#;
+        let equivalent = crate::psg::check_statements(input, quoted, &controlled()).unwrap();
+        assert!(equivalent.is_equivalent());
+        let circuit = polylithic_syntax_gen_with(input, &controlled()).unwrap();
+        assert_eq!(circuit.wire_map.len(), 3);
+
+        assert_eq!(parse("the speed is not zero or the door isn't open").unwrap(), "!speed || !door");
+        assert_eq!(parse("if it rains then the road is wet").unwrap(), "it rains -> road");
+        // Without a grammar only quoted variables count
+        assert!(statement_readings(input, &PsgOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_references_resolve_to_earlier_variables() {
+        // Predicates are dropped, so these examples repeat the predicate of the earlier mention
+        assert_eq!(
+            parse(r#"The alarm sounds if (the "start" button is pressed or the "door" is open) and the button is pressed"#)
+                .unwrap(),
+            "alarm sounds <- ((start || door) && start)"
+        );
+        assert_eq!(
+            parse("The engine stalls if the brake pedal is not pressed and the clutch is engaged and that pedal is not pressed")
+                .unwrap(),
+            "engine stalls <- ((!brake pedal && clutch) && !brake pedal)"
+        );
+        assert_eq!(parse(r#"The "door" opens only if it is unlocked"#).unwrap(), "door -> door");
+        assert_eq!(
+            parse("The fan runs if the front door is open or the back door is open").unwrap(),
+            "fan runs <- (front door || back door)"
+        );
+
+        let error = parse("The alarm sounds if that pedal is pressed").unwrap_err();
+        assert_eq!(error.message, "'that pedal' refers to no earlier variable");
+
+        // References reach across the rules of a rule set
+        let options = PsgOptions { disambiguation: Disambiguation::Precedence, ..controlled() };
+        let rules = parse_rule_set(
+            "ignition: The engine starts only if the brake pedal is pressed. parking: The gear locks if that pedal is pressed.",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(rules[1].expression.to_string(), "gear locks <- brake pedal");
+    }
+}
@@ -0,0 +1,497 @@
+//! Policy DSL: an unambiguous textual front end for PSG statements
+//!
+//! The grammar is published in `wiki/psg-dsl.md`:
+//!
+//! ```text
+//! policy     := ( declaration | binding )* expr ";"?
+//! declaration := "var" name ( "," name )* ":" type ";"
+//! binding    := "let" IDENT "=" expr ";"
+//! type       := "bool" | "u1" | "u2" | ... | "u64"
+//! expr       := or ( ( "->" | "<-" | "<->" ) or )?
+//! or         := xor ( "||" xor )*
+//! xor        := and ( "^" and )*
+//! and        := unary ( "&&" unary )*
+//! unary      := "!" unary | primary
+//! primary    := name ( COMPARISON value )? | "true" | "false" | "(" expr ")"
+//!             | "#" "(" expr ( "," expr )* ")" COMPARISON NUMBER
+//! value      := NUMBER | name
+//! name       := IDENT | STRING
+//! COMPARISON := "==" | "!=" | "<" | "<=" | ">" | ">="
+//! ```
+//!
+//! Precedence and associativity are those of the English parser under
+//! `Disambiguation::Precedence`, and a
+//! `let` binding is substituted where it is used, so a policy and the English
+//! statement it formalizes parse to the same `Expr`. Implications do not chain
+//! without parentheses. Quoted names hold any text (`"car starts"`). A rendered
+//! Boolean expression (`LogicExpression`'s `Display`) whose names are
+//! identifiers parses back as a policy.
+//!
+//! A policy whose expression is an implication with a lone variable on the left
+//! records that variable as the consequent, as for `The car only starts if ...`.
+//! Undeclared names are Boolean; integers must be declared. `//` starts a comment.
+
+use std::collections::HashMap;
+
+use super::integer::{self, Comparison, VarType, MAX_INTEGER_WIDTH};
+use super::parser::allocate_bits;
+use super::{Expr, LogicExpression, Operator, ParseError, Span, Variable};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    Ident(String),
+    Str(String),
+    Number(u64),
+    Symbol(&'static str),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: Kind,
+    span: Span,
+}
+
+/// Longest first, so `<->` wins over `<-` and `<`
+const SYMBOLS: [&str; 19] = [
+    "<->", "->", "<-", "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "^", "(", ")", ",", ";", ":", "=",
+];
+
+const COMPARISONS: [(&str, Comparison); 6] = [
+    ("==", Comparison::Eq),
+    ("!=", Comparison::Ne),
+    ("<=", Comparison::Le),
+    (">=", Comparison::Ge),
+    ("<", Comparison::Lt),
+    (">", Comparison::Gt),
+];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if input[start..].starts_with("//") {
+            while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+        } else if c == '"' {
+            chars.next();
+            let mut name = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped)) => name.push(escaped),
+                        None => return Err(ParseError::new("unterminated name", Span::new(start, input.len()))),
+                    },
+                    Some((_, c)) => name.push(c),
+                    None => return Err(ParseError::new("unterminated name", Span::new(start, input.len()))),
+                }
+            }
+            let end = chars.peek().map_or(input.len(), |&(i, _)| i);
+            tokens.push(Token { kind: Kind::Str(name), span: Span::new(start, end) });
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let mut end = start;
+            while let Some((i, c)) = chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_') {
+                end = i + c.len_utf8();
+            }
+            let word = &input[start..end];
+            let span = Span::new(start, end);
+            let kind = if c.is_ascii_digit() {
+                Kind::Number(word.parse().map_err(|_| ParseError::new(format!("invalid number '{}'", word), span))?)
+            } else {
+                Kind::Ident(word.to_string())
+            };
+            tokens.push(Token { kind, span });
+        } else if c == '#' {
+            chars.next();
+            tokens.push(Token { kind: Kind::Symbol("#"), span: Span::new(start, start + 1) });
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| input[start..].starts_with(**s))
+                .ok_or_else(|| ParseError::new(format!("unexpected character '{}'", c), Span::new(start, start + c.len_utf8())))?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token { kind: Kind::Symbol(symbol), span: Span::new(start, start + symbol.len()) });
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    input_len: usize,
+    types: HashMap<String, VarType>,
+    bindings: HashMap<String, Expr>,
+    /// Integer variables compared so far, with their widths
+    integers: Vec<(Variable, usize)>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Kind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    /// Span of the current token, or an empty span at the end of the input
+    fn here(&self) -> Span {
+        self.tokens.get(self.pos).map_or(Span::new(self.input_len, self.input_len), |t| t.span)
+    }
+
+    fn at_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Kind::Symbol(s)) if *s == symbol)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Kind::Ident(word)) if word == keyword)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.at_symbol(symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(ParseError::new(format!("expected '{}'", symbol), self.here()))
+        }
+    }
+
+    /// An identifier or quoted name
+    fn name(&mut self) -> Result<(String, Span), ParseError> {
+        let span = self.here();
+        match self.peek() {
+            Some(Kind::Ident(name) | Kind::Str(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok((name, span))
+            }
+            _ => Err(ParseError::new("expected a name", span)),
+        }
+    }
+
+    fn number(&mut self) -> Result<u64, ParseError> {
+        match self.peek() {
+            Some(&Kind::Number(value)) => {
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => Err(ParseError::new("expected a number", self.here())),
+        }
+    }
+
+    fn comparison(&mut self) -> Option<Comparison> {
+        let op = COMPARISONS.iter().find(|(symbol, _)| self.at_symbol(symbol))?.1;
+        self.pos += 1;
+        Some(op)
+    }
+
+    /// `var a, b: type;`
+    fn declaration(&mut self) -> Result<(), ParseError> {
+        let mut names = vec![self.name()?];
+        while self.eat_symbol(",") {
+            names.push(self.name()?);
+        }
+        self.expect_symbol(":")?;
+        let span = self.here();
+        let var_type = match self.peek() {
+            Some(Kind::Ident(name)) if name == "bool" => VarType::Bool,
+            Some(Kind::Ident(name)) if name.starts_with('u') => match name[1..].parse::<usize>() {
+                Ok(width) if (1..=MAX_INTEGER_WIDTH).contains(&width) => VarType::UInt { width },
+                _ => return Err(ParseError::new(format!("integer widths are 1..={}", MAX_INTEGER_WIDTH), span)),
+            },
+            _ => return Err(ParseError::new("expected a type ('bool', 'u8', ...)", span)),
+        };
+        self.pos += 1;
+        self.expect_symbol(";")?;
+        for (name, span) in names {
+            if self.types.insert(name.clone(), var_type).is_some() {
+                return Err(ParseError::new(format!("'{}' is declared twice", name), span));
+            }
+        }
+        Ok(())
+    }
+
+    /// `let name = expr;`
+    fn binding(&mut self) -> Result<(), ParseError> {
+        let span = self.here();
+        let name = match self.peek() {
+            Some(Kind::Ident(name)) => name.clone(),
+            _ => return Err(ParseError::new("expected a binding name", span)),
+        };
+        self.pos += 1;
+        if self.bindings.contains_key(&name) || self.types.contains_key(&name) {
+            return Err(ParseError::new(format!("'{}' is already defined", name), span));
+        }
+        self.expect_symbol("=")?;
+        let expr = self.expr()?;
+        self.expect_symbol(";")?;
+        self.bindings.insert(name, expr);
+        Ok(())
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let lhs = self.or()?;
+        let op = match self.peek() {
+            Some(Kind::Symbol("->")) => Operator::Implies,
+            Some(Kind::Symbol("<-")) => Operator::Converse,
+            Some(Kind::Symbol("<->")) => Operator::Iff,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.or()?;
+        if matches!(self.peek(), Some(Kind::Symbol("->" | "<-" | "<->"))) {
+            return Err(ParseError::new("implications need parentheses to chain", self.here()));
+        }
+        Ok(Expr::binary(op, lhs, rhs))
+    }
+
+    fn chain(&mut self, symbol: &str, op: Operator, next: fn(&mut Self) -> Result<Expr, ParseError>) -> Result<Expr, ParseError> {
+        let mut lhs = next(self)?;
+        while self.eat_symbol(symbol) {
+            let rhs = next(self)?;
+            lhs = Expr::binary(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        self.chain("||", Operator::Or, Self::xor)
+    }
+
+    fn xor(&mut self) -> Result<Expr, ParseError> {
+        self.chain("^", Operator::Xor, Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        self.chain("&&", Operator::And, Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat_symbol("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let span = self.here();
+        if self.eat_symbol("(") {
+            let inner = self.expr()?;
+            if !self.eat_symbol(")") {
+                return Err(ParseError::new("expected ')' to close this group", span.join(self.here())));
+            }
+            return Ok(inner);
+        }
+        if self.eat_symbol("#") {
+            self.expect_symbol("(")?;
+            let mut operands = vec![self.expr()?];
+            while self.eat_symbol(",") {
+                operands.push(self.expr()?);
+            }
+            self.expect_symbol(")")?;
+            let op = self.comparison().ok_or_else(|| ParseError::new("expected a comparison after '#(...)'", self.here()))?;
+            let k = self.number()? as usize;
+            return Ok(Expr::Threshold { op, k, operands });
+        }
+        for (keyword, value) in [("true", true), ("false", false)] {
+            if self.at_keyword(keyword) {
+                self.pos += 1;
+                return Ok(Expr::Const(value));
+            }
+        }
+
+        let (name, span) = self.name()?;
+        if let Some(expr) = self.bindings.get(&name) {
+            return Ok(expr.clone());
+        }
+        let var = Variable { name, span, bit: None };
+        if let Some(op) = self.comparison() {
+            let left = self.integer_bits(&var)?;
+            let right = match self.peek() {
+                Some(&Kind::Number(value)) => {
+                    self.pos += 1;
+                    integer::constant(value)
+                }
+                _ => {
+                    let (name, span) = self.name()?;
+                    self.integer_bits(&Variable { name, span, bit: None })?
+                }
+            };
+            return Ok(integer::compare(op, &left, &right));
+        }
+        if matches!(self.types.get(&var.name), Some(VarType::UInt { .. })) {
+            return Err(ParseError::new(format!("integer variable '{}' needs a comparison", var.name), var.span));
+        }
+        Ok(Expr::Var(var))
+    }
+
+    /// Bits of a compared variable, which must be declared as an integer
+    fn integer_bits(&mut self, var: &Variable) -> Result<Vec<Expr>, ParseError> {
+        match self.types.get(&var.name) {
+            Some(&VarType::UInt { width }) => {
+                if !self.integers.iter().any(|(v, _)| v == var) {
+                    self.integers.push((var.clone(), width));
+                }
+                Ok(integer::bits(var, width))
+            }
+            _ => Err(ParseError::new(format!("'{}' is compared but not declared as an integer", var.name), var.span)),
+        }
+    }
+}
+
+/// Parse a policy into a `LogicExpression`
+pub fn parse_policy(input: &str) -> Result<LogicExpression, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        input_len: input.len(),
+        types: HashMap::new(),
+        bindings: HashMap::new(),
+        integers: Vec::new(),
+    };
+    loop {
+        if parser.at_keyword("var") {
+            parser.pos += 1;
+            parser.declaration()?;
+        } else if parser.at_keyword("let") {
+            parser.pos += 1;
+            parser.binding()?;
+        } else {
+            break;
+        }
+    }
+    let root = parser.expr()?;
+    parser.eat_symbol(";");
+    if parser.pos < parser.tokens.len() {
+        let message = match parser.peek() {
+            Some(Kind::Ident(word)) if word == "var" || word == "let" => "declarations and bindings come before the expression",
+            Some(Kind::Symbol(")")) => "unmatched ')'",
+            _ => "expected an operator or the end of the policy",
+        };
+        return Err(ParseError::new(message, parser.here()));
+    }
+
+    let consequent = match &root {
+        Expr::Binary { op: Operator::Implies | Operator::Converse | Operator::Iff, lhs, .. } => match &**lhs {
+            Expr::Var(var) => Some(var.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let mut expression = LogicExpression::new(root);
+    expression.variables = allocate_bits(&expression.variables, &parser.integers);
+    expression.consequent = consequent;
+    Ok(expression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::{lexer, parser, Disambiguation, PsgOptions};
+
+    fn parse_english(input: &str, types: &HashMap<String, VarType>) -> LogicExpression {
+        let options =
+            PsgOptions { types: types.clone(), disambiguation: Disambiguation::Precedence, ..PsgOptions::default() };
+        parser::parse_with(&lexer::tokenize(input, &options.lexicon).unwrap(), input.len(), &options).unwrap()
+    }
+
+    #[test]
+    fn test_policy_matches_english_ast() {
+        let types: HashMap<String, VarType> = [("speed".to_string(), VarType::UInt { width: 8 })].into();
+        let cases = [
+            (
+                r#"The car only starts if the "start" button is pressed and the "brake" pedal is pressed"#,
+                r#""car starts" -> start && brake"#,
+            ),
+            (r#""a" or "b" and not "c" xor "d""#, "a || b && !c ^ d"),
+            (
+                r#"the "door" opens if at least 2 of "badge", "pin", not "face" are valid"#,
+                "let factors = #(badge, pin, !face) >= 2;\ndoor <- factors",
+            ),
+            (
+                r#"the "brake" is not pressed and the "speed" is greater than 60"#,
+                "var speed: u8; // km/h\nvar brake: bool;\n!brake && speed > 60;",
+            ),
+        ];
+        for (english, policy) in cases {
+            let expected = parse_english(english, &types);
+            let parsed = parse_policy(policy).unwrap();
+            assert_eq!(parsed.root, expected.root, "{}", policy);
+            assert_eq!(parsed.variables, expected.variables, "{}", policy);
+        }
+        let car = parse_policy(r#""car starts" -> start && brake"#).unwrap();
+        assert_eq!(car.consequent, Some(Variable::new("car starts")));
+        assert_eq!(car.variables[0].span, Span::new(0, 12));
+    }
+
+    #[test]
+    fn test_wiki_example_compiles() {
+        use crate::partitioned_gc::PartitionedGCScheme;
+        use crate::psg::{polylithic_syntax_gen_from, PsgOptions};
+
+        let policy = "var speed: u8;\nlet checks = #(badge, pin, face) >= 2;\n\"car starts\" -> start && brake && speed < 5 && checks;";
+        let expression = parse_policy(policy).unwrap();
+        let circuit = polylithic_syntax_gen_from(&expression, &PsgOptions::default());
+        assert_eq!(circuit.wire_map[circuit.consequent.as_ref().unwrap()], 0);
+        let gc = PartitionedGCScheme::garble_circuit(&circuit.circuit).unwrap();
+        let run = |starts: u8, speed: u8, badge: u8| {
+            let inputs: Vec<u8> = expression
+                .variables
+                .iter()
+                .map(|v| match (v.name.as_str(), v.bit) {
+                    ("car starts", _) => starts,
+                    ("speed", Some(bit)) => speed >> bit & 1,
+                    ("badge", _) => badge,
+                    ("face", _) => 0,
+                    _ => 1,
+                })
+                .collect();
+            let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+            PartitionedGCScheme::evaluate_garbled(&gc, &mut labels);
+            PartitionedGCScheme::decode_outputs(&gc, &labels).unwrap()
+        };
+        assert_eq!(run(1, 3, 1), vec![1]);
+        assert_eq!(run(1, 30, 1), vec![0]);
+        assert_eq!(run(1, 3, 0), vec![0]);
+        assert_eq!(run(0, 30, 0), vec![1]);
+    }
+
+    #[test]
+    fn test_rendered_expressions_parse_back() {
+        for english in [
+            r#""a" if and only if not "b" and ("c" or "d")"#,
+            r#"exactly one of "a", "b" or "c" is set and "d""#,
+            r#"the "door" stays shut unless the "badge" is valid"#,
+        ] {
+            let expected = parse_english(english, &HashMap::new());
+            let parsed = parse_policy(&expected.to_string()).unwrap();
+            assert_eq!(parsed.root, expected.root, "{}", expected);
+        }
+    }
+
+    #[test]
+    fn test_policy_errors() {
+        for (policy, message, at) in [
+            ("a -> b -> c", "parentheses to chain", Span::new(7, 9)),
+            ("var x: u8; x && y", "needs a comparison", Span::new(11, 12)),
+            ("x > 3", "not declared as an integer", Span::new(0, 1)),
+            ("var x: u99; x > 3", "widths", Span::new(7, 10)),
+            ("let a = b; let a = c; a", "already defined", Span::new(15, 16)),
+            ("a && (b || c", "close this group", Span::new(5, 12)),
+            ("a b", "expected an operator", Span::new(2, 3)),
+            ("#(a, b) 2", "comparison", Span::new(8, 9)),
+            ("a && \"b", "unterminated", Span::new(5, 7)),
+        ] {
+            let err = parse_policy(policy).unwrap_err();
+            assert!(err.message.contains(message), "{}: {}", policy, err);
+            assert_eq!(err.span, at, "{}: {}", policy, err);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! Truth tables and semantic equivalence of statements and circuits
+//!
+//! Both sides are read into one AND/XOR-inverter graph over shared inputs, and a
+//! miter (OR of the XORs of corresponding outputs) is checked. Structural hashing
+//! often makes both sides identical on its own; otherwise the truth table is
+//! enumerated up to `MAX_TRUTH_TABLE_VARIABLES` inputs. Beyond that, random
+//! simulation looks for a difference, XOR-AND rewriting tries to make the sides
+//! identical, and the CDCL solver (`sat`) decides the Tseitin encoding of the
+//! miter. A mismatch yields a distinguishing assignment, tagged with the method
+//! that found it.
+//!
+//! Statements are matched by variable name (a variable missing on one side is
+//! free there); circuits are matched by input and output position.
+
+use std::fmt;
+
+use super::aig::{Aig, Lit};
+use super::{controlled, parser, sat, xag, LogicExpression, ParseError, PsgOptions};
+use crate::partitioned_gc::Circuit;
+
+/// Largest number of inputs checked by enumerating the truth table
+pub const MAX_TRUTH_TABLE_VARIABLES: usize = 16;
+
+/// Truth table with row `r` assigning bit `i` of `r` to input `i`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruthTable {
+    pub inputs: Vec<String>,
+    /// Output values of every row
+    pub rows: Vec<Vec<bool>>,
+}
+
+impl TruthTable {
+    fn of_aig(aig: &Aig, inputs: Vec<String>) -> Option<Self> {
+        let n = aig.inputs.len();
+        if n > MAX_TRUTH_TABLE_VARIABLES {
+            return None;
+        }
+        let rows = (0..1u32 << n).map(|row| aig.eval(&(0..n).map(|i| row >> i & 1 == 1).collect::<Vec<_>>())).collect();
+        Some(TruthTable { inputs, rows })
+    }
+
+    /// Truth table of a statement, `None` past `MAX_TRUTH_TABLE_VARIABLES` variables
+    pub fn of_expression(expression: &LogicExpression) -> Option<Self> {
+        let names: Vec<String> = expression.variables.iter().map(|v| v.to_string()).collect();
+        TruthTable::of_aig(&Aig::from_circuit(&expression.compile(&names).circuit), names)
+    }
+
+    /// Truth table of a circuit (inputs named `w<wire>`), `None` past
+    /// `MAX_TRUTH_TABLE_VARIABLES` inputs
+    pub fn of_circuit(circuit: &Circuit) -> Option<Self> {
+        let aig = Aig::from_circuit(circuit);
+        let names = aig.inputs.iter().map(|w| format!("w{}", w)).collect();
+        TruthTable::of_aig(&aig, names)
+    }
+}
+
+impl fmt::Display for TruthTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} | out", self.inputs.join(" | "))?;
+        for (row, outputs) in self.rows.iter().enumerate() {
+            let inputs: Vec<String> = (0..self.inputs.len())
+                .map(|i| format!("{:>width$}", row >> i & 1, width = self.inputs[i].len()))
+                .collect();
+            let outputs: Vec<String> = outputs.iter().map(|&b| (b as u8).to_string()).collect();
+            writeln!(f, "{} | {}", inputs.join(" | "), outputs.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// How equivalence or a difference was established
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Both sides hashed (after rewriting) to the same graph
+    Structural,
+    TruthTable,
+    /// Random input vectors (differences only)
+    Simulation,
+    Sat,
+}
+
+/// Assignment on which the two sides differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    pub assignment: Vec<(String, bool)>,
+    pub left: Vec<bool>,
+    pub right: Vec<bool>,
+    pub method: Method,
+}
+
+/// Outcome of an equivalence check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    Equivalent(Method),
+    Different(Counterexample),
+}
+
+impl Equivalence {
+    pub fn is_equivalent(&self) -> bool {
+        matches!(self, Equivalence::Equivalent(_))
+    }
+}
+
+/// Circuits whose interfaces cannot be matched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquivalenceError {
+    pub message: String,
+}
+
+impl fmt::Display for EquivalenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for EquivalenceError {}
+
+/// Decide whether the outputs `left` and `right` of `aig` agree on every input
+fn check_miter(aig: &Aig, left: &[Lit], right: &[Lit], names: &[String]) -> Equivalence {
+    let mut aig = aig.clone();
+    aig.outputs = left.iter().chain(right).copied().collect();
+    let split = left.len();
+    let differs = |aig: &Aig, values: &[bool]| {
+        let out = aig.eval(values);
+        (out[..split] != out[split..]).then(|| values.to_vec())
+    };
+
+    let n = aig.inputs.len();
+    let (assignment, method) = if aig.outputs[..split] == aig.outputs[split..] {
+        (None, Method::Structural)
+    } else if n <= MAX_TRUTH_TABLE_VARIABLES {
+        let rows = (0..1u64 << n).map(|row| (0..n).map(|i| row >> i & 1 == 1).collect::<Vec<bool>>());
+        (rows.into_iter().find_map(|values| differs(&aig, &values)), Method::TruthTable)
+    } else {
+        // Random simulation finds most differences; XOR-AND rewriting often makes
+        // both sides structurally identical; the SAT solver decides the rest
+        let mut state = 0x9e3779b97f4a7c15u64;
+        let simulated = (0..256).find_map(|_| {
+            let values: Vec<bool> = (0..n)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state & 1 == 1
+                })
+                .collect();
+            differs(&aig, &values)
+        });
+        let rewritten = xag::minimize(aig.clone());
+        match simulated {
+            Some(values) => (Some(values), Method::Simulation),
+            None if rewritten.outputs[..split] == rewritten.outputs[split..] => (None, Method::Structural),
+            None => {
+                let mut miter = rewritten.clone();
+                let mut root = Lit::FALSE;
+                for i in 0..split {
+                    let diff = miter.xor(rewritten.outputs[i], rewritten.outputs[split + i]);
+                    root = miter.or(root, diff);
+                }
+                let values = sat::solve_aig(&miter, root);
+                (values, Method::Sat)
+            }
+        }
+    };
+
+    match assignment {
+        None => Equivalence::Equivalent(method),
+        Some(values) => {
+            let out = aig.eval(&values);
+            Equivalence::Different(Counterexample {
+                assignment: names.iter().cloned().zip(values).collect(),
+                left: out[..split].to_vec(),
+                right: out[split..].to_vec(),
+                method,
+            })
+        }
+    }
+}
+
+/// Whether two parsed statements mean the same, matching variables by name
+pub fn check_expressions(left: &LogicExpression, right: &LogicExpression) -> Equivalence {
+    let mut names: Vec<String> = Vec::new();
+    for var in left.variables.iter().chain(&right.variables) {
+        let name = var.to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    let mut aig = Aig::new();
+    let inputs: Vec<Lit> = (0..names.len()).map(|i| aig.add_input(i)).collect();
+    let side = |aig: &mut Aig, expression: &LogicExpression| {
+        let own: Vec<String> = expression.variables.iter().map(|v| v.to_string()).collect();
+        let wires: Vec<Lit> = own.iter().map(|name| inputs[names.iter().position(|n| n == name).expect("name collected")]).collect();
+        aig.add_circuit(&expression.compile(&own).circuit, &wires)
+    };
+    let left_out = side(&mut aig, left);
+    let right_out = side(&mut aig, right);
+    check_miter(&aig, &left_out, &right_out, &names)
+}
+
+/// Whether two circuits compute the same outputs, matching inputs and outputs by position
+pub fn check_circuits(left: &Circuit, right: &Circuit) -> Result<Equivalence, EquivalenceError> {
+    if left.input_wires.len() != right.input_wires.len() || left.output_wires.len() != right.output_wires.len() {
+        return Err(EquivalenceError {
+            message: format!(
+                "circuits differ in shape: {} inputs / {} outputs vs {} inputs / {} outputs",
+                left.input_wires.len(),
+                left.output_wires.len(),
+                right.input_wires.len(),
+                right.output_wires.len()
+            ),
+        });
+    }
+    let mut aig = Aig::new();
+    let inputs: Vec<Lit> = left.input_wires.iter().map(|&w| aig.add_input(w)).collect();
+    let left_out = aig.add_circuit(left, &inputs);
+    let right_out = aig.add_circuit(right, &inputs);
+    if aig.inputs.len() != inputs.len() {
+        return Err(EquivalenceError { message: "circuit reads wires that are neither inputs nor gate outputs".to_string() });
+    }
+    let names: Vec<String> = left.input_wires.iter().map(|w| format!("w{}", w)).collect();
+    Ok(check_miter(&aig, &left_out, &right_out, &names))
+}
+
+/// Parse two statements and check that they mean the same
+pub fn check_statements(left: &str, right: &str, options: &PsgOptions) -> Result<Equivalence, ParseError> {
+    let parse = |input: &str| {
+        let tokens = controlled::tokenize(input, options)?;
+        parser::parse_with(&tokens, input.len(), options)
+    };
+    Ok(check_expressions(&parse(left)?, &parse(right)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partitioned_gc::{GateType, PartitionedGCScheme};
+    use crate::psg::{minimize_and_gates, parse_precedence};
+
+    fn parse_str(input: &str) -> LogicExpression {
+        parse_precedence(input).unwrap()
+    }
+
+    #[test]
+    fn test_rephrased_statements() {
+        let options = PsgOptions::default();
+        let same = check_statements(
+            r#"The car only starts if the "start" button is pressed and the "brake" pedal is pressed"#,
+            r#"if the "car starts" then both "brake" and "start""#,
+            &options,
+        )
+        .unwrap();
+        assert!(same.is_equivalent(), "{:?}", same);
+
+        let different = check_statements(r#""a" or "b""#, r#""a" xor "b""#, &options).unwrap();
+        match different {
+            Equivalence::Different(cex) => {
+                assert_eq!(cex.assignment, vec![("a".to_string(), true), ("b".to_string(), true)]);
+                assert_eq!((cex.left, cex.right), (vec![true], vec![false]));
+                assert_eq!(cex.method, Method::TruthTable);
+            }
+            other => panic!("expected a counterexample, got {:?}", other),
+        }
+
+        let table = TruthTable::of_expression(&parse_str(r#""a" and not "b""#)).unwrap();
+        assert_eq!(table.rows, vec![vec![false], vec![true], vec![false], vec![false]]);
+    }
+
+    #[test]
+    fn test_large_circuits() {
+        // Absorption is invisible to rewriting and left to the SAT solver
+        let absorbed = parse_str(&(0..20).map(|i| format!(r#"("x{i}" or "x{i}" and "y{i}")"#)).collect::<Vec<_>>().join(" and "));
+        let plain = parse_str(&(0..20).map(|i| format!(r#""x{i}""#)).collect::<Vec<_>>().join(" and "));
+        assert_eq!(check_expressions(&absorbed, &plain), Equivalence::Equivalent(Method::Sat));
+
+        // 24-variable parity against its AND-minimized rewrite, and a one-gate change
+        let n = 24;
+        let input = (0..n / 2)
+            .map(|i| format!(r#"("x{i}" and not "y{i}" or not "x{i}" and "y{i}")"#))
+            .collect::<Vec<_>>()
+            .join(" xor ");
+        let expression = parse_str(&input);
+        assert_eq!(expression.variables.len(), n);
+        let names: Vec<String> = expression.variables.iter().map(|v| v.name.clone()).collect();
+        let circuit = expression.compile(&names).circuit;
+
+        let optimized = minimize_and_gates(&circuit);
+        assert_eq!(check_circuits(&circuit, &optimized), Ok(Equivalence::Equivalent(Method::Structural)));
+
+        let mut broken = optimized.clone();
+        let last = broken.gates.iter().rposition(|g| g.gate_type == GateType::Xor).unwrap();
+        broken.gates[last].gate_type = GateType::Or;
+        match check_circuits(&circuit, &broken).unwrap() {
+            Equivalence::Different(cex) => {
+                assert_eq!(cex.method, Method::Simulation);
+                let values: Vec<u8> = cex.assignment.iter().map(|(_, b)| *b as u8).collect();
+                let eval = |c: &Circuit| {
+                    let gc = PartitionedGCScheme::garble_circuit(c).unwrap();
+                    let mut labels = PartitionedGCScheme::encode_inputs(&gc, &values);
+                    PartitionedGCScheme::evaluate_garbled(&gc, &mut labels);
+                    PartitionedGCScheme::decode_outputs(&gc, &labels).unwrap()
+                };
+                assert_ne!(eval(&circuit), eval(&broken));
+            }
+            other => panic!("expected a counterexample, got {:?}", other),
+        }
+    }
+}
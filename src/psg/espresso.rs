@@ -0,0 +1,491 @@
+//! Heuristic two-level minimization (Espresso-style) and literal factoring
+//!
+//! For statements past `MAX_KMAP_VARIABLES` the truth table is out of reach, so the
+//! expression is expanded to a sum of cubes and improved with Espresso's EXPAND and
+//! IRREDUNDANT steps. Containment is decided with the unate recursive tautology
+//! check, so no truth table or OFF-set is ever built. REDUCE is not implemented:
+//! one EXPAND/IRREDUNDANT pass yields an irredundant prime cover, not necessarily a
+//! minimum one. The cover is then factored into a multi-level expression by
+//! repeatedly pulling out the most frequent literal.
+//!
+//! Before expanding, the number of cubes is estimated from the syntax tree alone
+//! (sums for unions, products for products, absorption ignored). Expressions
+//! estimated far past `MAX_COVER_CUBES`, such as a threshold over dozens of
+//! operands, are given up at once instead of after building thousands of cubes.
+
+use super::{Expr, LogicExpression, Operator, Variable};
+
+/// Largest number of cubes built while expanding an expression to a cover
+pub const MAX_COVER_CUBES: usize = 4096;
+
+/// Expansions estimated at more than this many times `MAX_COVER_CUBES` cubes are
+/// not attempted; absorption can only shrink a cover below its estimate
+const ESTIMATE_MARGIN: usize = 2;
+
+/// Product of literals: bit `i` of `pos` is the literal `x_i`, of `neg` is `!x_i`;
+/// a variable with neither bit set does not appear
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cube {
+    pos: Vec<u64>,
+    neg: Vec<u64>,
+}
+
+impl Cube {
+    /// The cube without literals (constant true) over `n` variables
+    pub fn universe(n: usize) -> Self {
+        let words = n.div_ceil(64);
+        Cube { pos: vec![0; words], neg: vec![0; words] }
+    }
+
+    fn literal(n: usize, var: usize, value: bool) -> Self {
+        let mut cube = Cube::universe(n);
+        cube.set(var, Some(value));
+        cube
+    }
+
+    /// Value `var` is fixed to, `None` when it does not appear
+    pub fn get(&self, var: usize) -> Option<bool> {
+        let bit = 1u64 << (var % 64);
+        if self.pos[var / 64] & bit != 0 {
+            Some(true)
+        } else if self.neg[var / 64] & bit != 0 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, var: usize, value: Option<bool>) {
+        let bit = 1u64 << (var % 64);
+        self.pos[var / 64] &= !bit;
+        self.neg[var / 64] &= !bit;
+        match value {
+            Some(true) => self.pos[var / 64] |= bit,
+            Some(false) => self.neg[var / 64] |= bit,
+            None => {}
+        }
+    }
+
+    pub fn literals(&self) -> usize {
+        self.pos.iter().chain(&self.neg).map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Variables appearing in the cube, ascending
+    pub fn vars(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.pos.len() * 64).filter(|&v| self.get(v).is_some())
+    }
+
+    /// Conjunction of two cubes, `None` when they conflict
+    fn intersect(&self, other: &Cube) -> Option<Cube> {
+        let pos: Vec<u64> = self.pos.iter().zip(&other.pos).map(|(a, b)| a | b).collect();
+        let neg: Vec<u64> = self.neg.iter().zip(&other.neg).map(|(a, b)| a | b).collect();
+        pos.iter().zip(&neg).all(|(p, n)| p & n == 0).then_some(Cube { pos, neg })
+    }
+
+    /// Every minterm of `other` is a minterm of `self`
+    fn contains(&self, other: &Cube) -> bool {
+        self.pos.iter().zip(&other.pos).all(|(a, b)| a & b == *a)
+            && self.neg.iter().zip(&other.neg).all(|(a, b)| a & b == *a)
+    }
+}
+
+/// Union of cubes (a sum of products) over `n` variables
+#[derive(Debug, Clone)]
+pub struct Cover {
+    pub n: usize,
+    pub cubes: Vec<Cube>,
+}
+
+impl Cover {
+    fn empty(n: usize) -> Self {
+        Cover { n, cubes: Vec::new() }
+    }
+
+    pub fn literals(&self) -> usize {
+        self.cubes.iter().map(Cube::literals).sum()
+    }
+
+    /// Drop cubes contained in another single cube
+    fn absorb(&mut self) {
+        self.cubes.sort_by_key(Cube::literals);
+        self.cubes.dedup();
+        let mut kept: Vec<Cube> = Vec::new();
+        for cube in self.cubes.drain(..) {
+            if !kept.iter().any(|k| k.contains(&cube)) {
+                kept.push(cube);
+            }
+        }
+        self.cubes = kept;
+    }
+
+    fn union(mut self, other: Cover) -> Cover {
+        self.cubes.extend(other.cubes);
+        self.absorb();
+        self
+    }
+
+    fn product(&self, other: &Cover) -> Option<Cover> {
+        let mut result = Cover::empty(self.n);
+        for a in &self.cubes {
+            for b in &other.cubes {
+                if let Some(cube) = a.intersect(b) {
+                    result.cubes.push(cube);
+                }
+            }
+            if result.cubes.len() > MAX_COVER_CUBES {
+                result.absorb();
+                if result.cubes.len() > MAX_COVER_CUBES {
+                    return None;
+                }
+            }
+        }
+        result.absorb();
+        Some(result)
+    }
+
+    /// Shannon cofactor with respect to `var = value`
+    fn cofactor(&self, var: usize, value: bool) -> Cover {
+        let cubes = self
+            .cubes
+            .iter()
+            .filter(|c| c.get(var) != Some(!value))
+            .map(|c| {
+                let mut c = c.clone();
+                c.set(var, None);
+                c
+            })
+            .collect();
+        Cover { n: self.n, cubes }
+    }
+
+    /// Generalized cofactor with respect to a cube
+    fn cofactor_cube(&self, cube: &Cube) -> Cover {
+        cube.vars().fold(self.clone(), |cover, v| cover.cofactor(v, cube.get(v).expect("literal of the cube")))
+    }
+
+    /// Whether the cover is constant true (unate recursive paradigm)
+    pub fn is_tautology(&self) -> bool {
+        if self.cubes.iter().any(|c| c.literals() == 0) {
+            return true;
+        }
+        if self.cubes.is_empty() {
+            return false;
+        }
+        // Split on the variable appearing in both polarities most often; a unate
+        // cover without the universal cube is never a tautology
+        let mut counts = vec![(0usize, 0usize); self.n];
+        for cube in &self.cubes {
+            for v in cube.vars() {
+                match cube.get(v) {
+                    Some(true) => counts[v].0 += 1,
+                    _ => counts[v].1 += 1,
+                }
+            }
+        }
+        let binate = (0..self.n)
+            .filter(|&v| counts[v].0 > 0 && counts[v].1 > 0)
+            .max_by_key(|&v| counts[v].0 + counts[v].1);
+        match binate {
+            Some(v) => self.cofactor(v, true).is_tautology() && self.cofactor(v, false).is_tautology(),
+            None => false,
+        }
+    }
+
+    /// Every minterm of `cube` is covered
+    fn covers(&self, cube: &Cube) -> bool {
+        self.cofactor_cube(cube).is_tautology()
+    }
+}
+
+/// How an operator (or its complement, when `positive` is false) expands: the
+/// union of the covers of its sides, or a union of products of its sides; the
+/// flags tell which polarity of each side is taken
+enum Expansion {
+    Union(bool, bool),
+    Products(&'static [(bool, bool)]),
+}
+
+fn expansion(op: Operator, positive: bool) -> Expansion {
+    match (op, positive) {
+        (Operator::And, true) => Expansion::Products(&[(true, true)]),
+        (Operator::And, false) => Expansion::Union(false, false),
+        (Operator::Or, true) => Expansion::Union(true, true),
+        (Operator::Or, false) => Expansion::Products(&[(false, false)]),
+        (Operator::Implies, true) => Expansion::Union(false, true),
+        (Operator::Implies, false) => Expansion::Products(&[(true, false)]),
+        (Operator::Converse, true) => Expansion::Union(true, false),
+        (Operator::Converse, false) => Expansion::Products(&[(false, true)]),
+        (Operator::Xor, true) | (Operator::Iff, false) => Expansion::Products(&[(true, false), (false, true)]),
+        (Operator::Xor, false) | (Operator::Iff, true) => Expansion::Products(&[(true, true), (false, false)]),
+        (Operator::Not, _) => unreachable!("negation is parsed as Expr::Not"),
+    }
+}
+
+/// Cubes the expansion of `expr` builds before absorption, for the expression and
+/// its complement (saturating); an upper bound on the size of either cover
+fn estimated_cubes(expr: &Expr) -> (usize, usize) {
+    let pick = |(on, off): (usize, usize), positive: bool| if positive { on } else { off };
+    match expr {
+        Expr::Var(_) => (1, 1),
+        Expr::Const(value) => (*value as usize, !*value as usize),
+        Expr::Not(inner) => {
+            let (on, off) = estimated_cubes(inner);
+            (off, on)
+        }
+        Expr::Binary { op, lhs, rhs } => {
+            let (lhs, rhs) = (estimated_cubes(lhs), estimated_cubes(rhs));
+            let count = |positive: bool| match expansion(*op, positive) {
+                Expansion::Union(l, r) => pick(lhs, l).saturating_add(pick(rhs, r)),
+                Expansion::Products(terms) => terms
+                    .iter()
+                    .map(|&(l, r)| pick(lhs, l).saturating_mul(pick(rhs, r)))
+                    .fold(0, usize::saturating_add),
+            };
+            (count(true), count(false))
+        }
+        Expr::Threshold { op, k, operands } => {
+            let count = |positive: bool| {
+                let mut layer: Vec<usize> =
+                    (0..=operands.len()).map(|c| (op.eval(c as u64, *k as u64) == positive) as usize).collect();
+                for (i, operand) in operands.iter().enumerate().rev() {
+                    let (on, off) = estimated_cubes(operand);
+                    layer = (0..=i)
+                        .map(|c| on.saturating_mul(layer[c + 1]).saturating_add(off.saturating_mul(layer[c])))
+                        .collect();
+                }
+                layer[0]
+            };
+            (count(true), count(false))
+        }
+    }
+}
+
+/// Cover of `expr` (or of its complement when `positive` is false), `None` past
+/// `MAX_COVER_CUBES` cubes
+fn cover_of(expr: &Expr, positive: bool, index: &dyn Fn(&Variable) -> usize, n: usize) -> Option<Cover> {
+    let universe = || Cover { n, cubes: vec![Cube::universe(n)] };
+    match expr {
+        Expr::Var(var) => Some(Cover { n, cubes: vec![Cube::literal(n, index(var), positive)] }),
+        Expr::Const(value) => Some(if *value == positive { universe() } else { Cover::empty(n) }),
+        Expr::Not(inner) => cover_of(inner, !positive, index, n),
+        Expr::Binary { op, lhs, rhs } => {
+            let side = |e: &Expr, p: bool| cover_of(e, p, index, n);
+            // Every operator as a union of products of its (possibly negated) sides
+            let terms = match expansion(*op, positive) {
+                Expansion::Union(l, r) => return Some(side(lhs, l)?.union(side(rhs, r)?)),
+                Expansion::Products(terms) => terms,
+            };
+            let mut cover = Cover::empty(n);
+            for &(l, r) in terms {
+                cover = cover.union(side(lhs, l)?.product(&side(rhs, r)?)?);
+                if cover.cubes.len() > MAX_COVER_CUBES {
+                    return None;
+                }
+            }
+            Some(cover)
+        }
+        Expr::Threshold { op, k, operands } => {
+            // Covers of "the operands from i on make the comparison come out
+            // `positive` with c of the earlier ones true", from the last operand back
+            let count = operands.len();
+            let mut layer: Vec<Cover> = (0..=count)
+                .map(|c| if op.eval(c as u64, *k as u64) == positive { universe() } else { Cover::empty(n) })
+                .collect();
+            for (i, operand) in operands.iter().enumerate().rev() {
+                let (on, off) = (cover_of(operand, true, index, n)?, cover_of(operand, false, index, n)?);
+                let mut next = Vec::with_capacity(i + 1);
+                for c in 0..=i {
+                    let cover = on.product(&layer[c + 1])?.union(off.product(&layer[c])?);
+                    if cover.cubes.len() > MAX_COVER_CUBES {
+                        return None;
+                    }
+                    next.push(cover);
+                }
+                layer = next;
+            }
+            layer.into_iter().next()
+        }
+    }
+}
+
+/// Cover of `expression` or its complement, `None` when it is estimated far past
+/// `MAX_COVER_CUBES` or grows past it
+fn expression_cover(expression: &LogicExpression, positive: bool) -> Option<Cover> {
+    let (on, off) = estimated_cubes(&expression.root);
+    if (if positive { on } else { off }) > ESTIMATE_MARGIN * MAX_COVER_CUBES {
+        return None;
+    }
+    let index = |var: &Variable| {
+        expression.variables.iter().position(|v| v == var).expect("variable collected by LogicExpression")
+    };
+    cover_of(&expression.root, positive, &index, expression.variables.len())
+}
+
+/// Sum-of-cubes cover of `expression`, `None` when it exceeds `MAX_COVER_CUBES`
+pub fn cover(expression: &LogicExpression) -> Option<Cover> {
+    expression_cover(expression, true)
+}
+
+/// Sum-of-cubes cover of the complement of `expression`
+pub fn complement_cover(expression: &LogicExpression) -> Option<Cover> {
+    expression_cover(expression, false)
+}
+
+/// EXPAND every cube to a prime (largest cubes first), then IRREDUNDANT
+pub fn espresso(cover: &Cover) -> Cover {
+    let mut f = cover.clone();
+    f.absorb();
+
+    // EXPAND: drop each literal whose removal keeps the cube inside the function
+    f.cubes.sort_by_key(Cube::literals);
+    let mut expanded: Vec<Cube> = Vec::new();
+    for cube in &f.cubes {
+        if expanded.iter().any(|e| e.contains(cube)) {
+            continue;
+        }
+        let mut prime = cube.clone();
+        for v in cube.vars().collect::<Vec<_>>() {
+            let value = prime.get(v);
+            prime.set(v, None);
+            if !f.covers(&prime) {
+                prime.set(v, value);
+            }
+        }
+        expanded.retain(|e| !prime.contains(e));
+        expanded.push(prime);
+    }
+
+    // IRREDUNDANT: drop cubes covered by the rest, biggest (most literals) first
+    expanded.sort_by_key(|c| std::cmp::Reverse(c.literals()));
+    let mut i = 0;
+    while i < expanded.len() {
+        let cube = expanded.remove(i);
+        let rest = Cover { n: f.n, cubes: expanded.clone() };
+        if !rest.covers(&cube) {
+            expanded.insert(i, cube);
+            i += 1;
+        }
+    }
+    Cover { n: f.n, cubes: expanded }
+}
+
+/// Multi-level expression for a cover: the literal shared by most cubes is
+/// factored out (`a&b | a&c | d` = `a&(b | c) | d`) until no literal is shared
+pub fn factor(cover: &Cover, variables: &[Variable]) -> Expr {
+    let literal = |v: usize, value: bool| {
+        let var = Expr::Var(variables[v].clone());
+        if value { var } else { Expr::Not(Box::new(var)) }
+    };
+    let or = |a: Expr, b: Expr| Expr::binary(Operator::Or, a, b);
+    let and = |a: Expr, b: Expr| Expr::binary(Operator::And, a, b);
+
+    if cover.cubes.iter().any(|c| c.literals() == 0) {
+        return Expr::Const(true);
+    }
+    let mut counts: std::collections::HashMap<(usize, bool), usize> = std::collections::HashMap::new();
+    for cube in &cover.cubes {
+        for v in cube.vars() {
+            *counts.entry((v, cube.get(v).expect("literal of the cube"))).or_default() += 1;
+        }
+    }
+    let best = counts.into_iter().filter(|&(_, count)| count > 1).max_by_key(|&((v, value), count)| (count, std::cmp::Reverse((v, value))));
+
+    match best {
+        None => cover
+            .cubes
+            .iter()
+            .map(|cube| cube.vars().map(|v| literal(v, cube.get(v).expect("literal of the cube"))).reduce(and).expect("non-empty cube"))
+            .reduce(or)
+            .unwrap_or(Expr::Const(false)),
+        Some(((v, value), _)) => {
+            let (with, without): (Vec<Cube>, Vec<Cube>) = cover.cubes.iter().cloned().partition(|c| c.get(v) == Some(value));
+            let quotient = Cover { n: cover.n, cubes: with }.cofactor(v, value);
+            let factored = match factor(&quotient, variables) {
+                Expr::Const(true) => literal(v, value),
+                q => and(literal(v, value), q),
+            };
+            if without.is_empty() {
+                factored
+            } else {
+                or(factored, factor(&Cover { n: cover.n, cubes: without }, variables))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::parse_precedence;
+
+    fn parse_str(input: &str) -> LogicExpression {
+        parse_precedence(input).unwrap()
+    }
+
+    #[test]
+    fn test_tautology() {
+        let n = 3;
+        let cube = |lits: &[(usize, bool)]| {
+            let mut c = Cube::universe(n);
+            for &(v, value) in lits {
+                c.set(v, Some(value));
+            }
+            c
+        };
+        // a | !a&b | !b
+        let f = Cover { n, cubes: vec![cube(&[(0, true)]), cube(&[(0, false), (1, true)]), cube(&[(1, false)])] };
+        assert!(f.is_tautology());
+        let g = Cover { n, cubes: vec![cube(&[(0, true)]), cube(&[(1, false)])] };
+        assert!(!g.is_tautology());
+    }
+
+    #[test]
+    fn test_estimate_skips_oversized_covers() {
+        let threshold = |n: usize, k: usize| {
+            let names: Vec<String> = (0..n).map(|i| format!(r#""v{}""#, i)).collect();
+            parse_str(&format!("at least {} of {}", k, names.join(", ")))
+        };
+        // Thresholds over variables expand without absorption: the estimate is exact
+        let small = threshold(10, 4);
+        let (on, off) = estimated_cubes(&small.root);
+        assert_eq!((on, off), (cover(&small).unwrap().cubes.len(), complement_cover(&small).unwrap().cubes.len()));
+
+        // C(50, 25) cubes: given up from the estimate, before any cube is built
+        let large = threshold(50, 25);
+        assert!(estimated_cubes(&large.root).0 > ESTIMATE_MARGIN * MAX_COVER_CUBES);
+        let start = std::time::Instant::now();
+        assert!(cover(&large).is_none() && complement_cover(&large).is_none());
+        assert!(crate::psg::normal::cnf(&large).auxiliary() > 0, "Tseitin encoding");
+        assert!(start.elapsed() < std::time::Duration::from_secs(1), "{:?}", start.elapsed());
+    }
+
+    #[test]
+    fn test_espresso_beyond_kmap_limit() {
+        // 16 variables; every pair (x, !x) of product terms merges into one literal
+        let input = (0..8)
+            .map(|i| format!(r#"("a{i}" and "b{i}") or ("a{i}" and not "b{i}")"#))
+            .collect::<Vec<_>>()
+            .join(" or ");
+        let expression = parse_str(&input);
+        assert_eq!(expression.variables.len(), 16);
+
+        let before = cover(&expression).unwrap();
+        let after = espresso(&before);
+        assert_eq!(before.cubes.len(), 16);
+        assert_eq!(after.cubes.len(), 8);
+        assert_eq!(after.literals(), 8);
+
+        let factored = LogicExpression::new(factor(&after, &expression.variables));
+        let mut state = 0x9e3779b97f4a7c15u64;
+        for _ in 0..256 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let value = |var: &Variable| {
+                let i = expression.variables.iter().position(|v| v == var).unwrap();
+                state >> i & 1 == 1
+            };
+            assert_eq!(expression.root.eval(&value), factored.root.eval(&value));
+        }
+    }
+}
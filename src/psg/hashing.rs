@@ -0,0 +1,305 @@
+//! Keyed hashing of variable names
+//!
+//! A plain `SHA-256(name)` of a short name such as `start` is reversed with a
+//! dictionary. Names are therefore hashed as
+//! `HMAC-SHA256(key, DOMAIN || salt || name)` with a fresh salt per statement.
+//! The salt and a commitment to the key are published in the manifest next to
+//! the circuit; a verifier holding the key checks it against the commitment and
+//! recomputes every hash. `VariableHashing::Legacy` reproduces the unsalted
+//! digests of artifacts made before keyed hashing.
+//!
+//! `VariableHasher::preimage_circuit` builds the hash as a Boolean circuit, so a
+//! prover can show in garbled-circuit ZK that they know the name behind a
+//! published digest; `gadgets::conjoin` attaches it to the statement circuit.
+//!
+//! The key is held as a `HashKey`, whose `Debug` output hides the bytes, so
+//! printing a circuit or its options does not leak it.
+
+use std::fmt;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::gadgets::{bytes_to_bits, sha256, Bit, Builder};
+use crate::partitioned_gc::Circuit;
+
+/// Domain separation prefix of variable hashes
+pub const DOMAIN: &[u8] = b"zk-fabric/psg/variable/v1";
+/// Domain separation prefix of key commitments
+const COMMITMENT_DOMAIN: &[u8] = b"zk-fabric/psg/key-commitment/v1";
+
+pub const SCHEME_KEYED: &str = "hmac-sha256-v1";
+pub const SCHEME_LEGACY: &str = "sha256-legacy";
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Secret hashing key; `Debug` shows only its length
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct HashKey(Vec<u8>);
+
+impl HashKey {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        HashKey(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for HashKey {
+    fn from(bytes: Vec<u8>) -> Self {
+        HashKey(bytes)
+    }
+}
+
+impl std::ops::Deref for HashKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HashKey(<{} bytes redacted>)", self.0.len())
+    }
+}
+
+/// How `hash_variables` hashes variable names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableHashing {
+    /// HMAC-SHA256 under `key`; a random key is drawn per statement when `None`
+    Keyed { key: Option<HashKey> },
+    /// Unsalted `SHA-256(name)`, only for reproducing old artifacts
+    Legacy,
+}
+
+impl Default for VariableHashing {
+    fn default() -> Self {
+        VariableHashing::Keyed { key: None }
+    }
+}
+
+/// Public hashing parameters, published with the circuit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HashManifest {
+    pub scheme: String,
+    pub salt: String,           // hex, empty for the legacy scheme
+    pub key_commitment: String, // hex SHA-256(COMMITMENT_DOMAIN || salt || key), empty for the legacy scheme
+}
+
+/// Error raised when a manifest cannot be used to recompute hashes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    UnknownScheme(String),
+    InvalidHex(String),
+    /// The key does not open the manifest's key commitment
+    KeyMismatch,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::UnknownScheme(scheme) => write!(f, "unknown hashing scheme '{}'", scheme),
+            ManifestError::InvalidHex(field) => write!(f, "manifest field '{}' is not valid hex", field),
+            ManifestError::KeyMismatch => write!(f, "key does not match the manifest's key commitment"),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str, field: &str) -> Result<Vec<u8>, ManifestError> {
+    let invalid = || ManifestError::InvalidHex(field.to_string());
+    if !hex.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()).ok_or_else(invalid))
+        .collect()
+}
+
+fn commitment(salt: &[u8], key: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(COMMITMENT_DOMAIN);
+    hasher.update(salt);
+    hasher.update(key);
+    to_hex(&hasher.finalize())
+}
+
+/// Hashes the variable names of one statement
+#[derive(Debug, Clone)]
+pub struct VariableHasher {
+    manifest: HashManifest,
+    salt: Vec<u8>,
+    /// Secret; empty for the legacy scheme
+    key: HashKey,
+}
+
+impl VariableHasher {
+    /// Hasher for a new statement: draws the salt, and the key unless one is given
+    pub fn new(hashing: &VariableHashing) -> Self {
+        match hashing {
+            VariableHashing::Legacy => VariableHasher {
+                manifest: HashManifest { scheme: SCHEME_LEGACY.to_string(), salt: String::new(), key_commitment: String::new() },
+                salt: Vec::new(),
+                key: HashKey::default(),
+            },
+            VariableHashing::Keyed { key } => {
+                let key = key.clone().unwrap_or_else(|| (0..KEY_LEN).map(|_| rand::random::<u8>()).collect::<Vec<u8>>().into());
+                let salt: Vec<u8> = (0..SALT_LEN).map(|_| rand::random::<u8>()).collect();
+                VariableHasher::keyed(key, salt)
+            }
+        }
+    }
+
+    fn keyed(key: HashKey, salt: Vec<u8>) -> Self {
+        let manifest = HashManifest {
+            scheme: SCHEME_KEYED.to_string(),
+            salt: to_hex(&salt),
+            key_commitment: commitment(&salt, &key),
+        };
+        VariableHasher { manifest, salt, key }
+    }
+
+    /// Verifier side: recompute hashes of a published statement with the shared key
+    /// (ignored for the legacy scheme)
+    pub fn from_manifest(manifest: &HashManifest, key: &[u8]) -> Result<Self, ManifestError> {
+        match manifest.scheme.as_str() {
+            SCHEME_LEGACY => Ok(VariableHasher::new(&VariableHashing::Legacy)),
+            SCHEME_KEYED => {
+                let salt = from_hex(&manifest.salt, "salt")?;
+                if commitment(&salt, key) != manifest.key_commitment {
+                    return Err(ManifestError::KeyMismatch);
+                }
+                Ok(VariableHasher::keyed(HashKey::new(key.to_vec()), salt))
+            }
+            other => Err(ManifestError::UnknownScheme(other.to_string())),
+        }
+    }
+
+    pub fn manifest(&self) -> &HashManifest {
+        &self.manifest
+    }
+
+    /// The secret key, to be shared with verifiers only
+    pub fn key(&self) -> &HashKey {
+        &self.key
+    }
+
+    /// Hex digest of a variable name
+    pub fn hash(&self, name: &str) -> String {
+        if self.manifest.scheme == SCHEME_LEGACY {
+            return to_hex(&Sha256::digest(name.as_bytes()));
+        }
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(DOMAIN);
+        mac.update(&self.salt);
+        mac.update(name.as_bytes());
+        to_hex(&mac.finalize().into_bytes())
+    }
+
+    /// Circuit over the `8 * name_len` bits of a name (most significant bit of each
+    /// byte first, see `gadgets::bytes_to_bits`) that is true when the name hashes
+    /// to `digest_hex`. Salt and key are constants of the circuit, so only the
+    /// garbler (verifier) may hold it; the name length is fixed and thus public.
+    pub fn preimage_circuit(&self, name_len: usize, digest_hex: &str) -> Result<Circuit, ManifestError> {
+        let digest = from_hex(digest_hex, "digest")?;
+        if digest.len() != 32 {
+            return Err(ManifestError::InvalidHex("digest".to_string()));
+        }
+        let mut builder = Builder::new();
+        let name = builder.inputs(8 * name_len);
+        let hash = if self.manifest.scheme == SCHEME_LEGACY {
+            sha256::sha256(&mut builder, &name)
+        } else {
+            let mut message: Vec<Bit> =
+                bytes_to_bits(&[DOMAIN, &self.salt[..]].concat()).into_iter().map(Bit::Const).collect();
+            message.extend(name);
+            sha256::hmac_sha256(&mut builder, &self.key, &message)
+        };
+        let matches = builder.equals_const(&hash, &bytes_to_bits(&digest));
+        Ok(builder.finish(&[matches]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::Aig;
+
+    #[test]
+    fn test_legacy_digest_is_plain_sha256() {
+        let hasher = VariableHasher::new(&VariableHashing::Legacy);
+        assert_eq!(hasher.hash("start"), "cced28c6dc3f99c2396a5eaad732bf6b28142335892b1cd0e6af6cdb53f5ccfa");
+        assert_eq!(hasher.manifest().scheme, SCHEME_LEGACY);
+    }
+
+    #[test]
+    fn test_keyed_hashes_are_salted_and_verifiable() {
+        let key = b"session key".to_vec();
+        let prover = VariableHasher::new(&VariableHashing::Keyed { key: Some(key.clone().into()) });
+        let other = VariableHasher::new(&VariableHashing::Keyed { key: Some(key.clone().into()) });
+        let legacy = VariableHasher::new(&VariableHashing::Legacy);
+        assert_ne!(prover.hash("start"), legacy.hash("start"));
+        assert_ne!(prover.hash("start"), other.hash("start"), "salt is fresh per statement");
+
+        let verifier = VariableHasher::from_manifest(prover.manifest(), &key).unwrap();
+        assert_eq!(verifier.hash("start"), prover.hash("start"));
+        assert_eq!(VariableHasher::from_manifest(prover.manifest(), b"guess").unwrap_err(), ManifestError::KeyMismatch);
+
+        let random = VariableHasher::new(&VariableHashing::default());
+        assert_eq!(random.key().len(), KEY_LEN);
+        assert!(VariableHasher::from_manifest(random.manifest(), random.key()).is_ok());
+        assert_eq!(format!("{:?}", random.key()), "HashKey(<32 bytes redacted>)");
+
+        // Printing a generated circuit does not reveal its key
+        let options = crate::psg::PsgOptions { hashing: VariableHashing::Keyed { key: Some(key.clone().into()) }, ..Default::default() };
+        let circuit = crate::psg::polylithic_syntax_gen_with(r#""a" and "b""#, &options).unwrap();
+        assert_eq!(circuit.hash_key.as_bytes(), key.as_slice());
+        assert!(format!("{:#?}", circuit).contains("hash_key: HashKey(<11 bytes redacted>)"));
+    }
+
+    #[test]
+    fn test_preimage_circuit_composes_with_statement() {
+        use crate::gadgets::conjoin;
+        use crate::partitioned_gc::PartitionedGCScheme;
+        use crate::psg::{polylithic_syntax_gen_with, PsgOptions};
+
+        let hasher = VariableHasher::new(&VariableHashing::Keyed { key: Some(b"session key".to_vec().into()) });
+        let preimage = hasher.preimage_circuit(5, &hasher.hash("start")).unwrap();
+        let legacy = VariableHasher::new(&VariableHashing::Legacy);
+        let legacy_preimage = legacy.preimage_circuit(5, &legacy.hash("start")).unwrap();
+        for circuit in [&preimage, &legacy_preimage] {
+            let aig = Aig::from_circuit(circuit);
+            assert_eq!(aig.eval(&bytes_to_bits(b"start")), vec![true]);
+            assert_eq!(aig.eval(&bytes_to_bits(b"stars")), vec![false]);
+        }
+        assert!(hasher.preimage_circuit(5, "not hex").is_err());
+
+        // Statement "a" and "b" proven together with knowledge of "start"
+        let options = PsgOptions::default();
+        let statement = polylithic_syntax_gen_with(r#""a" and "b""#, &options).unwrap().circuit;
+        let joint = conjoin(&[&statement, &preimage]);
+        let garbled = PartitionedGCScheme::garble_circuit(&joint).unwrap();
+        let run = |a: u8, name: &[u8]| {
+            let mut inputs = vec![a, 1];
+            inputs.extend(bytes_to_bits(name).into_iter().map(u8::from));
+            let mut labels = PartitionedGCScheme::encode_inputs(&garbled, &inputs);
+            PartitionedGCScheme::evaluate_garbled(&garbled, &mut labels);
+            PartitionedGCScheme::decode_outputs(&garbled, &labels).unwrap()
+        };
+        assert_eq!(run(1, b"start"), vec![1]);
+        assert_eq!(run(1, b"stop!"), vec![0]);
+        assert_eq!(run(0, b"start"), vec![0]);
+    }
+}
@@ -0,0 +1,208 @@
+//! Unsigned integer variables and comparison predicates
+//!
+//! A variable declared as `VarType::UInt { width }` (see `PsgOptions::types`)
+//! stands for `width` Boolean variables, one per bit, least significant bit
+//! first. They are allocated next to each other, so the integer enters the
+//! circuit as a contiguous vector of input wires.
+//!
+//! Comparisons are lowered to Boolean expressions over the bits: ripple
+//! comparators scanning from the least significant bit,
+//!
+//! - `x == y` = `(x0 <-> y0) && (x1 <-> y1) && ...`
+//! - `x < y`: `lt = (!xi && yi) || ((xi <-> yi) && lt)` for `i = 0..width`,
+//!
+//! with the other predicates derived by swapping or negating. Constants are
+//! folded bit by bit, so comparing against a number costs no XOR at all.
+
+use std::fmt;
+
+use super::{Expr, Operator, Variable};
+
+/// Widest integer variable (constants are `u64`)
+pub const MAX_INTEGER_WIDTH: usize = 64;
+
+/// Declared type of a statement variable; undeclared variables are Boolean
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VarType {
+    #[default]
+    Bool,
+    /// Unsigned integer of `width` bits
+    UInt { width: usize },
+}
+
+/// Comparison predicate between integer operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+
+    /// Predicate true exactly where `self` is false
+    pub fn negate(&self) -> Comparison {
+        match self {
+            Comparison::Lt => Comparison::Ge,
+            Comparison::Le => Comparison::Gt,
+            Comparison::Eq => Comparison::Ne,
+            Comparison::Ne => Comparison::Eq,
+            Comparison::Gt => Comparison::Le,
+            Comparison::Ge => Comparison::Lt,
+        }
+    }
+
+    pub fn eval(&self, left: u64, right: u64) -> bool {
+        match self {
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// Bit variables of an integer variable, least significant first
+pub fn bits(var: &Variable, width: usize) -> Vec<Expr> {
+    (0..width).map(|i| Expr::Var(Variable { bit: Some(i), ..var.clone() })).collect()
+}
+
+/// Bits of a constant, least significant first
+pub fn constant(value: u64) -> Vec<Expr> {
+    let width = (u64::BITS - value.leading_zeros()) as usize;
+    (0..width).map(|i| Expr::Const(value >> i & 1 == 1)).collect()
+}
+
+pub(super) fn not(a: Expr) -> Expr {
+    match a {
+        Expr::Const(value) => Expr::Const(!value),
+        Expr::Not(inner) => *inner,
+        other => Expr::Not(Box::new(other)),
+    }
+}
+
+pub(super) fn and(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Const(false), _) | (_, Expr::Const(false)) => Expr::Const(false),
+        (Expr::Const(true), other) | (other, Expr::Const(true)) => other,
+        (a, b) => Expr::binary(Operator::And, a, b),
+    }
+}
+
+pub(super) fn or(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Const(true), _) | (_, Expr::Const(true)) => Expr::Const(true),
+        (Expr::Const(false), other) | (other, Expr::Const(false)) => other,
+        (a, b) => Expr::binary(Operator::Or, a, b),
+    }
+}
+
+fn iff(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Const(value), other) | (other, Expr::Const(value)) => if value { other } else { not(other) },
+        (a, b) => Expr::binary(Operator::Iff, a, b),
+    }
+}
+
+fn equal(x: &[Expr], y: &[Expr]) -> Expr {
+    x.iter().zip(y).fold(Expr::Const(true), |acc, (a, b)| and(acc, iff(a.clone(), b.clone())))
+}
+
+fn less(x: &[Expr], y: &[Expr]) -> Expr {
+    x.iter().zip(y).fold(Expr::Const(false), |acc, (a, b)| match (a, b) {
+        (Expr::Const(false), b) => or(b.clone(), acc),
+        (Expr::Const(true), b) => and(b.clone(), acc),
+        (a, Expr::Const(true)) => or(not(a.clone()), acc),
+        (a, Expr::Const(false)) => and(not(a.clone()), acc),
+        (a, b) => {
+            let below = and(not(a.clone()), b.clone());
+            or(below, and(iff(a.clone(), b.clone()), acc))
+        }
+    })
+}
+
+/// Boolean expression of `left op right` over operand bits (least significant
+/// first); the shorter operand is zero-extended
+pub fn compare(op: Comparison, left: &[Expr], right: &[Expr]) -> Expr {
+    let width = left.len().max(right.len());
+    let extend = |bits: &[Expr]| {
+        let mut bits = bits.to_vec();
+        bits.resize(width, Expr::Const(false));
+        bits
+    };
+    let (x, y) = (extend(left), extend(right));
+    match op {
+        Comparison::Eq => equal(&x, &y),
+        Comparison::Ne => not(equal(&x, &y)),
+        Comparison::Lt => less(&x, &y),
+        Comparison::Gt => less(&y, &x),
+        Comparison::Le => not(less(&y, &x)),
+        Comparison::Ge => not(less(&x, &y)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPS: [Comparison; 6] =
+        [Comparison::Lt, Comparison::Le, Comparison::Eq, Comparison::Ne, Comparison::Gt, Comparison::Ge];
+
+    #[test]
+    fn test_comparators_match_integer_semantics() {
+        let (x, y) = (Variable::new("x"), Variable::new("y"));
+        let value = |var: &Variable, a: u64, b: u64| {
+            let word = if var.name == "x" { a } else { b };
+            word >> var.bit.unwrap() & 1 == 1
+        };
+        for op in OPS {
+            // Two variables of different widths
+            let expr = compare(op, &bits(&x, 4), &bits(&y, 3));
+            for a in 0..16 {
+                for b in 0..8 {
+                    assert_eq!(expr.eval(&|v| value(v, a, b)), op.eval(a, b), "{} {} {}", a, op, b);
+                }
+            }
+            // A variable against constants, including ones wider than the variable
+            for c in [0, 5, 15, 16, 300] {
+                let expr = compare(op, &bits(&x, 4), &constant(c));
+                for a in 0..16 {
+                    assert_eq!(expr.eval(&|v| value(v, a, 0)), op.eval(a, c), "{} {} {}", a, op, c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_constant_comparisons_fold() {
+        let x = Variable::new("x");
+        assert_eq!(compare(Comparison::Ge, &bits(&x, 4), &constant(0)), Expr::Const(true));
+        assert_eq!(compare(Comparison::Eq, &bits(&x, 4), &constant(16)), Expr::Const(false));
+        let greater = compare(Comparison::Gt, &bits(&x, 8), &constant(60));
+        let mut occurrences = 0;
+        greater.for_each_var(&mut |_| occurrences += 1);
+        assert_eq!(occurrences, 8, "one literal per bit");
+        assert!(!greater.render(&|v| v.to_string()).contains(Operator::Iff.symbol()));
+    }
+}
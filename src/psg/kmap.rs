@@ -0,0 +1,341 @@
+//! Exact two-level minimization (Karnaugh map / Quine–McCluskey)
+//!
+//! The statement's truth table is enumerated (up to `MAX_KMAP_VARIABLES` inputs),
+//! prime implicants are generated with Quine–McCluskey, and a cover is chosen with
+//! essential primes first, then Petrick's method, falling back to a greedy cover
+//! when Petrick's expansion grows too large.
+//!
+//! `threshold` recognizes the same truth table as a cardinality constraint
+//! (`at least k`, `exactly k`, `not exactly k` of some literals), whose adder
+//! circuit is far smaller than the `C(n, k)` products of its sum of products.
+
+use std::collections::HashSet;
+
+use super::{Comparison, Expr, LogicExpression, Variable};
+
+/// Largest number of variables minimized exactly (4096 truth-table rows)
+pub const MAX_KMAP_VARIABLES: usize = 12;
+
+/// Largest number of partial products kept while expanding Petrick's product
+const PETRICK_LIMIT: usize = 4096;
+
+/// A product term: variable `i` is fixed to bit `i` of `value` unless bit `i` of
+/// `mask` is set (don't care)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Implicant {
+    pub value: u32,
+    pub mask: u32,
+}
+
+impl Implicant {
+    pub fn covers(&self, minterm: u32) -> bool {
+        minterm & !self.mask == self.value
+    }
+
+    /// Number of literals over `n` variables
+    pub fn literals(&self, n: usize) -> usize {
+        n - (self.mask & ((1u32 << n) - 1)).count_ones() as usize
+    }
+}
+
+/// Result of two-level minimization
+#[derive(Debug, Clone)]
+pub struct Minimized {
+    /// Minimized sum of products over the expression's variables
+    pub expression: LogicExpression,
+    pub prime_implicants: usize,
+    pub terms: Vec<Implicant>,
+}
+
+/// Minterms of `expression`, with variable `i` as bit `i`
+pub fn minterms(expression: &LogicExpression) -> Vec<u32> {
+    let n = expression.variables.len();
+    (0..1u32 << n)
+        .filter(|&row| {
+            expression.root.eval(&|var| {
+                let i = expression.variables.iter().position(|v| v == var).expect("variable collected by LogicExpression");
+                row >> i & 1 == 1
+            })
+        })
+        .collect()
+}
+
+/// Quine–McCluskey prime implicant generation over `n` variables
+pub fn prime_implicants(minterms: &[u32], n: usize) -> Vec<Implicant> {
+    let mut current: HashSet<Implicant> = minterms.iter().map(|&value| Implicant { value, mask: 0 }).collect();
+    let mut primes = Vec::new();
+
+    while !current.is_empty() {
+        let mut merged = HashSet::new();
+        let mut used = HashSet::new();
+        for imp in &current {
+            for bit in (0..n).map(|b| 1u32 << b) {
+                if imp.mask & bit != 0 || imp.value & bit != 0 {
+                    continue;
+                }
+                let partner = Implicant { value: imp.value | bit, mask: imp.mask };
+                if current.contains(&partner) {
+                    merged.insert(Implicant { value: imp.value, mask: imp.mask | bit });
+                    used.insert(*imp);
+                    used.insert(partner);
+                }
+            }
+        }
+        primes.extend(current.iter().filter(|imp| !used.contains(imp)));
+        current = merged;
+    }
+
+    primes.sort();
+    primes
+}
+
+/// Choose a minimum-cost set of primes covering every minterm
+pub fn select_cover(primes: &[Implicant], minterms: &[u32], n: usize) -> Vec<Implicant> {
+    let mut chosen: Vec<usize> = Vec::new();
+    let mut uncovered: Vec<u32> = minterms.to_vec();
+
+    // Essential primes: the only prime covering some minterm
+    for &m in minterms {
+        let covering: Vec<usize> = (0..primes.len()).filter(|&i| primes[i].covers(m)).collect();
+        if covering.len() == 1 && !chosen.contains(&covering[0]) {
+            chosen.push(covering[0]);
+        }
+    }
+    uncovered.retain(|&m| !chosen.iter().any(|&i| primes[i].covers(m)));
+
+    let candidates: Vec<usize> = (0..primes.len())
+        .filter(|i| !chosen.contains(i) && uncovered.iter().any(|&m| primes[*i].covers(m)))
+        .collect();
+    let rest = petrick(primes, &candidates, &uncovered, n).unwrap_or_else(|| greedy(primes, &candidates, &uncovered, n));
+    chosen.extend(rest);
+
+    let mut cover: Vec<Implicant> = chosen.into_iter().map(|i| primes[i]).collect();
+    cover.sort();
+    cover
+}
+
+/// Petrick's method over `candidates`; `None` when the expansion exceeds `PETRICK_LIMIT`
+fn petrick(primes: &[Implicant], candidates: &[usize], uncovered: &[u32], n: usize) -> Option<Vec<usize>> {
+    if candidates.len() > 64 {
+        return None;
+    }
+    // Each product is a bitset over `candidates`
+    let mut products: Vec<u64> = vec![0];
+    for &m in uncovered {
+        let clause: Vec<u64> = (0..candidates.len())
+            .filter(|&j| primes[candidates[j]].covers(m))
+            .map(|j| 1u64 << j)
+            .collect();
+        let mut next: Vec<u64> = Vec::new();
+        for p in &products {
+            for c in &clause {
+                let product = p | c;
+                if !next.iter().any(|q| q & product == *q) {
+                    next.retain(|q| q & product != product);
+                    next.push(product);
+                }
+            }
+        }
+        if next.len() > PETRICK_LIMIT {
+            return None;
+        }
+        products = next;
+    }
+
+    let cost = |product: &u64| {
+        let selected = (0..candidates.len()).filter(|j| product >> j & 1 == 1);
+        let literals: usize = selected.map(|j| primes[candidates[j]].literals(n)).sum();
+        (product.count_ones(), literals)
+    };
+    let best = products.iter().min_by_key(|p| cost(p))?;
+    Some((0..candidates.len()).filter(|j| best >> j & 1 == 1).map(|j| candidates[j]).collect())
+}
+
+/// Greedy cover: repeatedly take the prime covering most uncovered minterms
+fn greedy(primes: &[Implicant], candidates: &[usize], uncovered: &[u32], n: usize) -> Vec<usize> {
+    let mut uncovered = uncovered.to_vec();
+    let mut chosen = Vec::new();
+    while !uncovered.is_empty() {
+        let best = *candidates
+            .iter()
+            .filter(|i| !chosen.contains(*i))
+            .max_by_key(|&&i| {
+                let covered = uncovered.iter().filter(|&&m| primes[i].covers(m)).count();
+                (covered, std::cmp::Reverse(primes[i].literals(n)))
+            })
+            .expect("every minterm is covered by some prime");
+        uncovered.retain(|&m| !primes[best].covers(m));
+        chosen.push(best);
+    }
+    chosen
+}
+
+/// Sum-of-products expression for a cover over `variables`
+pub fn sop_expr(cover: &[Implicant], variables: &[Variable]) -> Expr {
+    let n = variables.len();
+    let term = |imp: &Implicant| {
+        (0..n)
+            .filter(|i| imp.mask >> i & 1 == 0)
+            .map(|i| {
+                let var = Expr::Var(variables[i].clone());
+                if imp.value >> i & 1 == 1 { var } else { Expr::Not(Box::new(var)) }
+            })
+            .reduce(|acc, lit| Expr::binary(super::Operator::And, acc, lit))
+            .unwrap_or(Expr::Const(true))
+    };
+    cover
+        .iter()
+        .map(term)
+        .reduce(|acc, t| Expr::binary(super::Operator::Or, acc, t))
+        .unwrap_or(Expr::Const(false))
+}
+
+/// Cardinality constraint over literals with the truth table of `expression`,
+/// when there is one over at least three variables that is not a plain AND or OR
+pub fn threshold(expression: &LogicExpression) -> Option<Expr> {
+    let n = expression.variables.len();
+    if n > MAX_KMAP_VARIABLES {
+        return None;
+    }
+    let mut table = vec![false; 1 << n];
+    for row in minterms(expression) {
+        table[row as usize] = true;
+    }
+    // Dependent variables, negative when the function never rises with them
+    let mut literals = Vec::new();
+    for i in 0..n {
+        let bit = 1 << i;
+        let rows = (0..1usize << n).filter(|row| row & bit == 0);
+        let (mut rises, mut falls) = (false, false);
+        for row in rows {
+            rises |= !table[row] && table[row | bit];
+            falls |= table[row] && !table[row | bit];
+        }
+        if rises || falls {
+            literals.push((i, rises || !falls));
+        }
+    }
+    let m = literals.len();
+    if m < 3 {
+        return None;
+    }
+
+    // The value must depend on the number of true literals only
+    let mut by_count: Vec<Option<bool>> = vec![None; m + 1];
+    for assignment in 0..1usize << m {
+        let row: usize = literals
+            .iter()
+            .enumerate()
+            .filter(|&(j, &(_, positive))| (assignment >> j & 1 == 1) == positive)
+            .map(|(_, &(i, _))| 1 << i)
+            .sum();
+        let count = assignment.count_ones() as usize;
+        match by_count[count] {
+            Some(value) if value != table[row] => return None,
+            _ => by_count[count] = Some(table[row]),
+        }
+    }
+    let values: Vec<bool> = by_count.into_iter().map(|v| v.expect("every count occurs")).collect();
+    let trues: Vec<usize> = (0..=m).filter(|&c| values[c]).collect();
+    let (op, k) = if let Some(k) = (2..m).find(|&k| (0..=m).all(|c| values[c] == (c >= k))) {
+        (Comparison::Ge, k)
+    } else if trues.len() == 1 && (1..m).contains(&trues[0]) {
+        (Comparison::Eq, trues[0])
+    } else if trues.len() == m && values[0] && values[m] {
+        (Comparison::Ne, (0..=m).find(|&c| !values[c]).expect("one count is false"))
+    } else {
+        return None;
+    };
+
+    let operands = literals
+        .iter()
+        .map(|&(i, positive)| {
+            let var = Expr::Var(expression.variables[i].clone());
+            if positive { var } else { Expr::Not(Box::new(var)) }
+        })
+        .collect();
+    Some(Expr::Threshold { op, k, operands })
+}
+
+/// Exact two-level minimization, `None` above `MAX_KMAP_VARIABLES` variables
+pub fn minimize(expression: &LogicExpression) -> Option<Minimized> {
+    let n = expression.variables.len();
+    if n > MAX_KMAP_VARIABLES {
+        return None;
+    }
+    let minterms = minterms(expression);
+    let primes = prime_implicants(&minterms, n);
+    let terms = select_cover(&primes, &minterms, n);
+    let root = sop_expr(&terms, &expression.variables);
+
+    Some(Minimized {
+        expression: LogicExpression {
+            variables: expression.variables.clone(),
+            root,
+            consequent: expression.consequent.clone(),
+        },
+        prime_implicants: primes.len(),
+        terms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::parse_precedence;
+
+    fn parse_str(input: &str) -> LogicExpression {
+        parse_precedence(input).unwrap()
+    }
+
+    #[test]
+    fn test_prime_implicants_classic() {
+        // f(a,b,c,d) = Σm(4,8,10,11,12,15) + (no don't cares): textbook example
+        let minterms = [4, 8, 10, 11, 12, 15];
+        let primes = prime_implicants(&minterms, 4);
+        let cover = select_cover(&primes, &minterms, 4);
+        for m in 0..16 {
+            assert_eq!(cover.iter().any(|imp| imp.covers(m)), minterms.contains(&m), "minterm {}", m);
+        }
+        assert_eq!(cover.len(), 3);
+    }
+
+    #[test]
+    fn test_minimize_preserves_truth_table() {
+        for input in [
+            r#"("a" and "b") or ("a" and not "b") or ("c" and "a")"#,
+            r#""a" xor "b" xor "c""#,
+            r#"the "x" only if "y" or ("z" and not "y")"#,
+        ] {
+            let expression = parse_str(input);
+            let minimized = minimize(&expression).unwrap();
+            assert_eq!(minterms(&expression), minterms(&minimized.expression), "{}", input);
+        }
+
+        let redundant = minimize(&parse_str(r#"("a" and "b") or ("a" and not "b")"#)).unwrap();
+        assert_eq!(redundant.expression.to_string(), "a");
+    }
+
+    #[test]
+    fn test_threshold_recognized_from_sum_of_products() {
+        let majority = parse_str(r#"("a" and "b") or ("a" and "c") or ("b" and "c") or ("a" and "d" and not "a")"#);
+        let recognized = threshold(&majority).unwrap();
+        assert_eq!(recognized.render(&|v| v.to_string()), "#(a, b, c) >= 2");
+
+        let one_hot = parse_str(r#"("x" and not "y" and "z") or (not "x" and "y" and "z") or ("x" and "y" and not "z")"#);
+        assert_eq!(threshold(&one_hot).unwrap().render(&|v| v.to_string()), "#(x, y, z) == 2");
+        let at_most_one = parse_str(r#"not ("p" and "q") and not ("p" and "r") and not ("q" and "r")"#);
+        assert_eq!(threshold(&at_most_one).unwrap().render(&|v| v.to_string()), "#(!p, !q, !r) >= 2");
+
+        assert_eq!(threshold(&parse_str(r#""a" and "b" and "c""#)), None, "plain AND");
+        assert_eq!(threshold(&parse_str(r#""a" and ("b" or "c")"#)), None, "not symmetric");
+    }
+
+    #[test]
+    fn test_minimize_constants() {
+        let tautology = minimize(&parse_str(r#""a" or not "a""#)).unwrap();
+        assert_eq!(tautology.expression.root, Expr::Const(true));
+        let contradiction = minimize(&parse_str(r#""a" and not "a""#)).unwrap();
+        assert_eq!(contradiction.expression.root, Expr::Const(false));
+    }
+}
@@ -0,0 +1,395 @@
+//! Lexer for PSG composite statements
+//!
+//! Splits a statement into quoted variables, operator keywords, parentheses and
+//! the filler words in between. Keywords are matched as whole words (or whole
+//! multi-word phrases) from a per-language `Lexicon`. Every token keeps the byte span it was read from
+//! so the parser can point at the offending part of the statement.
+//!
+//! Variables may be quoted with `"..."`, `'...'`, `` `...` `` or typographic quotes,
+//! may span several words, and may contain the quote character escaped as `\"`.
+//! Inside `'...'` and `‘...’` an apostrophe followed by a letter or digit
+//! (`'driver's seat'`, `‘driver’s seat’`) does not close the quote.
+//!
+//! Comparisons are spelled as lexicon phrases (`greater than`) or as symbols
+//! (`<`, `<=`, `=`, `==`, `!=`, `>`, `>=`, `≤`, `≠`, `≥`); numbers stay words.
+
+use super::lexicon::{CorrelativePair, Keyword, Lexicon};
+use super::{Comparison, Connective, Operator, ParseError};
+
+/// Byte range `[start, end)` of a token in the source statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Smallest span covering both `self` and `other`
+    pub fn join(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+/// Kind of a lexed token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A quoted variable name, without its quotes
+    Variable(String),
+    /// A logic keyword (`and`, `or`, `xor`, `not`)
+    Operator(Operator),
+    /// A conditional connective (`if`, `only if`, `then`, ...)
+    Connective(Connective),
+    /// A comparison of integers (`greater than`, `<=`, ...)
+    Comparison(Comparison),
+    LParen,
+    RParen,
+    /// Any other word of the statement (the "rest of the words")
+    Word(String),
+    /// Punctuation that carries no logic (`,`, `.`, `;`, ...)
+    Punct(char),
+}
+
+/// A token together with its source span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '\''
+}
+
+/// Closing quote for an opening quote character
+fn closing_quote(open: char) -> Option<char> {
+    match open {
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        '`' => Some('`'),
+        '\u{201C}' => Some('\u{201D}'),
+        '\u{2018}' => Some('\u{2019}'),
+        _ => None,
+    }
+}
+
+/// Read a quoted variable starting at byte `start`, returning its normalized name
+/// and the byte offset just past the closing quote
+fn lex_quoted(input: &str, start: usize, open: char, close: char) -> Result<(String, usize), ParseError> {
+    let body = start + open.len_utf8();
+    let mut chars = input[body..].char_indices().map(|(i, c)| (body + i, c)).peekable();
+    let mut name = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some((_, escaped)) if escaped == close || escaped == open || escaped == '\\' => name.push(escaped),
+                Some((_, other)) => {
+                    name.push('\\');
+                    name.push(other);
+                }
+                None => break,
+            }
+        } else if c == close {
+            // The closing quote of '...' and ‘...’ doubles as the apostrophe
+            let apostrophe = matches!(close, '\'' | '\u{2019}')
+                && chars.peek().is_some_and(|&(_, next)| next.is_alphanumeric());
+            if !apostrophe {
+                let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+                if name.is_empty() {
+                    return Err(ParseError::new("empty quoted variable", Span::new(start, i + c.len_utf8())));
+                }
+                return Ok((name, i + c.len_utf8()));
+            }
+            name.push(c);
+        } else {
+            name.push(c);
+        }
+    }
+
+    Err(ParseError::new("unterminated quoted variable", Span::new(start, input.len())))
+}
+
+/// Comparison symbol at the start of `input`, with its length in bytes
+fn lex_comparison(input: &str) -> Option<(Comparison, usize)> {
+    let symbols = [
+        ("<=", Comparison::Le),
+        (">=", Comparison::Ge),
+        ("==", Comparison::Eq),
+        ("!=", Comparison::Ne),
+        ("<", Comparison::Lt),
+        (">", Comparison::Gt),
+        ("=", Comparison::Eq),
+        ("\u{2264}", Comparison::Le),
+        ("\u{2265}", Comparison::Ge),
+        ("\u{2260}", Comparison::Ne),
+    ];
+    symbols.iter().find(|(symbol, _)| input.starts_with(symbol)).map(|&(symbol, c)| (c, symbol.len()))
+}
+
+/// Whether `closer` follows in the clause that `rest` starts: at the same
+/// parenthesis depth, before a closing parenthesis, `.` or `;`
+fn closes_in_clause(rest: &[Token], closer: &str) -> bool {
+    let mut depth = 0usize;
+    for token in rest {
+        match &token.kind {
+            TokenKind::LParen => depth += 1,
+            TokenKind::RParen if depth == 0 => return false,
+            TokenKind::RParen => depth -= 1,
+            TokenKind::Punct('.' | ';') => return false,
+            TokenKind::Word(w) if depth == 0 && w.to_lowercase() == closer => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Replace runs of adjacent words that spell a keyword phrase by a single keyword token
+fn classify_keywords(raw: Vec<Token>, lexicon: &Lexicon) -> Vec<Token> {
+    let mut tokens = Vec::with_capacity(raw.len());
+    // Closers of open correlative pairs, innermost last
+    let mut open_pairs: Vec<&CorrelativePair> = Vec::new();
+    let mut i = 0;
+    while i < raw.len() {
+        let lowered: Vec<String> = raw[i..]
+            .iter()
+            .take(lexicon.max_phrase_len())
+            .map_while(|t| match &t.kind {
+                TokenKind::Word(w) => Some(w.to_lowercase()),
+                _ => None,
+            })
+            .collect();
+        let words: Vec<&str> = lowered.iter().map(String::as_str).collect();
+
+        // An opener without its closer later in the clause is a plain word
+        let opener = words.first().and_then(|w| lexicon.pairs.iter().find(|p| p.opener == *w));
+        if let Some(pair) = opener.filter(|p| closes_in_clause(&raw[i + 1..], &p.closer)) {
+            open_pairs.push(pair);
+            i += 1;
+            continue;
+        }
+        if let Some(pos) = words.first().and_then(|w| open_pairs.iter().rposition(|p| p.closer == *w)) {
+            let pair = open_pairs.remove(pos);
+            tokens.push(Token { kind: TokenKind::Operator(pair.operator), span: raw[i].span });
+            i += 1;
+            continue;
+        }
+
+        match lexicon.longest_match(&words) {
+            Some((len, keyword)) => {
+                let kind = match keyword {
+                    Keyword::Operator(op) => TokenKind::Operator(op),
+                    Keyword::Connective(c) => TokenKind::Connective(c),
+                    Keyword::Comparison(c) => TokenKind::Comparison(c),
+                };
+                let span = raw[i].span.join(raw[i + len - 1].span);
+                tokens.push(Token { kind, span });
+                i += len;
+            }
+            None => {
+                tokens.push(raw[i].clone());
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/// Tokenize a composite statement, reading keywords from `lexicon`
+pub fn tokenize(input: &str, lexicon: &Lexicon) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if let Some(close) = closing_quote(c) {
+            let (name, end) = lex_quoted(input, start, c, close)?;
+            while chars.peek().is_some_and(|&(i, _)| i < end) {
+                chars.next();
+            }
+            tokens.push(Token { kind: TokenKind::Variable(name), span: Span::new(start, end) });
+        } else if c == '(' || c == ')' {
+            chars.next();
+            let kind = if c == '(' { TokenKind::LParen } else { TokenKind::RParen };
+            tokens.push(Token { kind, span: Span::new(start, start + 1) });
+        } else if let Some((comparison, len)) = lex_comparison(&input[start..]) {
+            while chars.peek().is_some_and(|&(i, _)| i < start + len) {
+                chars.next();
+            }
+            tokens.push(Token { kind: TokenKind::Comparison(comparison), span: Span::new(start, start + len) });
+        } else if is_word_char(c) {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek() {
+                if !is_word_char(ch) {
+                    break;
+                }
+                end = i + ch.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Word(input[start..end].to_string()),
+                span: Span::new(start, end),
+            });
+        } else {
+            chars.next();
+            tokens.push(Token {
+                kind: TokenKind::Punct(c),
+                span: Span::new(start, start + c.len_utf8()),
+            });
+        }
+    }
+
+    Ok(classify_keywords(tokens, lexicon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_spans_and_keywords() {
+        let input = r#"the "start" button AND ("brake" or not "door")"#;
+        let tokens = tokenize(input, &Lexicon::english()).unwrap();
+
+        let vars: Vec<(&str, Span)> = tokens
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::Variable(v) => Some((v.as_str(), t.span)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vars.len(), 3);
+        assert_eq!(vars[0].0, "start");
+        assert_eq!(&input[vars[0].1.start..vars[0].1.end], "\"start\"");
+
+        assert!(tokens.iter().any(|t| t.kind == TokenKind::Operator(Operator::And)));
+        assert!(tokens.iter().any(|t| t.kind == TokenKind::Operator(Operator::Not)));
+        assert_eq!(tokens.iter().filter(|t| t.kind == TokenKind::LParen).count(), 1);
+    }
+
+    #[test]
+    fn test_tokenize_connective_phrases() {
+        let tokens = tokenize(r#""a" if and only if "b", "c" only if "d" If "e""#, &Lexicon::english()).unwrap();
+        let connectives: Vec<Connective> = tokens
+            .iter()
+            .filter_map(|t| match t.kind {
+                TokenKind::Connective(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(connectives, vec![Connective::Iff, Connective::OnlyIf, Connective::If]);
+        assert!(!tokens.iter().any(|t| t.kind == TokenKind::Operator(Operator::And)));
+    }
+
+    #[test]
+    fn test_tokenize_quoted_variables() {
+        let input = "the \"start  button\", the 'driver's seat' and `gear`; \u{201C}fuel cap\u{201D} or \"the \\\"big\\\" one\" \
+                     or the \u{2018}driver\u{2019}s door\u{2019}";
+        let tokens = tokenize(input, &Lexicon::english()).unwrap();
+        let vars: Vec<&Token> = tokens
+            .iter()
+            .filter(|t| matches!(t.kind, TokenKind::Variable(_)))
+            .collect();
+        let names: Vec<String> = vars
+            .iter()
+            .map(|t| match &t.kind {
+                TokenKind::Variable(v) => v.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(names, vec!["start button", "driver's seat", "gear", "fuel cap", "the \"big\" one", "driver\u{2019}s door"]);
+        assert_eq!(&input[vars[0].span.start..vars[0].span.end], "\"start  button\"");
+        assert_eq!(&input[vars[1].span.start..vars[1].span.end], "'driver's seat'");
+        assert_eq!(&input[vars[5].span.start..vars[5].span.end], "\u{2018}driver\u{2019}s door\u{2019}");
+        assert!(tokens.iter().any(|t| t.kind == TokenKind::Punct(',')));
+    }
+
+    fn operators(input: &str, lexicon: &Lexicon) -> Vec<Operator> {
+        tokenize(input, lexicon)
+            .unwrap()
+            .iter()
+            .filter_map(|t| match t.kind {
+                TokenKind::Operator(op) => Some(op),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_operators_on_word_boundaries_in_order() {
+        let en = Lexicon::english();
+        assert!(operators(r#"the "door" for the "motor" of Orlando"#, &en).is_empty());
+        assert_eq!(operators(r#""a" XOR "b" Or "c" and "d""#, &en), vec![Operator::Xor, Operator::Or, Operator::And]);
+        assert_eq!(
+            operators(r#""a" plus "b" as well as either "c" or "d""#, &en),
+            vec![Operator::And, Operator::And, Operator::Or]
+        );
+    }
+
+    #[test]
+    fn test_custom_and_foreign_lexicons() {
+        let mut en = Lexicon::english();
+        en.add_pair("either", "or", Operator::Xor);
+        en.add_keyword("along with", Keyword::Operator(Operator::And));
+        assert_eq!(
+            operators(r#"either "a" or "b" along with "c" or "d""#, &en),
+            vec![Operator::Xor, Operator::And, Operator::Or]
+        );
+
+        let de = Lexicon::for_language("de").unwrap();
+        let tokens = tokenize(r#"die "Tür" öffnet nur wenn der "Knopf" und nicht der "Alarm""#, &de).unwrap();
+        assert!(tokens.iter().any(|t| t.kind == TokenKind::Connective(Connective::OnlyIf)));
+        assert_eq!(operators(r#""a" und nicht "b" oder "c""#, &de), vec![Operator::And, Operator::Not, Operator::Or]);
+    }
+
+    #[test]
+    fn test_correlative_opener_needs_its_closer() {
+        let en = Lexicon::english();
+        let words = |input: &str| -> Vec<String> {
+            tokenize(input, &en)
+                .unwrap()
+                .into_iter()
+                .filter_map(|t| match t.kind {
+                    TokenKind::Word(w) => Some(w),
+                    _ => None,
+                })
+                .collect()
+        };
+        // No closer in the clause: `either` and `both` stay words
+        assert_eq!(words(r#""door" opens in either direction"#), ["opens", "in", "either", "direction"]);
+        assert_eq!(operators(r#""a" and "b" open both. "c" or "d""#, &en), vec![Operator::And, Operator::Or]);
+        assert_eq!(words(r#"("a" or both) and "b""#), ["both"]);
+        // The closer must sit at the opener's parenthesis depth
+        assert_eq!(words(r#"either ("a" or "b")"#), ["either"]);
+        assert_eq!(operators(r#"either "a" or ("b" and "c")"#, &en), vec![Operator::Or, Operator::And]);
+        assert!(words(r#"either "a" or ("b" and "c")"#).is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_comparisons() {
+        let tokens = tokenize(r#""speed" is greater than 60, "gear" >= 2 and "rpm"≠"limit""#, &Lexicon::english()).unwrap();
+        let comparisons: Vec<(Comparison, Span)> = tokens
+            .iter()
+            .filter_map(|t| match t.kind {
+                TokenKind::Comparison(c) => Some((c, t.span)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            comparisons.iter().map(|(c, _)| *c).collect::<Vec<_>>(),
+            vec![Comparison::Gt, Comparison::Ge, Comparison::Ne]
+        );
+        assert_eq!(comparisons[0].1, Span::new(11, 23));
+        assert!(tokens.iter().any(|t| t.kind == TokenKind::Word("60".to_string())));
+    }
+
+    #[test]
+    fn test_tokenize_unterminated_quote() {
+        let err = tokenize(r#"the "start button"#, &Lexicon::english()).unwrap_err();
+        assert_eq!(err.span.start, 4);
+    }
+}
@@ -0,0 +1,229 @@
+//! Per-language keyword tables for the PSG lexer
+//!
+//! A `Lexicon` maps whole words and multi-word phrases (matched case-insensitively,
+//! on word boundaries) to logic keywords. Teams extend a language with their own
+//! synonyms (`plus`, `as well as`, ...) or register a new language from scratch.
+
+use super::{Comparison, Connective, Operator};
+
+/// Logic keyword a phrase stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keyword {
+    Operator(Operator),
+    Connective(Connective),
+    /// Comparison of an integer variable (`greater than`, `at most`)
+    Comparison(Comparison),
+}
+
+/// Correlative pair such as `either … or`: the opener is absorbed and the next
+/// closer after it is read as `operator`. An opener whose closer does not follow
+/// in the same clause stays a plain word (`in either direction`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrelativePair {
+    pub opener: String,
+    pub closer: String,
+    pub operator: Operator,
+}
+
+/// Keyword table of one natural language
+#[derive(Debug, Clone)]
+pub struct Lexicon {
+    pub language: String,
+    /// Phrases as lowercased words, longest matched first
+    phrases: Vec<(Vec<String>, Keyword)>,
+    pub pairs: Vec<CorrelativePair>,
+    /// Word endings that negate the word they end (`isn't`, `doesn't`)
+    pub negative_suffixes: Vec<String>,
+    /// Words dropped when an unquoted clause is turned into a variable name
+    pub stop_words: Vec<String>,
+    /// Words that turn a later `if` of the same clause into `only if`
+    pub restrictive_words: Vec<String>,
+    /// Number words, `numbers[i]` standing for `i` (`zero`, `one`, ...)
+    pub numbers: Vec<String>,
+}
+
+impl Lexicon {
+    /// Empty table for `language`
+    pub fn new(language: &str) -> Self {
+        Lexicon {
+            language: language.to_string(),
+            phrases: Vec::new(),
+            pairs: Vec::new(),
+            negative_suffixes: Vec::new(),
+            stop_words: Vec::new(),
+            restrictive_words: Vec::new(),
+            numbers: Vec::new(),
+        }
+    }
+
+    pub fn english() -> Self {
+        use Connective::*;
+        let mut lexicon = Lexicon::new("en");
+        for (phrase, op) in [
+            ("and", Operator::And),
+            ("plus", Operator::And),
+            ("as well as", Operator::And),
+            ("or", Operator::Or),
+            ("xor", Operator::Xor),
+            ("not", Operator::Not),
+            ("never", Operator::Not),
+        ] {
+            lexicon.add_keyword(phrase, Keyword::Operator(op));
+        }
+        for (phrase, connective) in [
+            ("if", If),
+            ("only if", OnlyIf),
+            ("iff", Iff),
+            ("if and only if", Iff),
+            ("then", Then),
+            ("whenever", Whenever),
+            ("unless", Unless),
+        ] {
+            lexicon.add_keyword(phrase, Keyword::Connective(connective));
+        }
+        for (phrase, comparison) in [
+            ("less than", Comparison::Lt),
+            ("fewer than", Comparison::Lt),
+            ("below", Comparison::Lt),
+            ("at most", Comparison::Le),
+            ("equals", Comparison::Eq),
+            ("equal", Comparison::Eq),
+            ("equal to", Comparison::Eq),
+            ("different from", Comparison::Ne),
+            ("differs from", Comparison::Ne),
+            ("greater than", Comparison::Gt),
+            ("more than", Comparison::Gt),
+            ("above", Comparison::Gt),
+            ("exceeds", Comparison::Gt),
+            ("at least", Comparison::Ge),
+            ("exactly", Comparison::Eq),
+        ] {
+            lexicon.add_keyword(phrase, Keyword::Comparison(comparison));
+        }
+        lexicon.add_pair("either", "or", Operator::Or);
+        lexicon.add_pair("both", "and", Operator::And);
+        lexicon.negative_suffixes = vec!["n't".to_string()];
+        lexicon.stop_words = ["the", "a", "an", "only", "does", "do"].map(String::from).to_vec();
+        lexicon.restrictive_words = vec!["only".to_string()];
+        lexicon.numbers = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"]
+            .map(String::from)
+            .to_vec();
+        lexicon
+    }
+
+    pub fn german() -> Self {
+        use Connective::*;
+        let mut lexicon = Lexicon::new("de");
+        for (phrase, op) in [
+            ("und", Operator::And),
+            ("sowie", Operator::And),
+            ("oder", Operator::Or),
+            ("nicht", Operator::Not),
+            ("nie", Operator::Not),
+        ] {
+            lexicon.add_keyword(phrase, Keyword::Operator(op));
+        }
+        for (phrase, connective) in [
+            ("wenn", If),
+            ("falls", If),
+            ("nur wenn", OnlyIf),
+            ("genau dann wenn", Iff),
+            ("dann", Then),
+            ("immer wenn", Whenever),
+            ("außer wenn", Unless),
+        ] {
+            lexicon.add_keyword(phrase, Keyword::Connective(connective));
+        }
+        for (phrase, comparison) in [
+            ("kleiner als", Comparison::Lt),
+            ("weniger als", Comparison::Lt),
+            ("höchstens", Comparison::Le),
+            ("gleich", Comparison::Eq),
+            ("ungleich", Comparison::Ne),
+            ("größer als", Comparison::Gt),
+            ("mehr als", Comparison::Gt),
+            ("mindestens", Comparison::Ge),
+            ("genau", Comparison::Eq),
+        ] {
+            lexicon.add_keyword(phrase, Keyword::Comparison(comparison));
+        }
+        // "entweder … oder" is read as exclusive in German usage
+        lexicon.add_pair("entweder", "oder", Operator::Xor);
+        lexicon.stop_words = ["der", "die", "das", "ein", "eine", "nur"].map(String::from).to_vec();
+        lexicon.restrictive_words = vec!["nur".to_string()];
+        lexicon.numbers = ["null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun", "zehn"]
+            .map(String::from)
+            .to_vec();
+        lexicon
+    }
+
+    /// Built-in table for a language code (`en`, `de`)
+    pub fn for_language(language: &str) -> Option<Self> {
+        match language {
+            "en" => Some(Lexicon::english()),
+            "de" => Some(Lexicon::german()),
+            _ => None,
+        }
+    }
+
+    /// Register (or re-map) a keyword phrase
+    pub fn add_keyword(&mut self, phrase: &str, keyword: Keyword) {
+        let words: Vec<String> = phrase.split_whitespace().map(str::to_lowercase).collect();
+        self.phrases.retain(|(w, _)| *w != words);
+        self.phrases.push((words, keyword));
+        self.phrases.sort_by_key(|(words, _)| std::cmp::Reverse(words.len()));
+    }
+
+    /// Register (or re-map) a correlative pair such as `either … or`
+    pub fn add_pair(&mut self, opener: &str, closer: &str, operator: Operator) {
+        self.pairs.retain(|p| !p.opener.eq_ignore_ascii_case(opener));
+        self.pairs.push(CorrelativePair {
+            opener: opener.to_lowercase(),
+            closer: closer.to_lowercase(),
+            operator,
+        });
+    }
+
+    /// Number of words in the longest phrase
+    pub fn max_phrase_len(&self) -> usize {
+        self.phrases.first().map_or(1, |(w, _)| w.len())
+    }
+
+    /// Longest keyword phrase at the start of `words` (lowercased), with its length in words
+    pub fn longest_match(&self, words: &[&str]) -> Option<(usize, Keyword)> {
+        let phrase = self
+            .phrases
+            .iter()
+            .find(|(phrase, _)| phrase.len() <= words.len() && phrase.iter().zip(words).all(|(p, w)| p == w));
+        if let Some((phrase, keyword)) = phrase {
+            return Some((phrase.len(), *keyword));
+        }
+        let word = words.first()?;
+        self.negative_suffixes
+            .iter()
+            .any(|suffix| word.len() > suffix.len() && word.ends_with(suffix.as_str()))
+            .then_some((1, Keyword::Operator(Operator::Not)))
+    }
+
+    pub fn is_stop_word(&self, word: &str) -> bool {
+        self.stop_words.iter().any(|w| w.eq_ignore_ascii_case(word))
+    }
+
+    pub fn is_restrictive(&self, word: &str) -> bool {
+        self.restrictive_words.iter().any(|w| w.eq_ignore_ascii_case(word))
+    }
+
+    /// Value of a number written in digits or as a number word
+    pub fn number(&self, word: &str) -> Option<u64> {
+        word.parse().ok().or_else(|| {
+            let word = word.to_lowercase();
+            self.numbers.iter().position(|n| *n == word).map(|i| i as u64)
+        })
+    }
+}
+
+impl Default for Lexicon {
+    fn default() -> Self {
+        Lexicon::english()
+    }
+}
@@ -0,0 +1,417 @@
+//! Normal forms of a `LogicExpression`: NNF, CNF and DNF
+//!
+//! - NNF: only `&&`, `||` and negated variables; `->`, `<-`, `<->` and `^` are
+//!   expanded, so nested XORs grow exponentially. Cardinality constraints stay
+//!   atoms over NNF operands, a negated one flipping its comparison.
+//! - DNF: the absorbed sum of cubes of the expression, `None` past `MAX_COVER_CUBES`.
+//! - CNF: the De Morgan dual of the DNF of the complement while it stays within
+//!   `MAX_COVER_CUBES`, otherwise the Tseitin encoding. Tseitin introduces one
+//!   auxiliary variable per gate (`_t0`, `_t1`, ...) and is equisatisfiable rather
+//!   than equivalent: it is true exactly when the auxiliaries carry the value of
+//!   their gate and the statement is true. A cardinality constraint over `n`
+//!   operands is encoded as its decision diagram on (operand, count so far), at
+//!   most `n * (n + 1) / 2` if-then-else auxiliaries.
+//!
+//! Both clausal forms render to strings (with any naming of the variables, e.g. the
+//! hashed names of `build_regexp`) and compile to a `partitioned_gc::Circuit`.
+
+use super::compile::{self, CompiledCircuit};
+use super::espresso::{self, Cover};
+use super::{Comparison, Expr, LogicExpression, Operator, Variable};
+
+/// Normal form selected for display in the PSG pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalForm {
+    Nnf,
+    #[default]
+    Cnf,
+    Dnf,
+}
+
+/// Variable `var` (an index into the form's variables) or its negation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Literal {
+    pub var: usize,
+    pub positive: bool,
+}
+
+impl Literal {
+    pub fn new(var: usize, positive: bool) -> Self {
+        Literal { var, positive }
+    }
+}
+
+impl std::ops::Not for Literal {
+    type Output = Literal;
+
+    fn not(self) -> Literal {
+        Literal { var: self.var, positive: !self.positive }
+    }
+}
+
+/// Conjunction of clauses (`Cnf`) or disjunction of terms (`Dnf`) over `variables`;
+/// the first `inputs` variables are the statement's, the rest are Tseitin auxiliaries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clausal {
+    pub form: NormalForm,
+    pub variables: Vec<Variable>,
+    pub inputs: usize,
+    pub terms: Vec<Vec<Literal>>,
+}
+
+impl Clausal {
+    /// Number of Tseitin auxiliary variables
+    pub fn auxiliary(&self) -> usize {
+        self.variables.len() - self.inputs
+    }
+
+    /// (outer, inner) operator: `&&` of `||` clauses for CNF, `||` of `&&` terms for DNF
+    fn operators(&self) -> (Operator, Operator) {
+        match self.form {
+            NormalForm::Dnf => (Operator::Or, Operator::And),
+            _ => (Operator::And, Operator::Or),
+        }
+    }
+
+    pub fn to_expr(&self) -> Expr {
+        let (outer, inner) = self.operators();
+        // An empty inner term is the inner operator's identity, an empty form the outer's
+        let identity = |op: Operator| Expr::Const(op == Operator::And);
+        let literal = |l: &Literal| {
+            let var = Expr::Var(self.variables[l.var].clone());
+            if l.positive { var } else { Expr::Not(Box::new(var)) }
+        };
+        self.terms
+            .iter()
+            .map(|term| term.iter().map(literal).reduce(|a, b| Expr::binary(inner, a, b)).unwrap_or(identity(inner)))
+            .reduce(|a, b| Expr::binary(outer, a, b))
+            .unwrap_or(identity(outer))
+    }
+
+    /// Render with every term parenthesized; statement variables are named through
+    /// `name`, auxiliaries by their own name
+    pub fn render(&self, name: &dyn Fn(&Variable) -> String) -> String {
+        let (outer, inner) = self.operators();
+        if self.terms.is_empty() {
+            return (outer == Operator::And).to_string();
+        }
+        let literal = |l: &Literal| {
+            let var = &self.variables[l.var];
+            let var = if l.var < self.inputs { name(var) } else { var.name.clone() };
+            if l.positive { var } else { format!("{}{}", Operator::Not.symbol(), var) }
+        };
+        self.terms
+            .iter()
+            .map(|term| match term.len() {
+                0 => (inner == Operator::And).to_string(),
+                1 => literal(&term[0]),
+                _ => format!("({})", term.iter().map(literal).collect::<Vec<_>>().join(&format!(" {} ", inner.symbol()))),
+            })
+            .collect::<Vec<_>>()
+            .join(&format!(" {} ", outer.symbol()))
+    }
+
+    /// Compile into a circuit; `hashed` names the statement variables, auxiliaries
+    /// keep their own name and get the input wires after them
+    pub fn to_circuit(&self, hashed: &[String]) -> CompiledCircuit {
+        let expression = LogicExpression { variables: self.variables.clone(), root: self.to_expr(), consequent: None };
+        let names: Vec<String> = hashed
+            .iter()
+            .cloned()
+            .chain(self.variables[self.inputs..].iter().map(|v| v.name.clone()))
+            .collect();
+        compile::compile(&expression, &names)
+    }
+}
+
+/// Negation normal form of `expr`
+pub fn nnf(expr: &Expr) -> Expr {
+    nnf_of(expr, true)
+}
+
+fn nnf_of(expr: &Expr, positive: bool) -> Expr {
+    let and = |a, b| Expr::binary(Operator::And, a, b);
+    let or = |a, b| Expr::binary(Operator::Or, a, b);
+    match expr {
+        Expr::Var(_) if positive => expr.clone(),
+        Expr::Var(_) => Expr::Not(Box::new(expr.clone())),
+        Expr::Const(value) => Expr::Const(*value == positive),
+        Expr::Not(inner) => nnf_of(inner, !positive),
+        Expr::Binary { op, lhs, rhs } => {
+            let side = |e: &Expr, p: bool| nnf_of(e, p);
+            match (op, positive) {
+                (Operator::And, true) => and(side(lhs, true), side(rhs, true)),
+                (Operator::And, false) => or(side(lhs, false), side(rhs, false)),
+                (Operator::Or, true) => or(side(lhs, true), side(rhs, true)),
+                (Operator::Or, false) => and(side(lhs, false), side(rhs, false)),
+                (Operator::Implies, true) => or(side(lhs, false), side(rhs, true)),
+                (Operator::Implies, false) => and(side(lhs, true), side(rhs, false)),
+                (Operator::Converse, true) => or(side(lhs, true), side(rhs, false)),
+                (Operator::Converse, false) => and(side(lhs, false), side(rhs, true)),
+                (Operator::Xor, true) | (Operator::Iff, false) => {
+                    or(and(side(lhs, true), side(rhs, false)), and(side(lhs, false), side(rhs, true)))
+                }
+                (Operator::Xor, false) | (Operator::Iff, true) => {
+                    or(and(side(lhs, true), side(rhs, true)), and(side(lhs, false), side(rhs, false)))
+                }
+                (Operator::Not, _) => unreachable!("negation is parsed as Expr::Not"),
+            }
+        }
+        Expr::Threshold { op, k, operands } => Expr::Threshold {
+            op: if positive { *op } else { op.negate() },
+            k: *k,
+            operands: operands.iter().map(|e| nnf_of(e, true)).collect(),
+        },
+    }
+}
+
+fn cover_terms(cover: &Cover, negate: bool) -> Vec<Vec<Literal>> {
+    cover
+        .cubes
+        .iter()
+        .map(|cube| cube.vars().map(|v| Literal::new(v, cube.get(v).expect("literal of the cube") != negate)).collect())
+        .collect()
+}
+
+/// Disjunctive normal form, `None` past `MAX_COVER_CUBES` terms
+pub fn dnf(expression: &LogicExpression) -> Option<Clausal> {
+    let cover = espresso::cover(expression)?;
+    Some(Clausal {
+        form: NormalForm::Dnf,
+        variables: expression.variables.clone(),
+        inputs: expression.variables.len(),
+        terms: cover_terms(&cover, false),
+    })
+}
+
+/// Equivalent conjunctive normal form when it stays within `MAX_COVER_CUBES`
+/// clauses, the Tseitin encoding otherwise (at once when the clause count is
+/// estimated far past the limit)
+pub fn cnf(expression: &LogicExpression) -> Clausal {
+    match espresso::complement_cover(expression) {
+        // !f = c1 || c2 || ...  =>  f = !c1 && !c2 && ...
+        Some(cover) => Clausal {
+            form: NormalForm::Cnf,
+            variables: expression.variables.clone(),
+            inputs: expression.variables.len(),
+            terms: cover_terms(&cover, true),
+        },
+        None => tseitin(expression),
+    }
+}
+
+/// Tseitin encoding: one auxiliary variable per binary operator and constant
+pub fn tseitin(expression: &LogicExpression) -> Clausal {
+    let mut encoder = Tseitin { expression, variables: expression.variables.clone(), clauses: Vec::new() };
+    let root = encoder.encode(&expression.root);
+    encoder.clauses.push(vec![root]);
+    Clausal {
+        form: NormalForm::Cnf,
+        variables: encoder.variables,
+        inputs: expression.variables.len(),
+        terms: encoder.clauses,
+    }
+}
+
+struct Tseitin<'a> {
+    expression: &'a LogicExpression,
+    variables: Vec<Variable>,
+    clauses: Vec<Vec<Literal>>,
+}
+
+impl Tseitin<'_> {
+    fn fresh(&mut self) -> Literal {
+        let aux = self.variables.len() - self.expression.variables.len();
+        self.variables.push(Variable::new(format!("_t{}", aux)));
+        Literal::new(self.variables.len() - 1, true)
+    }
+
+    /// Literal equal to `expr` under the clauses added so far
+    fn encode(&mut self, expr: &Expr) -> Literal {
+        match expr {
+            Expr::Var(var) => {
+                Literal::new(self.expression.variables.iter().position(|v| v == var).expect("variable collected by LogicExpression"), true)
+            }
+            Expr::Const(value) => {
+                let t = self.fresh();
+                self.clauses.push(vec![if *value { t } else { !t }]);
+                t
+            }
+            Expr::Not(inner) => !self.encode(inner),
+            Expr::Binary { op, lhs, rhs } => {
+                let (a, b) = (self.encode(lhs), self.encode(rhs));
+                // Gates reduced to t = a && b, t = a || b and t = a ^ b over possibly negated inputs
+                match op {
+                    Operator::And => self.gate(Operator::And, a, b),
+                    Operator::Or => self.gate(Operator::Or, a, b),
+                    Operator::Implies => self.gate(Operator::Or, !a, b),
+                    Operator::Converse => self.gate(Operator::Or, a, !b),
+                    Operator::Xor => self.gate(Operator::Xor, a, b),
+                    Operator::Iff => !self.gate(Operator::Xor, a, b),
+                    Operator::Not => unreachable!("negation is parsed as Expr::Not"),
+                }
+            }
+            Expr::Threshold { op, k, operands } => {
+                let literals: Vec<Literal> = operands.iter().map(|e| self.encode(e)).collect();
+                match self.threshold(*op, *k, &literals) {
+                    Node::Literal(t) => t,
+                    Node::Const(value) => self.encode(&Expr::Const(value)),
+                }
+            }
+        }
+    }
+
+    /// Fresh `t = a kind b` for `kind` one of `&&`, `||`, `^`
+    fn gate(&mut self, kind: Operator, a: Literal, b: Literal) -> Literal {
+        let t = self.fresh();
+        let clauses = match kind {
+            Operator::And => vec![vec![!t, a], vec![!t, b], vec![t, !a, !b]],
+            Operator::Or => vec![vec![t, !a], vec![t, !b], vec![!t, a, b]],
+            _ => vec![vec![!t, a, b], vec![!t, !a, !b], vec![t, !a, b], vec![t, a, !b]],
+        };
+        self.clauses.extend(clauses);
+        t
+    }
+
+    /// `if l { then } else { otherwise }`, folding constant branches
+    fn ite(&mut self, l: Literal, then: Node, otherwise: Node) -> Node {
+        match (then, otherwise) {
+            (Node::Const(true), Node::Const(true)) => Node::Const(true),
+            (Node::Const(false), Node::Const(false)) => Node::Const(false),
+            (Node::Literal(a), Node::Literal(e)) if a == e => Node::Literal(a),
+            (Node::Const(true), Node::Const(false)) => Node::Literal(l),
+            (Node::Const(false), Node::Const(true)) => Node::Literal(!l),
+            (Node::Const(true), Node::Literal(e)) => Node::Literal(self.gate(Operator::Or, l, e)),
+            (Node::Const(false), Node::Literal(e)) => Node::Literal(self.gate(Operator::And, !l, e)),
+            (Node::Literal(t), Node::Const(true)) => Node::Literal(self.gate(Operator::Or, !l, t)),
+            (Node::Literal(t), Node::Const(false)) => Node::Literal(self.gate(Operator::And, l, t)),
+            (Node::Literal(a), Node::Literal(e)) => {
+                let t = self.fresh();
+                self.clauses.extend([vec![!l, !a, t], vec![!l, a, !t], vec![l, !e, t], vec![l, e, !t]]);
+                Node::Literal(t)
+            }
+        }
+    }
+
+    /// Decision diagram of `count(literals) op k`: the node of operand `i` with
+    /// `c` operands true so far tests operand `i`, layer by layer from the last
+    fn threshold(&mut self, op: Comparison, k: usize, literals: &[Literal]) -> Node {
+        let n = literals.len();
+        let mut layer: Vec<Node> = (0..=n).map(|c| Node::Const(op.eval(c as u64, k as u64))).collect();
+        for i in (0..n).rev() {
+            layer = (0..=i).map(|c| self.ite(literals[i], layer[c + 1], layer[c])).collect();
+        }
+        layer[0]
+    }
+}
+
+/// Value of a Tseitin subformula: a constant or a literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    Const(bool),
+    Literal(Literal),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::parse_precedence;
+
+    fn parse_str(input: &str) -> LogicExpression {
+        parse_precedence(input).unwrap()
+    }
+
+    fn assignments(expression: &LogicExpression) -> impl Iterator<Item = Vec<bool>> + '_ {
+        let n = expression.variables.len();
+        (0..1u32 << n).map(move |x| (0..n).map(|i| x >> i & 1 == 1).collect())
+    }
+
+    fn eval(expression: &LogicExpression, expr: &Expr, values: &[bool]) -> bool {
+        expr.eval(&|var| values[expression.variables.iter().position(|v| v == var).unwrap()])
+    }
+
+    #[test]
+    fn test_forms_are_equivalent() {
+        let expression = parse_str(r#""a" if and only if not "b" and ("c" or "d")"#);
+        let nnf = nnf(&expression.root);
+        let cnf = cnf(&expression);
+        let dnf = dnf(&expression).unwrap();
+        assert_eq!(cnf.auxiliary(), 0);
+
+        let mut nnf_ok = true;
+        fn only_nnf(e: &Expr, ok: &mut bool) {
+            match e {
+                Expr::Not(inner) => *ok &= matches!(**inner, Expr::Var(_)),
+                Expr::Binary { op, lhs, rhs } => {
+                    *ok &= matches!(op, Operator::And | Operator::Or);
+                    only_nnf(lhs, ok);
+                    only_nnf(rhs, ok);
+                }
+                _ => {}
+            }
+        }
+        only_nnf(&nnf, &mut nnf_ok);
+        assert!(nnf_ok, "{:?}", nnf);
+
+        for values in assignments(&expression) {
+            let expected = eval(&expression, &expression.root, &values);
+            assert_eq!(eval(&expression, &nnf, &values), expected);
+            assert_eq!(eval(&expression, &cnf.to_expr(), &values), expected);
+            assert_eq!(eval(&expression, &dnf.to_expr(), &values), expected);
+        }
+        // a&!b&c, a&!b&d, !a&b, !a&!c&!d
+        assert_eq!(dnf.terms.len(), 4);
+        assert!(dnf.render(&|v| v.name.clone()).contains("(!a && b)"));
+    }
+
+    #[test]
+    fn test_tseitin_is_equisatisfiable() {
+        let expression = parse_str(r#""a" and not ("b" or "c") xor "d""#);
+        let encoded = tseitin(&expression);
+        assert_eq!(encoded.auxiliary(), 3);
+        assert!(encoded.render(&|v| format!("h({})", v.name)).contains("h(a)"));
+
+        // For every input assignment, some auxiliary assignment satisfies the
+        // clauses exactly when the statement is true
+        let circuit = encoded.to_circuit(&expression.variables.iter().map(|v| v.name.clone()).collect::<Vec<_>>());
+        assert_eq!(circuit.circuit.input_wires.len(), 7);
+        let all = LogicExpression {
+            variables: encoded.variables.clone(),
+            root: encoded.to_expr(),
+            consequent: None,
+        };
+        for values in assignments(&expression) {
+            let expected = eval(&expression, &expression.root, &values);
+            let satisfiable = (0..8u32).any(|aux| {
+                let full: Vec<bool> = values.iter().copied().chain((0..3).map(|i| aux >> i & 1 == 1)).collect();
+                eval(&all, &all.root, &full)
+            });
+            assert_eq!(satisfiable, expected);
+        }
+    }
+
+    #[test]
+    fn test_cardinality_forms() {
+        let expression = parse_str(r#"not (exactly 2 of "a", "b", "c", "d" are set) or "e""#);
+        let nnf = nnf(&expression.root);
+        assert!(nnf.render(&|v| v.name.clone()).contains("#(a, b, c, d) != 2"));
+        let dnf = dnf(&expression).unwrap();
+        let cnf = cnf(&expression);
+        let encoded = tseitin(&expression);
+        assert!(encoded.auxiliary() <= 4 * 5 / 2 + 1, "{} auxiliaries", encoded.auxiliary());
+        let all = LogicExpression { variables: encoded.variables.clone(), root: encoded.to_expr(), consequent: None };
+        let aux = encoded.auxiliary();
+
+        for values in assignments(&expression) {
+            let expected = eval(&expression, &expression.root, &values);
+            assert_eq!(eval(&expression, &nnf, &values), expected);
+            assert_eq!(eval(&expression, &dnf.to_expr(), &values), expected);
+            assert_eq!(eval(&expression, &cnf.to_expr(), &values), expected);
+            let satisfiable = (0..1u32 << aux).any(|x| {
+                let full: Vec<bool> = values.iter().copied().chain((0..aux).map(|i| x >> i & 1 == 1)).collect();
+                eval(&all, &all.root, &full)
+            });
+            assert_eq!(satisfiable, expected, "{:?}", values);
+        }
+    }
+}
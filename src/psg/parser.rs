@@ -0,0 +1,749 @@
+//! Recursive-descent parser for PSG composite statements
+//!
+//! Grammar (filler words may appear between any two symbols and are skipped):
+//!
+//! ```text
+//! statement := "if" clause "then" clause
+//!            | clause ( CONNECTIVE clause )?
+//! clause    := expr | WORD+                  (unquoted clause, e.g. "the car starts")
+//! expr    := or
+//! or      := xor ( "or" xor )*
+//! xor     := and ( "xor" and )*
+//! and     := unary ( "and" unary )*
+//! unary   := "not" unary | primary
+//! primary := VARIABLE "not"? ( COMPARISON value )? | "(" expr ")"
+//!          | COMPARISON NUMBER unary ( ( "," | "and" | "or" )+ unary )*
+//! value   := NUMBER | VARIABLE
+//! ```
+//!
+//! giving the precedence NOT > AND > XOR > OR under `Disambiguation::Precedence`.
+//! Binary operators are left-associative.
+//!
+//! English has no precedence, so a chain mixing `and`, `or` and `xor` without
+//! parentheses (`"a" and "b" or "c"`) has several readings. `readings` lists each
+//! distinct grouping (runs of one operator associate, so `"a" and "b" and "c"`
+//! has one). How `parse_with` resolves such a chain is the `Disambiguation` policy:
+//! by default it refuses the statement, listing its readings.
+//!
+//! Comparisons (`the "speed" is greater than 60`) need their variables declared as
+//! integers (`PsgOptions::types`) and are lowered to Boolean expressions over the
+//! bits (see `integer`); an integer variable cannot stand alone as a condition.
+//!
+//! A comparison in front of a count and a list is a cardinality constraint:
+//! `at least 2 of "badge", "pin", "face" are valid` is `#(badge, pin, face) >= 2`,
+//! likewise `exactly one of`, `at most 3 of`. List items are separated by commas,
+//! `and` or `or`; the list ends after an item followed by predicate words (`are
+//! valid`) or without a separator, so `(at least 2 of "a", "b", "c") and "d"`
+//! needs its parentheses.
+//!
+//! Connectives bind loosest and fix the direction of the implication:
+//!
+//! | statement            | expression      |
+//! | -------------------- | --------------- |
+//! | `A only if B`        | `A -> B`        |
+//! | `A if B`             | `A <- B`        |
+//! | `A whenever B`       | `A <- B`        |
+//! | `if B then A`        | `B -> A`        |
+//! | `A unless B`         | `A <- !B`       |
+//! | `A if and only if B` | `A <-> B`       |
+//!
+//! An `only` (any restrictive word of the lexicon) anywhere in the main clause
+//! turns a later `if` into `only if` (`The car only starts if ...`).
+//!
+//! Negation is either a prefix (`not the "door"`) or sits in the predicate after
+//! a variable (`the "door" is not open`, `the "door" isn't open`).
+
+use std::collections::HashMap;
+
+use super::integer::{self, Comparison, VarType, MAX_INTEGER_WIDTH};
+use super::lexer::{Span, Token, TokenKind};
+use super::lexicon::Lexicon;
+use super::{Connective, Expr, LogicExpression, Operator, ParseError, PsgOptions, Variable};
+
+/// Most readings listed for an ambiguous statement
+pub const MAX_READINGS: usize = 16;
+
+/// How a chain mixing `and`, `or` and `xor` without parentheses is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Disambiguation {
+    /// Refuse the statement, listing its readings
+    #[default]
+    Strict,
+    /// Group from the left: `"a" or "b" and "c"` is `(a || b) && c`
+    LeftAssoc,
+    /// NOT > AND > XOR > OR: `"a" or "b" and "c"` is `a || (b && c)`
+    Precedence,
+}
+
+/// Grouping of a chain `x0 op0 x1 op1 ... xn` over its operand indices; runs of
+/// one operator are kept flat, since they associate
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Grouping {
+    Operand(usize),
+    Apply(Operator, Vec<Grouping>),
+}
+
+impl Grouping {
+    fn join(op: Operator, lhs: Grouping, rhs: Grouping) -> Grouping {
+        let mut children = Vec::new();
+        for side in [lhs, rhs] {
+            match side {
+                Grouping::Apply(inner, grouped) if inner == op => children.extend(grouped),
+                other => children.push(other),
+            }
+        }
+        Grouping::Apply(op, children)
+    }
+
+    fn left(ops: &[Operator]) -> Grouping {
+        ops.iter().enumerate().fold(Grouping::Operand(0), |acc, (i, op)| Grouping::join(*op, acc, Grouping::Operand(i + 1)))
+    }
+
+    fn precedence(ops: &[Operator]) -> Grouping {
+        let mut items: Vec<Grouping> = (0..=ops.len()).map(Grouping::Operand).collect();
+        let mut ops = ops.to_vec();
+        for level in [Operator::And, Operator::Xor, Operator::Or] {
+            let mut i = 0;
+            while i < ops.len() {
+                if ops[i] == level {
+                    let rhs = items.remove(i + 1);
+                    items[i] = Grouping::join(level, items[i].clone(), rhs);
+                    ops.remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        items.pop().expect("a chain has an operand")
+    }
+
+    /// Distinct groupings, at most `MAX_READINGS` per subchain
+    fn all(ops: &[Operator]) -> Vec<Grouping> {
+        let n = ops.len() + 1;
+        // spans[len - 1][i]: groupings of operands i..i + len
+        let mut spans: Vec<Vec<Vec<Grouping>>> = vec![(0..n).map(|i| vec![Grouping::Operand(i)]).collect()];
+        for len in 2..=n {
+            let row = (0..=n - len)
+                .map(|i| {
+                    let mut groupings = Vec::new();
+                    for split in 1..len {
+                        for lhs in &spans[split - 1][i] {
+                            for rhs in &spans[len - split - 1][i + split] {
+                                let grouping = Grouping::join(ops[i + split - 1], lhs.clone(), rhs.clone());
+                                if !groupings.contains(&grouping) && groupings.len() < MAX_READINGS {
+                                    groupings.push(grouping);
+                                }
+                            }
+                        }
+                    }
+                    groupings
+                })
+                .collect();
+            spans.push(row);
+        }
+        spans.pop().and_then(|mut row| row.pop()).expect("a chain has an operand")
+    }
+
+    fn build(&self, operands: &[Expr]) -> Expr {
+        match self {
+            Grouping::Operand(i) => operands[*i].clone(),
+            Grouping::Apply(op, children) => children
+                .iter()
+                .map(|child| child.build(operands))
+                .reduce(|acc, child| Expr::binary(*op, acc, child))
+                .expect("an operator has operands"),
+        }
+    }
+}
+
+fn is_filler(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::Word(_) | TokenKind::Punct(_))
+}
+
+fn is_operand_start(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::Variable(_) | TokenKind::LParen)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    input_len: usize,
+    lexicon: &'a Lexicon,
+    types: &'a HashMap<String, VarType>,
+    /// Integer variables compared so far, with their widths
+    integers: Vec<(Variable, usize)>,
+    disambiguation: Disambiguation,
+    /// Reading to take of each ambiguous chain, in parse order (the first when missing)
+    choices: &'a [usize],
+    /// Ambiguous chains parsed so far, with their number of readings
+    chains: Vec<(Span, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_kind(&self) -> Option<&'a TokenKind> {
+        self.peek().map(|t| &t.kind)
+    }
+
+    /// Span of the current token, or an empty span at the end of the input
+    fn here(&self) -> Span {
+        self.peek()
+            .map(|t| t.span)
+            .unwrap_or(Span::new(self.input_len, self.input_len))
+    }
+
+    fn skip_filler(&mut self) {
+        while self.peek_kind().is_some_and(is_filler) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume the predicate after a variable, returning whether it negates the
+    /// variable (`is not open`). A `not` that introduces another operand is a
+    /// prefix negation and is left for the caller.
+    fn skip_predicate(&mut self) -> bool {
+        let mut negated = false;
+        while let Some(kind) = self.peek_kind() {
+            match kind {
+                TokenKind::Operator(Operator::Not) => {
+                    let next = self.tokens[self.pos + 1..]
+                        .iter()
+                        .find(|t| !is_filler(&t.kind) && t.kind != TokenKind::Operator(Operator::Not));
+                    if next.is_some_and(|t| is_operand_start(&t.kind)) {
+                        break;
+                    }
+                    negated = !negated;
+                }
+                kind if is_filler(kind) => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        negated
+    }
+
+    /// Parse `unary ( op unary )*` with `op` any of `and`, `or`, `xor`, grouped
+    /// as the disambiguation policy says
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        let start = self.here();
+        let mut operands = vec![self.parse_unary()?];
+        let mut ops = Vec::new();
+        loop {
+            self.skip_filler();
+            match self.peek_kind() {
+                Some(TokenKind::Operator(op @ (Operator::And | Operator::Or | Operator::Xor))) => {
+                    self.pos += 1;
+                    ops.push(*op);
+                    operands.push(self.parse_unary()?);
+                }
+                _ => break,
+            }
+        }
+        let mixed = ops.iter().any(|op| *op != ops[0]);
+        let tree = match self.disambiguation {
+            _ if !mixed => Grouping::left(&ops),
+            Disambiguation::LeftAssoc => Grouping::left(&ops),
+            Disambiguation::Precedence => Grouping::precedence(&ops),
+            Disambiguation::Strict => {
+                let readings = Grouping::all(&ops);
+                let end = self.tokens[..self.pos].last().map_or(start, |t| t.span);
+                let choice = self.choices.get(self.chains.len()).copied().unwrap_or(0);
+                self.chains.push((start.join(end), readings.len()));
+                readings[choice.min(readings.len() - 1)].clone()
+            }
+        };
+        Ok(tree.build(&operands))
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        self.skip_filler();
+        let token = match self.peek() {
+            Some(token) => token,
+            None => return Err(ParseError::new("expected a quoted variable", self.here())),
+        };
+        match &token.kind {
+            TokenKind::Operator(Operator::Not) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            TokenKind::LParen => {
+                self.pos += 1;
+                let inner = self.parse_expr()?;
+                self.skip_filler();
+                match self.peek_kind() {
+                    Some(TokenKind::RParen) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(ParseError::new(
+                        "expected ')' to close this group",
+                        token.span.join(self.here()),
+                    )),
+                }
+            }
+            TokenKind::Variable(name) => {
+                self.pos += 1;
+                let var = Variable { name: name.clone(), span: token.span, bit: None };
+                let negated = self.skip_predicate();
+                let expr = match self.peek_kind() {
+                    Some(TokenKind::Comparison(comparison)) => {
+                        self.pos += 1;
+                        let left = self.integer_bits(&var)?;
+                        let right = self.parse_value()?;
+                        integer::compare(*comparison, &left, &right)
+                    }
+                    _ if matches!(self.types.get(&var.name), Some(VarType::UInt { .. })) => {
+                        let message = format!("integer variable '{}' needs a comparison", var.name);
+                        return Err(ParseError::new(message, var.span));
+                    }
+                    _ => Expr::Var(var),
+                };
+                if negated {
+                    Ok(Expr::Not(Box::new(expr)))
+                } else {
+                    Ok(expr)
+                }
+            }
+            TokenKind::Comparison(comparison) => {
+                self.pos += 1;
+                self.parse_threshold(*comparison, token.span)
+            }
+            _ => Err(ParseError::new("expected a quoted variable", token.span)),
+        }
+    }
+
+    /// Count and operand list of a cardinality constraint, after its comparison
+    fn parse_threshold(&mut self, op: Comparison, start: Span) -> Result<Expr, ParseError> {
+        while let Some(TokenKind::Punct(_)) = self.peek_kind() {
+            self.pos += 1;
+        }
+        let k = match self.peek_kind() {
+            Some(TokenKind::Word(word)) => self.lexicon.number(word),
+            _ => None,
+        };
+        let Some(k) = k else {
+            return Err(ParseError::new("expected a count after the comparison", start.join(self.here())));
+        };
+        self.pos += 1;
+
+        let mut operands = vec![self.parse_unary()?];
+        loop {
+            self.skip_filler();
+            let last = self.tokens[..self.pos].iter().rev().find(|t| !matches!(t.kind, TokenKind::Punct(_)));
+            if last.is_some_and(|t| matches!(t.kind, TokenKind::Word(_))) {
+                break;
+            }
+            let separator = match self.peek_kind() {
+                Some(TokenKind::Operator(Operator::And | Operator::Or)) => 1,
+                Some(kind) if is_operand_start(kind) || *kind == TokenKind::Operator(Operator::Not) => 0,
+                _ => break,
+            };
+            let next = self.tokens[self.pos + separator..].iter().find(|t| !is_filler(&t.kind));
+            if !next.is_some_and(|t| is_operand_start(&t.kind) || t.kind == TokenKind::Operator(Operator::Not)) {
+                break;
+            }
+            self.pos += separator;
+            operands.push(self.parse_unary()?);
+        }
+        Ok(Expr::Threshold { op, k: k as usize, operands })
+    }
+
+    /// Bits of a compared variable, which must be declared as an integer
+    fn integer_bits(&mut self, var: &Variable) -> Result<Vec<Expr>, ParseError> {
+        match self.types.get(&var.name) {
+            Some(&VarType::UInt { width }) if (1..=MAX_INTEGER_WIDTH).contains(&width) => {
+                if !self.integers.iter().any(|(v, _)| v == var) {
+                    self.integers.push((var.clone(), width));
+                }
+                Ok(integer::bits(var, width))
+            }
+            Some(&VarType::UInt { width }) => Err(ParseError::new(
+                format!("width {} of '{}' is outside 1..={}", width, var.name, MAX_INTEGER_WIDTH),
+                var.span,
+            )),
+            _ => Err(ParseError::new(format!("'{}' is compared but not declared as an integer", var.name), var.span)),
+        }
+    }
+
+    /// Right side of a comparison: a number or a quoted integer variable
+    fn parse_value(&mut self) -> Result<Vec<Expr>, ParseError> {
+        while let Some(token) = self.peek() {
+            match &token.kind {
+                TokenKind::Word(word) => {
+                    if let Some(value) = self.lexicon.number(word) {
+                        self.pos += 1;
+                        return Ok(integer::constant(value));
+                    }
+                }
+                TokenKind::Variable(name) => {
+                    self.pos += 1;
+                    return self.integer_bits(&Variable { name: name.clone(), span: token.span, bit: None });
+                }
+                TokenKind::Punct(_) => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        Err(ParseError::new("expected a number or a quoted integer variable", self.here()))
+    }
+
+    /// Parse one side of a conditional: an expression over quoted variables, or
+    /// an unquoted clause that becomes a variable named after its words
+    fn parse_clause(&mut self) -> Result<Expr, ParseError> {
+        let end = self.tokens[self.pos..]
+            .iter()
+            .position(|t| matches!(t.kind, TokenKind::Connective(_)))
+            .map_or(self.tokens.len(), |i| self.pos + i);
+        let clause = &self.tokens[self.pos..end];
+
+        let has_operand = clause.iter().any(|t| is_operand_start(&t.kind));
+        if has_operand {
+            return self.parse_expr();
+        }
+
+        let binary_op = |t: &&Token| matches!(t.kind, TokenKind::Operator(op) if op != Operator::Not);
+        if let Some(op) = clause.iter().find(binary_op) {
+            return Err(ParseError::new("operators in a clause need quoted variables", op.span));
+        }
+        let negations = clause
+            .iter()
+            .filter(|t| t.kind == TokenKind::Operator(Operator::Not))
+            .count();
+        let words: Vec<String> = clause
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::Word(w) => Some(w.to_lowercase()),
+                _ => None,
+            })
+            .filter(|w| !self.lexicon.is_stop_word(w))
+            .collect();
+        if words.is_empty() {
+            return Err(ParseError::new("expected a quoted variable or a clause", self.here()));
+        }
+        let span = clause[0].span.join(clause[clause.len() - 1].span);
+        self.pos = end;
+        let var = Expr::Var(Variable { name: words.join(" "), span, bit: None });
+        if negations % 2 == 1 {
+            Ok(Expr::Not(Box::new(var)))
+        } else {
+            Ok(var)
+        }
+    }
+
+    fn expect_connective(&mut self, expected: Connective, message: &str) -> Result<(), ParseError> {
+        self.skip_filler();
+        match self.peek_kind() {
+            Some(TokenKind::Connective(c)) if *c == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(ParseError::new(message, self.here())),
+        }
+    }
+
+    /// Parse a full statement, returning its expression and main clause
+    fn parse_statement(&mut self) -> Result<(Expr, Expr), ParseError> {
+        if let Some(TokenKind::Connective(Connective::If)) = self.peek_kind() {
+            self.pos += 1;
+            let antecedent = self.parse_clause()?;
+            self.expect_connective(Connective::Then, "expected 'then' after the condition")?;
+            let consequent = self.parse_clause()?;
+            return Ok((Expr::binary(Operator::Implies, antecedent, consequent.clone()), consequent));
+        }
+
+        let start = self.pos;
+        let main = self.parse_clause()?;
+        self.skip_filler();
+        let connective = match self.peek_kind() {
+            Some(TokenKind::Connective(c)) => *c,
+            _ => return Ok((main.clone(), main)),
+        };
+        let saw_only = self.tokens[start..self.pos]
+            .iter()
+            .any(|t| matches!(&t.kind, TokenKind::Word(w) if self.lexicon.is_restrictive(w)));
+        let connective_span = self.here();
+        self.pos += 1;
+        let condition = self.parse_clause()?;
+
+        let root = match connective {
+            Connective::If if saw_only => Expr::binary(Operator::Implies, main.clone(), condition),
+            Connective::OnlyIf => Expr::binary(Operator::Implies, main.clone(), condition),
+            Connective::If | Connective::Whenever => Expr::binary(Operator::Converse, main.clone(), condition),
+            Connective::Unless => Expr::binary(Operator::Converse, main.clone(), Expr::Not(Box::new(condition))),
+            Connective::Iff => Expr::binary(Operator::Iff, main.clone(), condition),
+            Connective::Then => {
+                return Err(ParseError::new("'then' without a leading 'if'", connective_span));
+            }
+        };
+        Ok((root, main))
+    }
+}
+
+/// Variables in order of first appearance, the bits of each integer placed
+/// together (least significant first) where the integer first appears. Integers
+/// whose comparisons folded to a constant still get their bits, at the end.
+pub(super) fn allocate_bits(variables: &[Variable], integers: &[(Variable, usize)]) -> Vec<Variable> {
+    let mut allocated: Vec<Variable> = Vec::new();
+    let place = |allocated: &mut Vec<Variable>, var: &Variable| {
+        if var.bit.is_none() {
+            allocated.push(var.clone());
+        } else if !allocated.iter().any(|v| v.name == var.name && v.bit.is_some()) {
+            let (_, width) = integers.iter().find(|(v, _)| v.name == var.name).expect("integer compared by the parser");
+            allocated.extend((0..*width).map(|bit| Variable { bit: Some(bit), ..var.clone() }));
+        }
+    };
+    for var in variables {
+        place(&mut allocated, var);
+    }
+    for (var, _) in integers {
+        place(&mut allocated, &Variable { bit: Some(0), ..var.clone() });
+    }
+    allocated
+}
+
+/// Parse a token stream with the lexicon, declared types and disambiguation
+/// policy of `options`
+pub fn parse_with(tokens: &[Token], input_len: usize, options: &PsgOptions) -> Result<LogicExpression, ParseError> {
+    let (expression, chains) = parse_choosing(tokens, input_len, options, options.disambiguation, &[])?;
+    match chains.first() {
+        Some(&(span, _)) => {
+            let readings: Vec<String> =
+                readings(tokens, input_len, options)?.iter().map(|reading| reading.to_string()).collect();
+            let message = format!(
+                "ambiguous statement with {} readings: {}; add parentheses or choose a disambiguation policy",
+                readings.len(),
+                readings.join(" | ")
+            );
+            Err(ParseError::new(message, span))
+        }
+        None => Ok(expression),
+    }
+}
+
+/// Every reading of the statement (at most `MAX_READINGS`), one per grouping
+/// of its ambiguous chains; a single one when it is not ambiguous
+pub fn readings(tokens: &[Token], input_len: usize, options: &PsgOptions) -> Result<Vec<LogicExpression>, ParseError> {
+    let (first, chains) = parse_choosing(tokens, input_len, options, Disambiguation::Strict, &[])?;
+    let counts: Vec<usize> = chains.iter().map(|&(_, count)| count).collect();
+    let mut readings = vec![first];
+    let mut choices = vec![0; counts.len()];
+    while readings.len() < MAX_READINGS {
+        // Next choice vector, as an odometer over the chains' readings
+        let Some(i) = (0..counts.len()).rev().find(|&i| choices[i] + 1 < counts[i]) else {
+            break;
+        };
+        choices[i] += 1;
+        choices[i + 1..].fill(0);
+        readings.push(parse_choosing(tokens, input_len, options, Disambiguation::Strict, &choices)?.0);
+    }
+    Ok(readings)
+}
+
+/// Parse, taking reading `choices[i]` of the `i`-th ambiguous chain under the
+/// strict policy; returns the ambiguous chains met
+fn parse_choosing(
+    tokens: &[Token],
+    input_len: usize,
+    options: &PsgOptions,
+    disambiguation: Disambiguation,
+    choices: &[usize],
+) -> Result<(LogicExpression, Vec<(Span, usize)>), ParseError> {
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        input_len,
+        lexicon: &options.lexicon,
+        types: &options.types,
+        integers: Vec::new(),
+        disambiguation,
+        choices,
+        chains: Vec::new(),
+    };
+    let (root, main) = parser.parse_statement()?;
+
+    parser.skip_filler();
+    if let Some(token) = parser.peek() {
+        let message = match token.kind {
+            TokenKind::RParen => "unmatched ')'",
+            TokenKind::Variable(_) => "missing operator before this variable",
+            TokenKind::Connective(_) => "only one conditional connective per statement",
+            _ => "unexpected token",
+        };
+        return Err(ParseError::new(message, token.span));
+    }
+
+    let is_conditional = root != main;
+    let mut expression = LogicExpression::new(root);
+    expression.variables = allocate_bits(&expression.variables, &parser.integers);
+    if let (true, Expr::Var(var)) = (is_conditional, main) {
+        expression.consequent = Some(var);
+    }
+    Ok((expression, parser.chains))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::lexer::tokenize;
+    use crate::psg::parse_precedence;
+
+    #[test]
+    fn test_parse_precedence() {
+        let expr = parse_precedence(r#""a" or "b" and not "c" xor "d""#).unwrap();
+        assert_eq!(expr.to_string(), "a || ((b && !c) ^ d)");
+    }
+
+    #[test]
+    fn test_parse_parentheses_and_filler() {
+        let expr = parse_precedence(r#"the ("start" button or the "key") and the "brake" pedal"#).unwrap();
+        assert_eq!(expr.to_string(), "(start || key) && brake");
+        assert_eq!(
+            expr.variables,
+            vec![
+                Variable::new("start"),
+                Variable::new("key"),
+                Variable::new("brake"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_implication_direction() {
+        let car = parse_precedence(
+            r#"The car only starts if the "start" button is pressed and the "brake" pedal is pressed"#,
+        )
+        .unwrap();
+        assert_eq!(car.to_string(), "car starts -> (start && brake)");
+        assert_eq!(car.consequent, Some(Variable::new("car starts")));
+        assert_eq!(car.consequent.unwrap().span, Span::new(0, 19));
+
+        let cases = [
+            (r#"the "door" opens if the "badge" is valid"#, "door <- badge"),
+            (r#"the "door" opens only if the "badge" is valid"#, "door -> badge"),
+            (r#"the "alarm" sounds whenever the "door" is open"#, "alarm <- door"),
+            (r#"If the "badge" is valid then the "door" opens"#, "badge -> door"),
+            (r#"the "door" stays shut unless the "badge" is valid"#, "door <- !badge"),
+            (r#""a" if and only if "b" or "c""#, "a <-> (b || c)"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_precedence(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_negation() {
+        let cases = [
+            (r#"the "door" is not open"#, "!door"),
+            (r#"the "door" isn't open and the "alarm" is armed"#, "!door && alarm"),
+            (r#"not ("a" or "b") and "c" is never off"#, "!(a || b) && !c"),
+            (r#""a" and not "b""#, "a && !b"),
+            (r#"the "door" is not not open"#, "door"),
+            (r#"open the "door" unless the "alarm" is armed"#, "door <- !alarm"),
+            (r#"the car does not start if the "key" is missing"#, "!car start <- key"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_precedence(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_integer_comparisons() {
+        let types: HashMap<String, VarType> = [("speed", 8), ("gear", 3), ("limit", 8)]
+            .map(|(name, width)| (name.to_string(), VarType::UInt { width }))
+            .into();
+        let options = PsgOptions { types, ..PsgOptions::default() };
+        let parse_typed = |input: &str| parse_with(&tokenize(input, &options.lexicon)?, input.len(), &options);
+
+        let expr = parse_typed(r#"the "brake" is off and the "speed" is greater than 60 and the "gear" equals 2"#).unwrap();
+        let labels: Vec<String> = expr.variables.iter().map(|v| v.to_string()).collect();
+        assert_eq!(labels[..3], ["brake", "speed[0]", "speed[1]"]);
+        assert_eq!(labels[8..12], ["speed[7]", "gear[0]", "gear[1]", "gear[2]"]);
+        assert_eq!(expr.variables[1].span, Span::new(27, 34));
+
+        let value = |speed: u64, gear: u64| {
+            move |v: &Variable| match v.name.as_str() {
+                "speed" => speed >> v.bit.unwrap() & 1 == 1,
+                "gear" => gear >> v.bit.unwrap() & 1 == 1,
+                "limit" => 70 >> v.bit.unwrap() & 1 == 1,
+                _ => true,
+            }
+        };
+        assert!(expr.root.eval(&value(61, 2)));
+        assert!(!expr.root.eval(&value(60, 2)));
+        assert!(!expr.root.eval(&value(200, 3)));
+
+        let expr = parse_typed(r#""speed" is not above the "limit" or "gear" != 0"#).unwrap();
+        assert!(expr.root.eval(&value(70, 0)) && expr.root.eval(&value(90, 1)) && !expr.root.eval(&value(71, 0)));
+        let folded = parse_typed(r#""gear" >= 0 and "speed" < 300"#).unwrap();
+        assert_eq!(folded.root, Expr::binary(Operator::And, Expr::Const(true), Expr::Const(true)));
+        assert_eq!(folded.variables.len(), 11, "bits of folded comparisons are still allocated");
+
+        assert!(parse_typed(r#""door" is greater than 2"#).unwrap_err().message.contains("not declared"));
+        assert!(parse_typed(r#""speed" and "door""#).unwrap_err().message.contains("needs a comparison"));
+        assert_eq!(parse_typed(r#""speed" is below"#).unwrap_err().span, Span::new(16, 16));
+    }
+
+    #[test]
+    fn test_parse_cardinality() {
+        let cases = [
+            (r#"at least 2 of "badge", "pin", "face" are valid"#, "#(badge, pin, face) >= 2"),
+            (r#"exactly one of "a", "b" or "c" is set and "d""#, "(#(a, b, c) == 1) && d"),
+            (r#"the "door" opens if at most 1 of the "x", not "y" and "z" fail"#, "door <- (#(x, !y, z) <= 1)"),
+            (r#"(at least 2 of "a", "b", "c") and "d""#, "(#(a, b, c) >= 2) && d"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_precedence(input).unwrap().to_string(), expected, "{}", input);
+        }
+        let expr = parse_precedence(r#"at least 2 of "badge", "pin", "face" are valid"#).unwrap();
+        let valid = |names: &'static [&str]| move |v: &Variable| names.contains(&v.name.as_str());
+        assert!(expr.root.eval(&valid(&["badge", "face"])));
+        assert!(!expr.root.eval(&valid(&["pin"])));
+
+        let err = parse_precedence(r#"at least some of "a", "b""#).unwrap_err();
+        assert!(err.message.contains("count"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_ambiguous_statements() {
+        let lexicon = Lexicon::english();
+        let with = |disambiguation: Disambiguation| PsgOptions { disambiguation, ..PsgOptions::default() };
+        let all = |input: &str| -> Vec<String> {
+            let tokens = tokenize(input, &lexicon).unwrap();
+            readings(&tokens, input.len(), &with(Disambiguation::Strict)).unwrap().iter().map(|r| r.to_string()).collect()
+        };
+        assert_eq!(all(r#""a" and "b" or "c""#), ["a && (b || c)", "(a && b) || c"]);
+        assert_eq!(all(r#""a" and "b" and the "c""#), ["(a && b) && c"]);
+        assert_eq!(all(r#""a" and ("b" or "c")"#).len(), 1);
+        // (a && (b || c)) && d and a && ((b || c) && d) are one reading
+        assert_eq!(all(r#""a" and "b" or "c" and "d""#).len(), 4);
+        assert_eq!(all(r#"the "x" opens if ("a" and "b" or "c") xor "d" or "e""#).len(), 4);
+
+        let input = r#"the "x" opens if "a" and "b" or "c""#;
+        let tokens = tokenize(input, &lexicon).unwrap();
+        let err = parse_with(&tokens, input.len(), &with(Disambiguation::Strict)).unwrap_err();
+        assert_eq!(err.span, Span::new(17, 35));
+        assert!(err.message.contains("2 readings: x <- (a && (b || c)) | x <- ((a && b) || c)"), "{}", err.message);
+        let read = |policy| parse_with(&tokens, input.len(), &with(policy)).unwrap().to_string();
+        assert_eq!(read(Disambiguation::LeftAssoc), "x <- ((a && b) || c)");
+        assert_eq!(read(Disambiguation::Precedence), "x <- ((a && b) || c)");
+        let input = r#""a" or "b" and "c""#;
+        let tokens = tokenize(input, &lexicon).unwrap();
+        let read = |policy| parse_with(&tokens, input.len(), &with(policy)).unwrap().to_string();
+        assert_eq!(read(Disambiguation::LeftAssoc), "(a || b) && c");
+        assert_eq!(read(Disambiguation::Precedence), "a || (b && c)");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_precedence(r#"("a" and "b""#).is_err());
+        assert!(parse_precedence(r#""a" and "b")"#).is_err());
+        assert!(parse_precedence(r#""a" "b""#).is_err());
+        assert!(parse_precedence(r#"if "a" "b""#).is_err());
+        assert!(parse_precedence(r#""a" if "b" if "c""#).is_err());
+        let err = parse_precedence(r#""a" and"#).unwrap_err();
+        assert_eq!(err.span, Span::new(7, 7));
+    }
+}
@@ -0,0 +1,252 @@
+//! Pretty printing of statements and circuits
+//!
+//! `english` renders a statement as normalized English that the parser reads
+//! back under the strict disambiguation policy: variables are quoted, chains of
+//! one operator stay flat, and every other nested group gets parentheses. A
+//! top-level implication keeps its connective (`"s" only if "c"`), while nested
+//! implications are spelled out with `not` and `or`. Bits of integer variables
+//! are quoted as `"speed[3]"`, because comparisons are already lowered to bits.
+//!
+//! `from_circuit` reads a compiled circuit back into a syntax tree, one tree per
+//! gate output, so the optimized circuit can be printed in English for the
+//! prover (with the original names) or in infix for the public (with the
+//! hashes). Shared gates are repeated at every use. Gate patterns are read back
+//! as the operators they implement: `!(a ^ b)` as `a <-> b` and `!a || b` as
+//! `a -> b`, after De Morgan's laws turn `!(a && !b)` into `!a || b`.
+
+use std::collections::HashMap;
+
+use super::{Comparison, Expr, LogicExpression, Operator, Variable};
+use crate::partitioned_gc::{Circuit, GateType};
+
+fn quote(var: &Variable) -> String {
+    format!("\"{}\"", var.to_string().replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn phrase(op: Comparison) -> &'static str {
+    match op {
+        Comparison::Lt => "fewer than",
+        Comparison::Le => "at most",
+        Comparison::Eq => "exactly",
+        Comparison::Ne => "different from",
+        Comparison::Gt => "more than",
+        Comparison::Ge => "at least",
+    }
+}
+
+struct English<'a> {
+    /// Variable a constant is spelled with (`"a" or not "a"`)
+    witness: Option<&'a Variable>,
+}
+
+impl English<'_> {
+    /// Implications spelled with `not` and `or`, equivalence with `xor`
+    fn lowered(op: Operator, lhs: &Expr, rhs: &Expr) -> Expr {
+        let not = |e: &Expr| Expr::Not(Box::new(e.clone()));
+        match op {
+            Operator::Implies => Expr::binary(Operator::Or, not(lhs), rhs.clone()),
+            Operator::Converse => Expr::binary(Operator::Or, lhs.clone(), not(rhs)),
+            Operator::Iff => not(&Expr::binary(Operator::Xor, lhs.clone(), rhs.clone())),
+            _ => Expr::binary(op, lhs.clone(), rhs.clone()),
+        }
+    }
+
+    /// Operands of a chain of `op`, flattened
+    fn chain<'e>(expr: &'e Expr, op: Operator, operands: &mut Vec<&'e Expr>) {
+        match expr {
+            Expr::Binary { op: inner, lhs, rhs } if *inner == op => {
+                English::chain(lhs, op, operands);
+                English::chain(rhs, op, operands);
+            }
+            _ => operands.push(expr),
+        }
+    }
+
+    /// An operand: anything but a variable, a constant or a negation gets parentheses
+    fn operand(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Var(_) | Expr::Const(_) | Expr::Not(_) => self.expr(expr),
+            _ => format!("({})", self.expr(expr)),
+        }
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Var(var) => quote(var),
+            Expr::Const(value) => match self.witness {
+                Some(var) if *value => format!("({} or not {})", quote(var), quote(var)),
+                Some(var) => format!("({} and not {})", quote(var), quote(var)),
+                None => value.to_string(),
+            },
+            Expr::Not(inner) => format!("not {}", self.operand(inner)),
+            Expr::Binary { op: op @ (Operator::And | Operator::Or | Operator::Xor), .. } => {
+                let mut operands = Vec::new();
+                English::chain(expr, *op, &mut operands);
+                let word = match op {
+                    Operator::And => " and ",
+                    Operator::Or => " or ",
+                    _ => " xor ",
+                };
+                operands.iter().map(|e| self.operand(e)).collect::<Vec<_>>().join(word)
+            }
+            Expr::Binary { op, lhs, rhs } => self.expr(&English::lowered(*op, lhs, rhs)),
+            Expr::Threshold { op, k, operands } => {
+                let items: Vec<String> = operands.iter().map(|e| self.operand(e)).collect();
+                format!("{} {} of {}", phrase(*op), k, items.join(", "))
+            }
+        }
+    }
+
+    fn statement(&self, root: &Expr) -> String {
+        match root {
+            Expr::Binary { op, lhs, rhs } => {
+                let connective = match op {
+                    Operator::Implies => "only if",
+                    Operator::Converse => "if",
+                    Operator::Iff => "if and only if",
+                    _ => return self.expr(root),
+                };
+                format!("{} {} {}", self.expr(lhs), connective, self.expr(rhs))
+            }
+            _ => self.expr(root),
+        }
+    }
+}
+
+/// Normalized English of a statement, with its variable names quoted
+pub(super) fn english(expression: &LogicExpression) -> String {
+    English { witness: expression.variables.first() }.statement(&expression.root)
+}
+
+/// Negation, pushed through an AND or OR when that cancels another negation
+fn not(expr: Expr) -> Expr {
+    let negated = |e: &Expr| matches!(e, Expr::Not(_));
+    match expr {
+        Expr::Const(value) => Expr::Const(!value),
+        Expr::Not(inner) => *inner,
+        Expr::Binary { op: op @ (Operator::And | Operator::Or), lhs, rhs } if negated(&lhs) || negated(&rhs) => {
+            let dual = if op == Operator::And { GateType::Or } else { GateType::And };
+            gate(dual, not(*lhs), not(*rhs))
+        }
+        Expr::Binary { op: Operator::Xor, lhs, rhs } => Expr::Binary { op: Operator::Iff, lhs, rhs },
+        Expr::Binary { op: Operator::Iff, lhs, rhs } => Expr::Binary { op: Operator::Xor, lhs, rhs },
+        other => Expr::Not(Box::new(other)),
+    }
+}
+
+fn gate(gate_type: GateType, a: Expr, b: Expr) -> Expr {
+    match (gate_type, a, b) {
+        (GateType::And, Expr::Const(false), _) | (GateType::And, _, Expr::Const(false)) => Expr::Const(false),
+        (GateType::Or, Expr::Const(true), _) | (GateType::Or, _, Expr::Const(true)) => Expr::Const(true),
+        (GateType::And, Expr::Const(true), e) | (GateType::And, e, Expr::Const(true)) => e,
+        (GateType::Or, Expr::Const(false), e) | (GateType::Or, e, Expr::Const(false)) => e,
+        (GateType::Xor, Expr::Const(value), e) | (GateType::Xor, e, Expr::Const(value)) => {
+            if value { not(e) } else { e }
+        }
+        (GateType::Xor, a, b) if a == b => Expr::Const(false),
+        (GateType::And | GateType::Or, a, b) if a == b => a,
+        (GateType::Or, Expr::Not(a), b) if !matches!(b, Expr::Not(_)) => {
+            Expr::Binary { op: Operator::Implies, lhs: a, rhs: Box::new(b) }
+        }
+        (GateType::Or, a, Expr::Not(b)) => Expr::Binary { op: Operator::Converse, lhs: Box::new(a), rhs: b },
+        (GateType::And, a, b) => Expr::binary(Operator::And, a, b),
+        (GateType::Or, a, b) => Expr::binary(Operator::Or, a, b),
+        (GateType::Xor, a, b) => Expr::binary(Operator::Xor, a, b),
+        (GateType::Not, ..) => unreachable!("NOT gates have one input"),
+    }
+}
+
+/// Statement computed by the first output of `circuit`, `variables[i]` entering
+/// on `circuit.input_wires[i]`
+pub(super) fn from_circuit(circuit: &Circuit, variables: &[Variable]) -> LogicExpression {
+    assert_eq!(circuit.input_wires.len(), variables.len(), "one variable per input wire");
+    let mut exprs: HashMap<usize, Expr> =
+        circuit.input_wires.iter().zip(variables).map(|(&wire, var)| (wire, Expr::Var(var.clone()))).collect();
+    for g in &circuit.gates {
+        let input = |wire: usize| {
+            exprs.get(&wire).cloned().unwrap_or_else(|| panic!("wire {} is used before it is driven", wire))
+        };
+        let expr = match (g.gate_type, g.right_wire) {
+            (GateType::Not, _) => not(input(g.left_wire)),
+            (gate_type, Some(right)) => gate(gate_type, input(g.left_wire), input(right)),
+            (_, None) => panic!("binary gate driving wire {} has no right wire", g.output_wire),
+        };
+        exprs.insert(g.output_wire, expr);
+    }
+    let output = circuit.output_wires.first().expect("circuit has an output");
+    let root = exprs.remove(output).unwrap_or_else(|| panic!("output wire {} is not driven", output));
+    let consequent = match &root {
+        Expr::Binary { op: Operator::Implies | Operator::Converse | Operator::Iff, lhs, .. } => match lhs.as_ref() {
+            Expr::Var(var) => Some(var.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    LogicExpression { variables: variables.to_vec(), root, consequent }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psg::{
+        check_circuits, check_expressions, lexer, parse_policy, parse_precedence, parser, polylithic_syntax_gen_with, statement_readings,
+        PsgOptions, VarType,
+    };
+
+    #[test]
+    fn test_english_is_normalized_and_parses_back() {
+        let cases = [
+            (
+                r#"The car only starts if the "start" button and the "brake" pedal are pressed"#,
+                r#""car starts" only if "start" and "brake""#,
+            ),
+            (r#""a" and "b" or not "c" and "d""#, r#"("a" and "b") or (not "c" and "d")"#),
+            (r#"(at least 2 of "x", "y", "z" are set) and "w""#, r#"(at least 2 of "x", "y", "z") and "w""#),
+            (r#""a" xor not ("b" or "say \"hi\"")"#, r#""a" xor not ("b" or "say \"hi\"")"#),
+        ];
+        for (input, expected) in cases {
+            let expression = parse_precedence(input).unwrap();
+            let printed = english(&expression);
+            assert_eq!(printed, expected, "{}", input);
+            // Strict parsing accepts the printed form and reads the same statement
+            let tokens = lexer::tokenize(&printed, &PsgOptions::default().lexicon).unwrap();
+            let reparsed = parser::parse_with(&tokens, printed.len(), &PsgOptions::default()).unwrap();
+            assert!(check_expressions(&expression, &reparsed).is_equivalent(), "{}", printed);
+            assert_eq!(reparsed.consequent, expression.consequent);
+        }
+
+        // Nested implications have no connective of their own
+        let policy = parse_policy("(a -> b) ^ !(c <-> d)").unwrap();
+        assert_eq!(english(&policy), r#"(not "a" or "b") xor not (not ("c" xor "d"))"#);
+    }
+
+    #[test]
+    fn test_circuit_round_trips_through_english() {
+        let mut options = PsgOptions::default();
+        options.types.insert("speed".to_string(), VarType::UInt { width: 4 });
+        for input in [
+            r#"The car only starts if the "start" button and the "brake" pedal are pressed"#,
+            r#""alarm" if and only if ("door" xor "window")"#,
+            r#"exactly one of "a", "b", "c" is chosen unless "override" is set"#,
+            r#""go" only if the "speed" is less than 5 and not "stop""#,
+            r#"at least 3 of "p", "q", "r", "s", "t""#,
+        ] {
+            let statement = statement_readings(input, &options).unwrap().remove(0);
+            let circuit = polylithic_syntax_gen_with(input, &options).unwrap();
+
+            // Prover side: the optimized circuit in English, with the original names
+            let recovered = circuit.to_expression(&statement.variables);
+            let printed = recovered.to_english();
+            let tokens = lexer::tokenize(&printed, &options.lexicon).unwrap();
+            let reparsed = parser::parse_with(&tokens, printed.len(), &PsgOptions::default()).unwrap();
+            assert!(check_expressions(&statement, &reparsed).is_equivalent(), "{} => {}", input, printed);
+
+            // Public side: the same circuit over hashes
+            let hashed = circuit.hashed_expression();
+            assert!(hashed.variables.iter().all(|var| circuit.wire_map.contains_key(&var.name)));
+            let names: Vec<String> = hashed.variables.iter().map(|var| var.name.clone()).collect();
+            let compiled = hashed.compile(&names).circuit;
+            assert!(check_circuits(&compiled, &circuit.circuit).unwrap().is_equivalent(), "{}", hashed);
+        }
+    }
+}
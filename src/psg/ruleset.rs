@@ -0,0 +1,232 @@
+//! Rule sets: several statements compiled into one circuit
+//!
+//! A rule set is a document of English statements, one rule per sentence.
+//! Sentences end with `.` or `;`. A sentence may start with a label, a single
+//! word followed by a colon (`R1: The car only starts if ...`), which becomes the
+//! rule's ID. Unlabeled rules are numbered by position (`rule-1`, `rule-2`, ...),
+//! so their IDs change when rules are inserted or reordered; label the rules of
+//! a published set.
+//!
+//! The rules share one variable list: a variable named in several rules is
+//! hashed once and enters the circuit on a single input wire. Each rule drives
+//! an output wire of its own, or all of them are combined into one conjunction
+//! (`RuleOutputs`). Structural hashing merges rules that compute the same
+//! function into one wire, so each later duplicate gets a copy of it through two
+//! NOT gates. The gate set has no buffer, and NOT gates are free under free-XOR
+//! garbling, so every rule keeps an output wire of its own at no cost.
+//!
+//! With a controlled grammar, references such as `that pedal` resolve to
+//! variables of earlier rules too. Rules parsed by another front end
+//! (`parse_policy`, `parse_snort_rule`) are combined the same way from `Rule`
+//! values.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::controlled::{self, Discourse};
+use super::lexer::{self, Token, TokenKind};
+use super::{parser, LogicExpression, ParseError, PsgOptions, Span, Variable};
+
+/// A parsed rule and its stable ID
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub id: String,
+    pub expression: LogicExpression,
+}
+
+/// Output wires of a compiled rule set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuleOutputs {
+    /// One output wire per rule, in rule order
+    #[default]
+    PerRule,
+    /// A single output wire, true when every rule holds
+    Conjunction,
+}
+
+/// Public metadata of a rule in a compiled rule set
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompiledRule {
+    pub id: String,
+    pub expression: String,         // hashed expression, as built by build_regexp
+    pub consequent: Option<String>, // hashed consequent variable of a conditional rule
+    pub output_wire: usize,         // shared by every rule under RuleOutputs::Conjunction
+}
+
+/// Split `tokens` into sentences at `.` and `;`, with the span of each terminator
+/// (empty at the end of the input)
+fn sentences(tokens: &[Token], input_len: usize) -> Vec<(&[Token], Span)> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if matches!(token.kind, TokenKind::Punct('.' | ';')) {
+            sentences.push((&tokens[start..i], token.span));
+            start = i + 1;
+        }
+    }
+    sentences.push((&tokens[start..], Span::new(input_len, input_len)));
+    sentences.retain(|(sentence, _)| !sentence.iter().all(|t| matches!(t.kind, TokenKind::Punct(_))));
+    sentences
+}
+
+/// Parse a rule set, one English statement per sentence, with the options of
+/// `polylithic_syntax_gen_with`
+pub fn parse_rule_set(input: &str, options: &PsgOptions) -> Result<Vec<Rule>, ParseError> {
+    let tokens = lexer::tokenize(input, &options.lexicon)?;
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut ids = HashSet::new();
+    let mut discourse = Discourse::default();
+    for (sentence, end) in sentences(&tokens, input.len()) {
+        let (id, body) = match sentence {
+            [Token { kind: TokenKind::Word(label), span }, Token { kind: TokenKind::Punct(':'), .. }, body @ ..] => {
+                if body.is_empty() {
+                    return Err(ParseError::new(format!("rule '{}' has no statement", label), *span));
+                }
+                ((label.clone(), *span), body)
+            }
+            _ => ((format!("rule-{}", rules.len() + 1), sentence[0].span), sentence),
+        };
+        if !ids.insert(id.0.clone()) {
+            return Err(ParseError::new(format!("duplicate rule ID '{}'", id.0), id.1));
+        }
+        let expression = match &options.grammar {
+            Some(grammar) => {
+                let body = controlled::recognize(body, grammar, &options.lexicon, &mut discourse)?;
+                parser::parse_with(&body, end.start, options)?
+            }
+            None => parser::parse_with(body, end.start, options)?,
+        };
+        rules.push(Rule { id: id.0, expression });
+    }
+    if rules.is_empty() {
+        return Err(ParseError::new("rule set has no statements", Span::new(0, input.len())));
+    }
+    Ok(rules)
+}
+
+/// Distinct variables of all rules, in order of first appearance
+pub(super) fn shared_variables(rules: &[Rule]) -> Vec<Variable> {
+    let mut variables: Vec<Variable> = Vec::new();
+    for rule in rules {
+        for var in &rule.expression.variables {
+            if !variables.contains(var) {
+                variables.push(var.clone());
+            }
+        }
+    }
+    variables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partitioned_gc::PartitionedGCScheme;
+    use crate::psg::{polylithic_rule_set_gen, Aig, Satisfiability, VarType, VariableHasher};
+
+    const RULES: &str = r#"
+        ignition: The car only starts if the "start" button and the "brake" pedal are pressed.
+        parking: The "brake" pedal is pressed unless the "gear" is engaged;
+        The "alarm" sounds if the "door" is open and not the "brake" pedal.
+    "#;
+
+    #[test]
+    fn test_parse_rule_set_ids_and_errors() {
+        let rules = parse_rule_set(RULES, &PsgOptions::default()).unwrap();
+        let ids: Vec<&str> = rules.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["ignition", "parking", "rule-3"]);
+        assert_eq!(rules[0].expression.to_string(), "car starts -> (start && brake)");
+        assert_eq!(shared_variables(&rules).len(), 6, "brake is shared by all three rules");
+
+        let duplicate = r#"a: "x" or "y". a: "y" or "z"."#;
+        let error = parse_rule_set(duplicate, &PsgOptions::default()).unwrap_err();
+        assert_eq!(error.message, "duplicate rule ID 'a'");
+        assert_eq!(&duplicate[error.span.start..error.span.end], "a");
+
+        // Spans point into the document, not into the sentence
+        let broken = r#"r1: "x" or "y". r2: "y" and ("z"."#;
+        let error = parse_rule_set(broken, &PsgOptions::default()).unwrap_err();
+        assert!(error.span.start > broken.find("r2").unwrap(), "{}", error);
+        assert!(parse_rule_set(" . ; ", &PsgOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_rule_set_shares_input_wires() {
+        let options = PsgOptions::default();
+        let set = polylithic_rule_set_gen(RULES, &options, RuleOutputs::PerRule).unwrap();
+        let rules = parse_rule_set(RULES, &options).unwrap();
+        let variables = shared_variables(&rules);
+        assert_eq!(set.circuit.input_wires.len(), variables.len());
+        assert_eq!(set.wire_map.len(), variables.len());
+        assert_eq!(set.circuit.output_wires.len(), 3);
+        assert_eq!(set.output_wire("parking"), Some(set.circuit.output_wires[1]));
+        assert_eq!(set.output_wire("missing"), None);
+
+        // Every output computes its own rule over the shared inputs
+        let hasher = VariableHasher::from_manifest(&set.hashing, &set.hash_key).unwrap();
+        let garbled = PartitionedGCScheme::garble_circuit(&set.circuit).unwrap();
+        let aig = Aig::from_circuit(&set.circuit);
+        for assignment in 0..1u32 << variables.len() {
+            let value = |var: &Variable| assignment >> variables.iter().position(|v| v == var).unwrap() & 1 == 1;
+            let mut inputs = vec![0; variables.len()];
+            for var in &variables {
+                inputs[set.wire_map[&hasher.hash(&var.name)]] = value(var) as u8;
+            }
+            let expected: Vec<u8> = rules.iter().map(|r| r.expression.root.eval(&value) as u8).collect();
+            let mut labels = PartitionedGCScheme::encode_inputs(&garbled, &inputs);
+            PartitionedGCScheme::evaluate_garbled(&garbled, &mut labels);
+            assert_eq!(PartitionedGCScheme::decode_outputs(&garbled, &labels), Some(expected.clone()));
+            let bits: Vec<bool> = inputs.iter().map(|&b| b == 1).collect();
+            assert_eq!(aig.eval(&bits).into_iter().map(u8::from).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn test_equal_and_constant_rules_get_distinct_outputs() {
+        let options = PsgOptions::default();
+        for input in [
+            r#"r1: "a" and "b". r2: "a" and "b"."#,
+            r#"r1: "a". r2: "a". r3: "a"."#,
+            r#"r1: "a" xor "a". r2: "b" and not "b"."#,
+        ] {
+            let set = polylithic_rule_set_gen(input, &options, RuleOutputs::PerRule).unwrap();
+            set.circuit.validate().unwrap();
+            let wires: HashSet<usize> = set.rules.iter().map(|rule| rule.output_wire).collect();
+            assert_eq!(wires.len(), set.rules.len(), "{}", input);
+
+            let rules = parse_rule_set(input, &options).unwrap();
+            let variables = shared_variables(&rules);
+            let hasher = VariableHasher::from_manifest(&set.hashing, &set.hash_key).unwrap();
+            let garbled = PartitionedGCScheme::garble_circuit(&set.circuit).unwrap();
+            for assignment in 0..1u32 << variables.len() {
+                let value = |var: &Variable| assignment >> variables.iter().position(|v| v == var).unwrap() & 1 == 1;
+                let mut inputs = vec![0; variables.len()];
+                for var in &variables {
+                    inputs[set.wire_map[&hasher.hash(&var.name)]] = value(var) as u8;
+                }
+                let expected: Vec<u8> = rules.iter().map(|r| r.expression.root.eval(&value) as u8).collect();
+                let mut labels = PartitionedGCScheme::encode_inputs(&garbled, &inputs);
+                PartitionedGCScheme::evaluate_garbled(&garbled, &mut labels);
+                assert_eq!(PartitionedGCScheme::decode_outputs(&garbled, &labels), Some(expected), "{}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rule_set_conjunction() {
+        let mut options = PsgOptions::default();
+        options.types.insert("speed".to_string(), VarType::UInt { width: 8 });
+        let input = r#"
+            slow: The "speed" is less than 10;
+            fast: The "speed" is greater than 100.
+        "#;
+        let set = polylithic_rule_set_gen(input, &options, RuleOutputs::Conjunction).unwrap();
+        assert_eq!(set.circuit.output_wires.len(), 1);
+        assert_eq!(set.circuit.input_wires.len(), 8, "one integer shared by both rules");
+        assert!(set.rules.iter().all(|rule| rule.output_wire == set.circuit.output_wires[0]));
+        assert!(
+            matches!(set.satisfiability, Some(Satisfiability::Unsatisfiable { .. })),
+            "the rules contradict each other"
+        );
+    }
+}
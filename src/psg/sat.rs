@@ -0,0 +1,455 @@
+//! CDCL satisfiability solver over `Clausal` CNF
+//!
+//! Conflict-driven clause learning with two-watched-literal unit propagation:
+//! each conflict is analyzed back to its first unique implication point, the
+//! learned clause is added and the search jumps back to the level where that
+//! clause becomes unit. Decisions pick the most active variable (activity is
+//! bumped for variables involved in conflicts, seeded with occurrence counts)
+//! and reuse its last value. The search restarts on the Luby sequence.
+//!
+//! Circuits are checked through the Tseitin encoding of their AND/XOR-inverter
+//! graph, e.g. to warn about statements that are always true or always false.
+//! That check runs on every statement, so it gives up after `CONFLICT_BUDGET`
+//! conflicts instead of blocking circuit generation.
+
+use super::aig::{Aig, Lit};
+use super::normal::{Clausal, Literal, NormalForm};
+use crate::partitioned_gc::Circuit;
+
+/// Conflicts `satisfiability` spends on each polarity before reporting `Unknown`
+pub const CONFLICT_BUDGET: usize = 20_000;
+
+/// Conflicts between restarts, times the Luby sequence
+const RESTART_UNIT: usize = 100;
+
+fn code(lit: Literal) -> usize {
+    2 * lit.var + !lit.positive as usize
+}
+
+/// `i`-th element (from 0) of the Luby sequence 1, 1, 2, 1, 1, 2, 4, ...
+fn luby(mut i: usize) -> usize {
+    let (mut size, mut exponent) = (1, 0);
+    while size < i + 1 {
+        exponent += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        exponent -= 1;
+        i %= size;
+    }
+    1 << exponent
+}
+
+/// Result of a search; `Unknown` when the conflict budget ran out
+enum Outcome {
+    Satisfiable(Vec<bool>),
+    Unsatisfiable,
+    Unknown,
+}
+
+struct Solver {
+    clauses: Vec<Vec<Literal>>,
+    /// Literal code -> clauses watching that literal
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    /// Decision level at which each variable was assigned
+    levels: Vec<usize>,
+    /// Clause that implied each variable, `None` for decisions and level-0 units
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Literal>,
+    /// Trail length at the start of each decision level
+    trail_lim: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    increment: f64,
+    /// Last value of each variable, tried first when it is decided again
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+}
+
+impl Solver {
+    fn value(&self, lit: Literal) -> Option<bool> {
+        self.values[lit.var].map(|v| v == lit.positive)
+    }
+
+    fn assign(&mut self, lit: Literal, reason: Option<usize>) {
+        self.values[lit.var] = Some(lit.positive);
+        self.levels[lit.var] = self.trail_lim.len();
+        self.reasons[lit.var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Watch the first two literals of a clause of at least two literals
+    fn add_clause(&mut self, clause: Vec<Literal>) -> usize {
+        let c = self.clauses.len();
+        self.watches[code(clause[0])].push(c);
+        self.watches[code(clause[1])].push(c);
+        self.clauses.push(clause);
+        c
+    }
+
+    /// Unit propagation; the falsified clause on conflict
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let falsified = !self.trail[self.propagated];
+            self.propagated += 1;
+            let watching = std::mem::take(&mut self.watches[code(falsified)]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = None;
+            for (k, &c) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[k..]);
+                    break;
+                }
+                let clause = &mut self.clauses[c];
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if self.values[first.var].map(|v| v == first.positive) == Some(true) {
+                    kept.push(c);
+                    continue;
+                }
+                let replacement = (2..clause.len())
+                    .find(|&i| self.values[clause[i].var].map(|v| v == clause[i].positive) != Some(false));
+                match replacement {
+                    Some(i) => {
+                        clause.swap(1, i);
+                        let watch = code(clause[1]);
+                        self.watches[watch].push(c);
+                    }
+                    None => {
+                        kept.push(c);
+                        match self.value(first) {
+                            Some(false) => conflict = Some(c),
+                            _ => self.assign(first, Some(c)),
+                        }
+                    }
+                }
+            }
+            self.watches[code(falsified)] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.increment;
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+            self.increment *= 1e-100;
+        }
+    }
+
+    /// First-UIP clause learned from a conflict, asserting literal first and a
+    /// literal of the backjump level second, with the backjump level
+    fn analyze(&mut self, conflict: usize) -> (Vec<Literal>, usize) {
+        let current = self.trail_lim.len();
+        let mut learned = vec![Literal::new(0, true)]; // asserting literal, set below
+        let mut pending = 0;
+        let mut clause = conflict;
+        let mut pivot = None;
+        let mut index = self.trail.len();
+        loop {
+            for k in 0..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                if Some(lit.var) == pivot || self.seen[lit.var] || self.levels[lit.var] == 0 {
+                    continue;
+                }
+                self.seen[lit.var] = true;
+                self.bump(lit.var);
+                if self.levels[lit.var] == current {
+                    pending += 1;
+                } else {
+                    learned.push(lit);
+                }
+            }
+            // Latest assignment of the current level involved in the conflict
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[lit.var] = false;
+            pending -= 1;
+            if pending == 0 {
+                learned[0] = !lit;
+                break;
+            }
+            clause = self.reasons[lit.var].expect("implied literals of the current level have a reason");
+            pivot = Some(lit.var);
+        }
+        for lit in &learned[1..] {
+            self.seen[lit.var] = false;
+        }
+        self.increment /= 0.95;
+
+        let level = match (1..learned.len()).max_by_key(|&i| self.levels[learned[i].var]) {
+            Some(i) => {
+                learned.swap(1, i);
+                self.levels[learned[1].var]
+            }
+            None => 0,
+        };
+        (learned, level)
+    }
+
+    /// Undo every assignment above decision level `level`
+    fn backjump(&mut self, level: usize) {
+        if self.trail_lim.len() <= level {
+            return;
+        }
+        for undone in self.trail.drain(self.trail_lim[level]..) {
+            self.values[undone.var] = None;
+            self.phases[undone.var] = undone.positive;
+        }
+        self.trail_lim.truncate(level);
+        self.propagated = self.trail.len();
+    }
+
+    /// Most active unassigned variable, decided at its saved phase; false when all are assigned
+    fn decide(&mut self) -> bool {
+        let next = (0..self.values.len())
+            .filter(|&v| self.values[v].is_none())
+            .max_by(|&a, &b| self.activity[a].total_cmp(&self.activity[b]).then(b.cmp(&a)));
+        match next {
+            Some(var) => {
+                self.trail_lim.push(self.trail.len());
+                self.assign(Literal::new(var, self.phases[var]), None);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// CDCL search over `clauses`, giving up after `budget` conflicts if one is set
+fn search(num_vars: usize, clauses: &[Vec<Literal>], budget: Option<usize>) -> Outcome {
+    let mut solver = Solver {
+        clauses: Vec::new(),
+        watches: vec![Vec::new(); 2 * num_vars],
+        values: vec![None; num_vars],
+        levels: vec![0; num_vars],
+        reasons: vec![None; num_vars],
+        trail: Vec::new(),
+        trail_lim: Vec::new(),
+        propagated: 0,
+        activity: vec![0.0; num_vars],
+        increment: 1.0,
+        phases: vec![false; num_vars],
+        seen: vec![false; num_vars],
+    };
+    let mut occurrences = vec![(0usize, 0usize); num_vars];
+    let mut units = Vec::new();
+    for clause in clauses {
+        let mut clause = clause.clone();
+        clause.sort();
+        clause.dedup();
+        if clause.windows(2).any(|w| w[0].var == w[1].var) {
+            continue; // contains x and !x
+        }
+        for lit in &clause {
+            if lit.positive { occurrences[lit.var].0 += 1 } else { occurrences[lit.var].1 += 1 }
+        }
+        match clause.len() {
+            0 => return Outcome::Unsatisfiable,
+            1 => units.push(clause[0]),
+            _ => {
+                solver.add_clause(clause);
+            }
+        }
+    }
+    for unit in units {
+        match solver.value(unit) {
+            Some(false) => return Outcome::Unsatisfiable,
+            Some(true) => {}
+            None => solver.assign(unit, None),
+        }
+    }
+    // Start from the variables that occur most, in their more frequent polarity
+    for (v, &(positive, negative)) in occurrences.iter().enumerate() {
+        solver.activity[v] = (positive + negative) as f64 * 1e-3;
+        solver.phases[v] = positive >= negative;
+    }
+
+    let (mut conflicts, mut restarts, mut next_restart) = (0, 0, RESTART_UNIT);
+    loop {
+        if let Some(conflict) = solver.propagate() {
+            if solver.trail_lim.is_empty() {
+                return Outcome::Unsatisfiable;
+            }
+            let (learned, level) = solver.analyze(conflict);
+            solver.backjump(level);
+            if learned.len() == 1 {
+                solver.assign(learned[0], None);
+            } else {
+                let asserting = learned[0];
+                let c = solver.add_clause(learned);
+                solver.assign(asserting, Some(c));
+            }
+            conflicts += 1;
+            if budget.is_some_and(|budget| conflicts >= budget) {
+                return Outcome::Unknown;
+            }
+            if conflicts >= next_restart {
+                restarts += 1;
+                next_restart = conflicts + RESTART_UNIT * luby(restarts);
+                solver.backjump(0);
+            }
+        } else if !solver.decide() {
+            return Outcome::Satisfiable(solver.values.iter().map(|v| v.unwrap_or(false)).collect());
+        }
+    }
+}
+
+/// Satisfying assignment of `clauses` over `num_vars` variables, `None` if unsatisfiable
+pub fn solve_clauses(num_vars: usize, clauses: &[Vec<Literal>]) -> Option<Vec<bool>> {
+    match search(num_vars, clauses, None) {
+        Outcome::Satisfiable(model) => Some(model),
+        Outcome::Unsatisfiable => None,
+        Outcome::Unknown => unreachable!("an unbounded search decides satisfiability"),
+    }
+}
+
+/// Satisfying assignment of a CNF (one value per variable, auxiliaries included)
+pub fn solve(cnf: &Clausal) -> Option<Vec<bool>> {
+    assert_eq!(cnf.form, NormalForm::Cnf, "only CNF can be solved");
+    solve_clauses(cnf.variables.len(), &cnf.terms)
+}
+
+/// Input values (in input order) under which `root` is true, `None` if there are none
+pub fn solve_aig(aig: &Aig, root: Lit) -> Option<Vec<bool>> {
+    solve_clauses(aig.nodes.len(), &aig.clauses(root)).map(|model| aig.input_values(&model))
+}
+
+/// `solve_aig` within a conflict budget
+fn solve_aig_within(aig: &Aig, root: Lit, budget: usize) -> Outcome {
+    match search(aig.nodes.len(), &aig.clauses(root), Some(budget)) {
+        Outcome::Satisfiable(model) => Outcome::Satisfiable(aig.input_values(&model)),
+        other => other,
+    }
+}
+
+/// Whether a statement's circuit can be true, false, or both; witnesses assign
+/// every input wire (in `input_wires` order)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Satisfiability {
+    Contingent { satisfying: Vec<(usize, bool)>, falsifying: Vec<(usize, bool)> },
+    /// Always true; `witness` is one (satisfying) assignment
+    Tautology { witness: Vec<(usize, bool)> },
+    /// Always false; `witness` is one (falsifying) assignment
+    Unsatisfiable { witness: Vec<(usize, bool)> },
+    /// The solver ran out of its conflict budget; the witnesses it did find
+    Unknown { satisfying: Option<Vec<(usize, bool)>>, falsifying: Option<Vec<(usize, bool)>> },
+}
+
+/// Satisfiability of the first output of `circuit`, spending at most
+/// `CONFLICT_BUDGET` conflicts on each polarity
+pub fn satisfiability(circuit: &Circuit) -> Satisfiability {
+    let aig = Aig::from_circuit(circuit);
+    let output = *aig.outputs.first().expect("circuit has an output");
+    let named = |values: Vec<bool>| aig.inputs.iter().copied().zip(values).collect::<Vec<_>>();
+    let witness = |outcome: Outcome| match outcome {
+        Outcome::Satisfiable(values) => Ok(Some(named(values))),
+        Outcome::Unsatisfiable => Ok(None),
+        Outcome::Unknown => Err(()),
+    };
+    let satisfying = witness(solve_aig_within(&aig, output, CONFLICT_BUDGET));
+    let falsifying = witness(solve_aig_within(&aig, !output, CONFLICT_BUDGET));
+    match (satisfying, falsifying) {
+        (Ok(Some(satisfying)), Ok(Some(falsifying))) => Satisfiability::Contingent { satisfying, falsifying },
+        (Ok(Some(witness)), Ok(None)) => Satisfiability::Tautology { witness },
+        (Ok(None), Ok(Some(witness))) => Satisfiability::Unsatisfiable { witness },
+        (Ok(None), Ok(None)) => unreachable!("a circuit output is either true or false"),
+        (satisfying, falsifying) => {
+            Satisfiability::Unknown { satisfying: satisfying.ok().flatten(), falsifying: falsifying.ok().flatten() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfies(clauses: &[Vec<Literal>], values: &[bool]) -> bool {
+        clauses.iter().all(|c| c.iter().any(|l| values[l.var] == l.positive))
+    }
+
+    #[test]
+    fn test_pigeonhole_and_random_instances() {
+        // 4 pigeons, 3 holes: unsatisfiable; p(i, h) = 3 * i + h
+        let p = |i: usize, h: usize, positive: bool| Literal::new(3 * i + h, positive);
+        let mut clauses: Vec<Vec<Literal>> = (0..4).map(|i| (0..3).map(|h| p(i, h, true)).collect()).collect();
+        for h in 0..3 {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    clauses.push(vec![p(i, h, false), p(j, h, false)]);
+                }
+            }
+        }
+        assert_eq!(solve_clauses(12, &clauses), None);
+        assert!(matches!(search(12, &clauses, Some(1)), Outcome::Unknown), "budget of one conflict");
+        clauses.retain(|c| c.len() == 2 || c[0].var < 9); // 3 pigeons fit
+        let model = solve_clauses(12, &clauses).unwrap();
+        assert!(satisfies(&clauses, &model));
+
+        // Random 3-SAT checked against brute force
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = |m: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % m) as usize
+        };
+        for _ in 0..50 {
+            let clauses: Vec<Vec<Literal>> =
+                (0..40).map(|_| (0..3).map(|_| Literal::new(next(10), next(2) == 0)).collect()).collect();
+            let brute = (0..1u32 << 10).any(|x| satisfies(&clauses, &(0..10).map(|i| x >> i & 1 == 1).collect::<Vec<_>>()));
+            match solve_clauses(10, &clauses) {
+                Some(model) => assert!(satisfies(&clauses, &model)),
+                None => assert!(!brute),
+            }
+            assert_eq!(solve_clauses(10, &clauses).is_some(), brute);
+        }
+    }
+
+    #[test]
+    fn test_circuit_satisfiability() {
+        let check = |input: &str| {
+            let expression = crate::psg::parse_precedence(input).unwrap();
+            let names: Vec<String> = expression.variables.iter().map(|v| v.name.clone()).collect();
+            satisfiability(&expression.compile(&names).circuit)
+        };
+        assert!(matches!(check(r#""a" or not "a" and "b" if "b""#), Satisfiability::Tautology { .. }));
+        assert!(matches!(check(r#""a" and "b" and not ("a" or "c")"#), Satisfiability::Unsatisfiable { .. }));
+        match check(r#""a" xor "b" and "c""#) {
+            Satisfiability::Contingent { satisfying, falsifying } => {
+                let value = |w: &[(usize, bool)]| w[0].1 ^ (w[1].1 && w[2].1);
+                assert!(value(&satisfying) && !value(&falsifying));
+            }
+            other => panic!("expected a contingent statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_large_cardinality_statement() {
+        // Adder trees of threshold statements defeated chronological backtracking
+        let names: Vec<String> = (0..200).map(|i| format!("\"v{}\"", i)).collect();
+        let input = format!("at least 100 of {}", names.join(", "));
+        let expression = crate::psg::parse_precedence(&input).unwrap();
+        let names: Vec<String> = expression.variables.iter().map(|v| v.name.clone()).collect();
+        let circuit = expression.compile(&names).circuit;
+        match satisfiability(&circuit) {
+            Satisfiability::Contingent { satisfying, falsifying } => {
+                let count = |w: &[(usize, bool)]| w.iter().filter(|(_, b)| *b).count();
+                assert!(count(&satisfying) >= 100 && count(&falsifying) < 100);
+            }
+            other => panic!("expected a contingent statement, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,578 @@
+//! Snort rule front end
+//!
+//! Reads a subset of Snort 2 rule syntax into a `LogicExpression`, so an IDS
+//! sensor can prove that a packet raised an alert without revealing the packet:
+//!
+//! ```text
+//! alert tcp $EXTERNAL_NET any -> 192.168.1.0/24 [80,443] (msg:"login"; flags:S; content:"POST"; nocase; sid:1000001;)
+//! ```
+//!
+//! Header fields are integer variables (see `SNORT_FIELDS`) compared with the
+//! rule's values: addresses by CIDR prefix, ports by value or range, the protocol
+//! by IP protocol number. A list holds when one of its plain entries holds and
+//! none of its negated ones; `<>` accepts either direction. Named sets such as
+//! `$HOME_NET` are Boolean variables (`src_ip in $HOME_NET`) evaluated by the sensor.
+//!
+//! Options are conjoined. Each `content` (`content:!"..."` negated) together with
+//! its modifiers, and each `flow` keyword, is a Boolean variable named after its
+//! option text; `flags` is lowered to the bits of `tcp_flags`. `msg`, `sid` and
+//! `rev` are kept and may appear once each, other metadata (`classtype`,
+//! `reference`, ...) is ignored and any other option is rejected. A port range
+//! must not end below its start.
+
+use std::net::Ipv4Addr;
+
+use super::integer::{self, and, not, or, Comparison};
+use super::parser::allocate_bits;
+use super::{Expr, LogicExpression, ParseError, Span, Variable};
+
+/// Packet fields a rule compares, with their widths in bits; each enters the
+/// circuit as that many input wires, least significant bit first
+pub const SNORT_FIELDS: [(&str, usize); 6] = [
+    ("ip_proto", 8),
+    ("src_ip", 32),
+    ("src_port", 16),
+    ("dst_ip", 32),
+    ("dst_port", 16),
+    ("tcp_flags", 8),
+];
+
+/// Options that do not affect whether a rule matches
+const IGNORED_OPTIONS: [&str; 6] = ["classtype", "priority", "reference", "gid", "metadata", "fast_pattern"];
+
+/// Options modifying the preceding `content`
+const CONTENT_MODIFIERS: [&str; 16] = [
+    "nocase",
+    "rawbytes",
+    "depth",
+    "offset",
+    "distance",
+    "within",
+    "http_client_body",
+    "http_cookie",
+    "http_raw_cookie",
+    "http_header",
+    "http_raw_header",
+    "http_method",
+    "http_uri",
+    "http_raw_uri",
+    "http_stat_code",
+    "http_stat_msg",
+];
+
+const FLOW_KEYWORDS: [&str; 9] = [
+    "to_client",
+    "to_server",
+    "from_client",
+    "from_server",
+    "established",
+    "not_established",
+    "stateless",
+    "no_stream",
+    "only_stream",
+];
+
+/// What the IDS does with a matching packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleAction {
+    Alert,
+    Log,
+    Pass,
+    Drop,
+    Reject,
+    Sdrop,
+}
+
+/// A parsed Snort rule
+#[derive(Debug, Clone)]
+pub struct SnortRule {
+    pub action: RuleAction,
+    pub msg: Option<String>,
+    pub sid: Option<u64>,
+    pub rev: Option<u64>,
+    /// Condition under which the rule fires
+    pub expression: LogicExpression,
+}
+
+/// A piece of the rule text and where it starts
+#[derive(Debug, Clone, Copy)]
+struct Field<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+impl<'a> Field<'a> {
+    fn span(&self) -> Span {
+        Span::new(self.start, self.start + self.text.len())
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::new(message, self.span())
+    }
+
+    /// Bytes `from..to` of the field
+    fn slice(&self, from: usize, to: usize) -> Field<'a> {
+        Field { text: &self.text[from..to], start: self.start + from }
+    }
+
+    fn trim(&self) -> Field<'a> {
+        let leading = self.text.len() - self.text.trim_start().len();
+        self.slice(leading, leading + self.text.trim().len())
+    }
+
+    /// Split at `separator` outside quotes and brackets
+    fn split(&self, separator: char) -> Vec<Field<'a>> {
+        let (mut parts, mut from) = (Vec::new(), 0);
+        let (mut depth, mut quoted, mut escaped) = (0usize, false, false);
+        for (i, c) in self.text.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                '[' if !quoted => depth += 1,
+                ']' if !quoted => depth = depth.saturating_sub(1),
+                c if c == separator && !quoted && depth == 0 => {
+                    parts.push(self.slice(from, i));
+                    from = i + c.len_utf8();
+                }
+                _ => {}
+            }
+        }
+        parts.push(self.slice(from, self.text.len()));
+        parts
+    }
+
+    /// Whitespace-separated words
+    fn words(&self) -> Vec<Field<'a>> {
+        let mut words = Vec::new();
+        let mut from = None;
+        for (i, c) in self.text.char_indices().chain([(self.text.len(), ' ')]) {
+            match (c.is_whitespace(), from) {
+                (true, Some(start)) => {
+                    words.push(self.slice(start, i));
+                    from = None;
+                }
+                (false, None) => from = Some(i),
+                _ => {}
+            }
+        }
+        words
+    }
+}
+
+/// `content` awaiting its modifiers
+struct Content {
+    name: String,
+    negated: bool,
+    span: Span,
+}
+
+struct RuleParser {
+    /// Integer fields compared so far, with their widths
+    integers: Vec<(Variable, usize)>,
+}
+
+impl RuleParser {
+    /// Bits of a packet field, least significant first
+    fn field(&mut self, name: &str, span: Span) -> Vec<Expr> {
+        let width = SNORT_FIELDS.iter().find(|(n, _)| *n == name).expect("known packet field").1;
+        let var = Variable { name: name.to_string(), span, bit: None };
+        if !self.integers.iter().any(|(v, _)| *v == var) {
+            self.integers.push((var.clone(), width));
+        }
+        integer::bits(&var, width)
+    }
+
+    /// Address or port set: `any`, `$NAME`, `!set`, `[set, ...]` or one `atom`
+    fn set(
+        &mut self,
+        name: &str,
+        field: Field,
+        atom: fn(&mut Self, &str, Field) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        let text = field.text;
+        if text.is_empty() {
+            return Err(field.error(format!("expected a value for {}", name)));
+        }
+        if text.starts_with('!') {
+            return Ok(not(self.set(name, field.slice(1, text.len()), atom)?));
+        }
+        if text == "any" {
+            return Ok(Expr::Const(true));
+        }
+        if text.starts_with('$') {
+            return Ok(Expr::Var(Variable { name: format!("{} in {}", name, text), span: field.span(), bit: None }));
+        }
+        if text.starts_with('[') {
+            if !text.ends_with(']') {
+                return Err(field.error("unclosed '['"));
+            }
+            let (mut any, mut none) = (Expr::Const(false), Expr::Const(true));
+            let mut positive = false;
+            for item in field.slice(1, text.len() - 1).split(',') {
+                let item = item.trim();
+                let expr = self.set(name, item, atom)?;
+                if item.text.starts_with('!') {
+                    none = and(none, expr);
+                } else {
+                    any = or(any, expr);
+                    positive = true;
+                }
+            }
+            return Ok(if positive { and(any, none) } else { none });
+        }
+        atom(self, name, field)
+    }
+
+    /// `a.b.c.d` or `a.b.c.d/prefix`
+    fn address(&mut self, name: &str, field: Field) -> Result<Expr, ParseError> {
+        let (ip, prefix) = match field.text.split_once('/') {
+            Some((ip, prefix)) => (ip, prefix.parse::<usize>().ok().filter(|p| *p <= 32)),
+            None => (field.text, Some(32)),
+        };
+        let invalid = || field.error(format!("invalid address '{}'", field.text));
+        let ip: Ipv4Addr = ip.parse().map_err(|_| invalid())?;
+        let prefix = prefix.ok_or_else(invalid)?;
+        let bits = self.field(name, field.span());
+        let network = u64::from(u32::from(ip)) >> (32 - prefix);
+        Ok(integer::compare(Comparison::Eq, &bits[32 - prefix..], &integer::constant(network)))
+    }
+
+    /// `port`, `low:high`, `low:` or `:high`
+    fn port(&mut self, name: &str, field: Field) -> Result<Expr, ParseError> {
+        let number = |text: &str| {
+            text.parse::<u16>().map_err(|_| field.error(format!("invalid port '{}'", field.text)))
+        };
+        let (low, high) = match field.text.split_once(':') {
+            Some((low, high)) => (
+                if low.is_empty() { 0 } else { number(low)? },
+                if high.is_empty() { u16::MAX } else { number(high)? },
+            ),
+            None => {
+                let port = number(field.text)?;
+                (port, port)
+            }
+        };
+        if low > high {
+            return Err(field.error(format!("port range '{}' ends below its start", field.text)));
+        }
+        let bits = self.field(name, field.span());
+        if low == high {
+            return Ok(integer::compare(Comparison::Eq, &bits, &integer::constant(low.into())));
+        }
+        let above = integer::compare(Comparison::Ge, &bits, &integer::constant(low.into()));
+        Ok(and(above, integer::compare(Comparison::Le, &bits, &integer::constant(high.into()))))
+    }
+
+    /// Source and destination of one direction of traffic
+    fn endpoints(&mut self, from: (Field, Field), to: (Field, Field)) -> Result<Expr, ParseError> {
+        let conditions = [
+            self.set("src_ip", from.0, Self::address)?,
+            self.set("src_port", from.1, Self::port)?,
+            self.set("dst_ip", to.0, Self::address)?,
+            self.set("dst_port", to.1, Self::port)?,
+        ];
+        Ok(conditions.into_iter().fold(Expr::Const(true), and))
+    }
+
+    /// `flags:[+*!]FSRPAUCE0[,mask]`
+    fn flags(&mut self, field: Field) -> Result<Expr, ParseError> {
+        let (flags, mask) = match field.text.split_once(',') {
+            Some((flags, mask)) => (flags, Some(mask)),
+            None => (field.text, None),
+        };
+        let bit_set = |letters: &str| {
+            letters.chars().try_fold(0u8, |set, c| {
+                let bit = match c.to_ascii_uppercase() {
+                    'F' => 0,
+                    'S' => 1,
+                    'R' => 2,
+                    'P' => 3,
+                    'A' => 4,
+                    'U' => 5,
+                    'E' | '2' => 6,
+                    'C' | '1' => 7,
+                    '0' => return Some(set),
+                    _ => return None,
+                };
+                Some(set | 1 << bit)
+            })
+        };
+        let modifier = flags.chars().find(|c| "+*!".contains(*c));
+        let letters: String = flags.chars().filter(|c| !"+*!".contains(*c)).collect();
+        let invalid = || field.error(format!("invalid flags '{}'", field.text));
+        let listed = bit_set(&letters).ok_or_else(invalid)?;
+        let ignored = mask.map_or(Some(0), bit_set).ok_or_else(invalid)?;
+        if letters.is_empty() {
+            return Err(invalid());
+        }
+
+        let bits = self.field("tcp_flags", field.span());
+        let is_listed = |i: usize| listed >> i & 1 == 1;
+        let expr = match modifier {
+            None => (0..8)
+                .filter(|i| ignored >> i & 1 == 0)
+                .map(|i| if is_listed(i) { bits[i].clone() } else { not(bits[i].clone()) })
+                .fold(Expr::Const(true), and),
+            Some('+') => (0..8).filter(|&i| is_listed(i)).map(|i| bits[i].clone()).fold(Expr::Const(true), and),
+            Some('*') => (0..8).filter(|&i| is_listed(i)).map(|i| bits[i].clone()).fold(Expr::Const(false), or),
+            _ => (0..8).filter(|&i| is_listed(i)).map(|i| not(bits[i].clone())).fold(Expr::Const(true), and),
+        };
+        Ok(expr)
+    }
+}
+
+fn quoted(field: Field) -> Result<String, ParseError> {
+    let text = field.text;
+    if text.len() < 2 || !text.starts_with('"') || !text.ends_with('"') {
+        return Err(field.error("expected a quoted string"));
+    }
+    Ok(text[1..text.len() - 1].replace("\\\"", "\"").replace("\\;", ";").replace("\\\\", "\\"))
+}
+
+fn number(field: Field) -> Result<u64, ParseError> {
+    field.text.parse().map_err(|_| field.error(format!("expected a number, found '{}'", field.text)))
+}
+
+/// Value of an option that may appear once per rule
+fn once<T>(slot: &mut Option<T>, key: Field, value: T) -> Result<(), ParseError> {
+    match slot.replace(value) {
+        Some(_) => Err(key.error(format!("duplicate '{}' option", key.text))),
+        None => Ok(()),
+    }
+}
+
+/// Parse one rule into its alert condition
+pub fn parse_snort_rule(rule: &str) -> Result<SnortRule, ParseError> {
+    let all = Field { text: rule, start: 0 }.trim();
+    let open = all.text.find('(').ok_or_else(|| all.error("expected '(' before the rule options"))?;
+    if !all.text.ends_with(')') {
+        return Err(ParseError::new("rule options must end with ')'", Span::new(all.span().end, all.span().end)));
+    }
+    let header = all.slice(0, open).words();
+    let options = all.slice(open + 1, all.text.len() - 1);
+
+    let [action, proto, src_ip, src_port, direction, dst_ip, dst_port] = header[..] else {
+        return Err(all.slice(0, open).error(format!(
+            "expected 'action protocol address port direction address port', found {} fields",
+            header.len()
+        )));
+    };
+    let action = match action.text {
+        "alert" => RuleAction::Alert,
+        "log" => RuleAction::Log,
+        "pass" => RuleAction::Pass,
+        "drop" => RuleAction::Drop,
+        "reject" => RuleAction::Reject,
+        "sdrop" => RuleAction::Sdrop,
+        other => return Err(action.error(format!("unknown action '{}'", other))),
+    };
+
+    let mut parser = RuleParser { integers: Vec::new() };
+    let protocol = match proto.text {
+        "ip" => None,
+        "icmp" => Some(1),
+        "tcp" => Some(6),
+        "udp" => Some(17),
+        other => return Err(proto.error(format!("unknown protocol '{}'", other))),
+    };
+    let mut conditions = Vec::new();
+    if let Some(number) = protocol {
+        let bits = parser.field("ip_proto", proto.span());
+        conditions.push(integer::compare(Comparison::Eq, &bits, &integer::constant(number)));
+    }
+    let forward = parser.endpoints((src_ip, src_port), (dst_ip, dst_port))?;
+    conditions.push(match direction.text {
+        "->" => forward,
+        "<>" => or(forward, parser.endpoints((dst_ip, dst_port), (src_ip, src_port))?),
+        other => return Err(direction.error(format!("expected '->' or '<>', found '{}'", other))),
+    });
+
+    let (mut msg, mut sid, mut rev) = (None, None, None);
+    let mut content: Option<Content> = None;
+    let flush = |content: &mut Option<Content>, conditions: &mut Vec<Expr>| {
+        if let Some(c) = content.take() {
+            let var = Expr::Var(Variable { name: c.name, span: c.span, bit: None });
+            conditions.push(if c.negated { not(var) } else { var });
+        }
+    };
+    for option in options.split(';') {
+        let option = option.trim();
+        if option.text.is_empty() {
+            continue;
+        }
+        let (key, value) = match option.text.find(':') {
+            Some(colon) => (option.slice(0, colon).trim(), Some(option.slice(colon + 1, option.text.len()).trim())),
+            None => (option, None),
+        };
+        let value_of = || value.ok_or_else(|| key.error(format!("'{}' needs a value", key.text)));
+        if CONTENT_MODIFIERS.contains(&key.text) {
+            let pending = content
+                .as_mut()
+                .ok_or_else(|| key.error(format!("'{}' modifies a preceding content", key.text)))?;
+            pending.name.push(';');
+            pending.name.push_str(&option.text.split_whitespace().collect::<String>());
+            pending.span = pending.span.join(option.span());
+            continue;
+        }
+        if IGNORED_OPTIONS.contains(&key.text) {
+            continue;
+        }
+        flush(&mut content, &mut conditions);
+        match key.text {
+            "msg" => once(&mut msg, key, quoted(value_of()?)?)?,
+            "sid" => once(&mut sid, key, number(value_of()?)?)?,
+            "rev" => once(&mut rev, key, number(value_of()?)?)?,
+            "content" => {
+                let value = value_of()?;
+                let negated = value.text.starts_with('!');
+                let pattern = if negated { value.slice(1, value.text.len()).trim() } else { value };
+                quoted(pattern)?;
+                content = Some(Content { name: format!("content:{}", pattern.text), negated, span: option.span() });
+            }
+            "flow" => {
+                for keyword in value_of()?.split(',') {
+                    let keyword = keyword.trim();
+                    if !FLOW_KEYWORDS.contains(&keyword.text) {
+                        return Err(keyword.error(format!("unknown flow keyword '{}'", keyword.text)));
+                    }
+                    let name = format!("flow:{}", keyword.text);
+                    conditions.push(Expr::Var(Variable { name, span: keyword.span(), bit: None }));
+                }
+            }
+            "flags" => conditions.push(parser.flags(value_of()?)?),
+            other => return Err(key.error(format!("unsupported rule option '{}'", other))),
+        }
+    }
+    flush(&mut content, &mut conditions);
+
+    let mut expression = LogicExpression::new(conditions.into_iter().fold(Expr::Const(true), and));
+    expression.variables = allocate_bits(&expression.variables, &parser.integers);
+    if expression.variables.is_empty() {
+        return Err(all.error("rule matches every packet, there is nothing to prove"));
+    }
+    Ok(SnortRule { action, msg, sid, rev, expression })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partitioned_gc::PartitionedGCScheme;
+    use crate::psg::{polylithic_syntax_gen_from, PsgOptions, VariableHashing};
+
+    /// Packet fields by name, and the Boolean variables the sensor reports true
+    struct Packet {
+        fields: [(&'static str, u64); 6],
+        matched: &'static [&'static str],
+    }
+
+    impl Packet {
+        fn value(&self, var: &Variable) -> bool {
+            match var.bit {
+                Some(bit) => self.fields.iter().find(|(n, _)| *n == var.name).unwrap().1 >> bit & 1 == 1,
+                None => self.matched.contains(&var.name.as_str()),
+            }
+        }
+    }
+
+    fn ip(text: &str) -> u64 {
+        u32::from(text.parse::<Ipv4Addr>().unwrap()).into()
+    }
+
+    fn syn_packet(dst: &str, port: u64, matched: &'static [&'static str]) -> Packet {
+        let fields = [
+            ("ip_proto", 6),
+            ("src_ip", ip("203.0.113.9")),
+            ("src_port", 51000),
+            ("dst_ip", ip(dst)),
+            ("dst_port", port),
+            ("tcp_flags", 0x02),
+        ];
+        Packet { fields, matched }
+    }
+
+    #[test]
+    fn test_rule_header_and_options() {
+        let rule = parse_snort_rule(
+            r#"alert tcp $EXTERNAL_NET any -> 192.168.1.0/24 [80,443,8000:8100] (msg:"web \"login\""; flow:to_server; flags:S,12; content:"POST"; nocase; depth:4; fast_pattern; sid:1000001; rev:2;)"#,
+        )
+        .unwrap();
+        assert_eq!(rule.action, RuleAction::Alert);
+        assert_eq!((rule.msg.as_deref(), rule.sid, rule.rev), (Some(r#"web "login""#), Some(1000001), Some(2)));
+        let names: Vec<String> = rule.expression.variables.iter().map(|v| v.to_string()).collect();
+        assert_eq!(names[..2], ["ip_proto[0]", "ip_proto[1]"]);
+        assert!(names.contains(&"src_ip in $EXTERNAL_NET".to_string()));
+        assert!(names.contains(&"content:\"POST\";nocase;depth:4".to_string()));
+        // Every field a rule compares enters with its full width
+        assert_eq!(names.iter().filter(|n| n.starts_with("dst_ip[")).count(), 32);
+
+        let matched = &["src_ip in $EXTERNAL_NET", "flow:to_server", "content:\"POST\";nocase;depth:4"];
+        let eval = |packet: &Packet| rule.expression.root.eval(&|v| packet.value(v));
+        assert!(eval(&syn_packet("192.168.1.20", 443, matched)));
+        assert!(eval(&syn_packet("192.168.1.20", 8050, matched)));
+        assert!(!eval(&syn_packet("192.168.2.20", 443, matched)), "outside the /24");
+        assert!(!eval(&syn_packet("192.168.1.20", 22, matched)), "port not listed");
+        assert!(!eval(&syn_packet("192.168.1.20", 80, &matched[..2])), "content missing");
+        let mut ecn = syn_packet("192.168.1.20", 80, matched);
+        ecn.fields[5].1 = 0x02 | 0x40 | 0x80;
+        assert!(eval(&ecn), "reserved bits masked by ',12'");
+        ecn.fields[5].1 = 0x12;
+        assert!(!eval(&ecn), "SYN-ACK is not SYN");
+    }
+
+    #[test]
+    fn test_rule_lists_negation_and_direction() {
+        let rule = parse_snort_rule(
+            "drop udp [10.0.0.0/8,!10.1.0.0/16] !53 <> any :1023 (content:!\"|00 01|\"; flags:*SF;)",
+        )
+        .unwrap();
+        let eval = |fields: [(&'static str, u64); 6], matched: &'static [&'static str]| {
+            rule.expression.root.eval(&|v| Packet { fields, matched }.value(v))
+        };
+        let packet = |src: &str, sport: u64, dst: &str, dport: u64| {
+            [("ip_proto", 17), ("src_ip", ip(src)), ("src_port", sport), ("dst_ip", ip(dst)), ("dst_port", dport), ("tcp_flags", 1)]
+        };
+        assert!(eval(packet("10.2.0.1", 5000, "8.8.8.8", 123), &[]));
+        assert!(!eval(packet("10.1.0.1", 5000, "8.8.8.8", 123), &[]), "negated list entry");
+        assert!(!eval(packet("10.2.0.1", 53, "8.8.8.8", 123), &[]), "negated port");
+        assert!(eval(packet("8.8.8.8", 123, "10.2.0.1", 5000), &[]), "reverse direction");
+        assert!(!eval(packet("10.2.0.1", 5000, "8.8.8.8", 123), &["content:\"|00 01|\""]));
+
+        for (bad, at) in [
+            ("alert tcp any any -> any any", "expected '('"),
+            ("alert tcp any any -> any (sid:1;)", "found 6 fields"),
+            ("alert tcp any any => any any (sid:1;)", "'->' or '<>'"),
+            ("alert tcp 10.0.0.300 any -> any any (sid:1;)", "invalid address"),
+            ("alert tcp any any -> any any (nocase; sid:1;)", "modifies a preceding content"),
+            ("alert tcp any any -> any any (pcre:\"/a/\";)", "unsupported rule option 'pcre'"),
+            ("alert ip any any -> any any (msg:\"all\";)", "matches every packet"),
+            ("alert tcp any any -> any 1024:80 (sid:1;)", "port range '1024:80' ends below its start"),
+            ("alert tcp any any -> any any (flags:S; sid:1; sid:2;)", "duplicate 'sid' option"),
+            ("alert tcp any any -> any any (flags:S; rev:1; rev:1;)", "duplicate 'rev' option"),
+            ("alert tcp any any -> any any (msg:\"a\"; flags:S; msg:\"b\";)", "duplicate 'msg' option"),
+        ] {
+            let err = parse_snort_rule(bad).unwrap_err();
+            assert!(err.to_string().contains(at), "{}: {}", bad, err);
+        }
+        let err = parse_snort_rule("alert tcp any any -> any 99999 (sid:1;)").unwrap_err();
+        assert_eq!(err.span, Span::new(25, 30));
+    }
+
+    #[test]
+    fn test_rule_circuit_proves_alert() {
+        let rule = parse_snort_rule("alert tcp any any -> 10.0.0.0/8 22 (flags:S; content:\"SSH-\";)").unwrap();
+        let options = PsgOptions { hashing: VariableHashing::Legacy, ..PsgOptions::default() };
+        let circuit = polylithic_syntax_gen_from(&rule.expression, &options);
+        let gc = PartitionedGCScheme::garble_circuit(&circuit.circuit).unwrap();
+        let run = |dst: &str, content: bool| {
+            let packet = syn_packet(dst, 22, if content { &["content:\"SSH-\""] } else { &[] });
+            let inputs: Vec<u8> = rule.expression.variables.iter().map(|v| packet.value(v) as u8).collect();
+            let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+            PartitionedGCScheme::evaluate_garbled(&gc, &mut labels);
+            PartitionedGCScheme::decode_outputs(&gc, &labels).unwrap()
+        };
+        assert_eq!(run("10.20.30.40", true), vec![1]);
+        assert_eq!(run("10.20.30.40", false), vec![0]);
+        assert_eq!(run("11.0.0.1", true), vec![0]);
+    }
+}
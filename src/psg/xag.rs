@@ -0,0 +1,189 @@
+//! Free-XOR-aware rewriting: minimize the AND count of a circuit
+//!
+//! With free-XOR garbling only AND and OR gates need a garbled table, so the cost
+//! of a circuit is its multiplicative complexity. The circuit is read into an
+//! AND/XOR-inverter graph (where OR, NAND and NOR are ANDs with complemented edges
+//! and NOT is free) and two-term ORs and XORs of AND nodes are rewritten:
+//!
+//! - shared literal: `(l & a) | (l & b)` = `l & (a | b)`, likewise for `^`
+//! - complementary literal: `(s & a) | (!s & b)` = `b ^ (s & (a ^ b))`; the terms
+//!   are disjoint, so the same holds for `^`. With `b = !a` this turns a sum of
+//!   products back into a single XOR.
+//!
+//! Rewrites are applied until the AND count stops decreasing. The result keeps its
+//! OR gates: an OR costs the same table as an AND, and lowering it would only add
+//! NOT gates.
+
+use super::aig::{Aig, Lit, Node};
+use crate::partitioned_gc::Circuit;
+
+#[derive(Clone, Copy)]
+enum Combine {
+    Or,
+    Xor,
+}
+
+/// Fanins of `lit` when it is an uncomplemented AND node
+fn and_fanins(aig: &Aig, lit: Lit) -> Option<[Lit; 2]> {
+    match aig.nodes[lit.node()] {
+        Node::And(a, b) if !lit.is_complement() => Some([a, b]),
+        _ => None,
+    }
+}
+
+/// One rewrite pass over `aig`
+pub fn rewrite(aig: &Aig) -> Aig {
+    let fanout = aig.fanouts();
+    let mut new = Aig::with_inputs(&aig.inputs);
+    let mut map: Vec<Lit> = vec![Lit::FALSE; aig.nodes.len()];
+    let mapped = |map: &[Lit], l: Lit| if l.is_complement() { !map[l.node()] } else { map[l.node()] };
+
+    for (i, node) in aig.nodes.iter().enumerate() {
+        // `x op y` over two AND terms, complemented when the node is `!(x | y)`
+        let (terms, combine, complement) = match *node {
+            Node::And(u, v) if u.is_complement() && v.is_complement() => ((!u, !v), Combine::Or, true),
+            Node::Xor(u, v) => ((u, v), Combine::Xor, false),
+            _ => ((Lit::FALSE, Lit::FALSE), Combine::Or, false),
+        };
+        let rewritten = match (and_fanins(aig, terms.0), and_fanins(aig, terms.1)) {
+            (Some(x), Some(y)) if fanout[terms.0.node()] == 1 || fanout[terms.1.node()] == 1 => {
+                let x = x.map(|l| mapped(&map, l));
+                let y = y.map(|l| mapped(&map, l));
+                combine_terms(&mut new, x, y, combine).map(|l| if complement { !l } else { l })
+            }
+            _ => None,
+        };
+
+        map[i] = match (rewritten, *node) {
+            (Some(lit), _) => lit,
+            (None, Node::Const) => Lit::FALSE,
+            (None, Node::Input(k)) => new.input(k),
+            (None, Node::And(a, b)) => new.and(mapped(&map, a), mapped(&map, b)),
+            (None, Node::Xor(a, b)) => new.xor(mapped(&map, a), mapped(&map, b)),
+        };
+    }
+    new.outputs = aig.outputs.iter().map(|&l| mapped(&map, l)).collect();
+    new
+}
+
+/// `(x0 & x1) op (y0 & y1)` with fewer ANDs, when the terms share a literal or
+/// contain complementary ones
+fn combine_terms(aig: &mut Aig, x: [Lit; 2], y: [Lit; 2], combine: Combine) -> Option<Lit> {
+    let apply = |aig: &mut Aig, a: Lit, b: Lit| match combine {
+        Combine::Or => aig.or(a, b),
+        Combine::Xor => aig.xor(a, b),
+    };
+    for (i, j) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        let (a, b) = (x[1 - i], y[1 - j]);
+        if x[i] == y[j] {
+            let rest = apply(aig, a, b);
+            return Some(aig.and(x[i], rest));
+        }
+        if x[i] == !y[j] {
+            let diff = aig.xor(a, b);
+            let select = aig.and(x[i], diff);
+            return Some(aig.xor(b, select));
+        }
+    }
+    None
+}
+
+/// Rewrite `circuit` with as few AND and OR gates as the rewrite rules find; input
+/// and output wires keep their roles
+pub fn minimize_and_gates(circuit: &Circuit) -> Circuit {
+    minimize(Aig::from_circuit(circuit)).to_circuit()
+}
+
+/// Apply `rewrite` until the AND count stops decreasing
+pub fn minimize(mut aig: Aig) -> Aig {
+    loop {
+        let next = rewrite(&aig);
+        if next.counts().0 >= aig.counts().0 {
+            return aig;
+        }
+        aig = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partitioned_gc::{Gate, GateType, PartitionedGCScheme};
+    use crate::psg::{compile, parse_precedence};
+
+    fn circuit(gates: Vec<Gate>, inputs: usize, output: usize) -> Circuit {
+        Circuit::new(gates, (0..inputs).collect(), vec![output])
+    }
+
+    #[test]
+    fn test_sum_of_products_becomes_xor_and_mux() {
+        // (a & !b) | (!a & b)
+        let xor = circuit(
+            vec![
+                Gate::not(1, 2),
+                Gate::binary(GateType::And, 0, 2, 3),
+                Gate::not(0, 4),
+                Gate::binary(GateType::And, 4, 1, 5),
+                Gate::binary(GateType::Or, 3, 5, 6),
+            ],
+            2,
+            6,
+        );
+        let optimized = minimize_and_gates(&xor);
+        assert_eq!((xor.and_count(), optimized.and_count()), (3, 0));
+        assert!(optimized.gates.iter().all(|g| g.gate_type != GateType::Or));
+
+        // (s & a) | (!s & b)
+        let mux = circuit(
+            vec![
+                Gate::binary(GateType::And, 0, 1, 3),
+                Gate::not(0, 4),
+                Gate::binary(GateType::And, 4, 2, 5),
+                Gate::binary(GateType::Or, 3, 5, 6),
+            ],
+            3,
+            6,
+        );
+        assert_eq!(minimize_and_gates(&mux).and_count(), 1);
+    }
+
+    #[test]
+    fn test_minimized_circuit_matches_expression() {
+        for (input, ands) in [
+            (r#"("a" and "b") or ("a" and "c")"#, 2),
+            (r#"("a" and not "b") or (not "a" and "b") or "c""#, 1),
+            (r#"("s" and "x") xor ("s" and "y") xor not "z""#, 1),
+            (r#"The car only starts if the "start" button is pressed and the "brake" pedal is pressed"#, 2),
+        ] {
+            let expression = parse_precedence(input).unwrap();
+            let hashed: Vec<String> = expression.variables.iter().map(|v| v.name.clone()).collect();
+            let optimized = minimize_and_gates(&compile::compile(&expression, &hashed).circuit);
+            assert_eq!(optimized.and_count(), ands, "{}", input);
+
+            let gc = PartitionedGCScheme::garble_circuit(&optimized).unwrap();
+            let n = expression.variables.len();
+            for x in 0..(1u32 << n) {
+                let inputs: Vec<u8> = (0..n).map(|i| ((x >> i) & 1) as u8).collect();
+                let expected = expression
+                    .root
+                    .eval(&|var| inputs[expression.variables.iter().position(|v| v == var).unwrap()] == 1);
+                let mut labels = PartitionedGCScheme::encode_inputs(&gc, &inputs);
+                PartitionedGCScheme::evaluate_garbled(&gc, &mut labels);
+                assert_eq!(PartitionedGCScheme::decode_outputs(&gc, &labels), Some(vec![expected as u8]), "{}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_free_xor_step_never_adds_gates() {
+        let chain = |op: &str, n: usize| (0..n).map(|i| format!(r#""v{}""#, i)).collect::<Vec<_>>().join(op);
+        let terms: Vec<String> = (0..20).map(|i| format!(r#"("a{}" and "b{}")"#, i, i)).collect();
+        for statement in [chain(" or ", 200), terms.join(" or "), r#"("a" and not "b") or (not "a" and "b")"#.to_string()] {
+            let report = crate::psg::polylithic_syntax_gen(&statement).unwrap().free_xor.unwrap();
+            assert!(report.and_gates_after <= report.and_gates_before, "{}: {:?}", statement, report);
+            if report.and_gates_after == report.and_gates_before {
+                assert!(report.gates_after <= report.gates_before, "{}: {:?}", statement, report);
+            }
+        }
+    }
+}
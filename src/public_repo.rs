@@ -3,6 +3,7 @@ use std::io::Write;
 use std::path::Path;
 
 use crate::partitioned_gc::{Circuit, GarbledGate, WireKeys};
+use crate::psg::HashManifest;
 use serde::Serialize;
 use std::collections::HashMap;
 
@@ -11,22 +12,25 @@ struct PublicCircuitData {
     circuit: Circuit,
     wire_keys: HashMap<usize, WireKeys>,
     garbled_gates: Vec<GarbledGate>,
+    hashing: HashManifest, // salt and key commitment verifiers need to recompute variable hashes
 }
 
 pub fn publish_to_public_repo(
-    garbled_gates: &Vec<GarbledGate>,
+    garbled_gates: &[GarbledGate],
     wire_keys: &HashMap<usize, WireKeys>,
     circuit: &Circuit,
+    hashing: &HashManifest,
 ) -> std::io::Result<()> {
     // Create output directory if it doesn't exist
     let output_dir = Path::new("public_repo/");
-    create_dir_all(&output_dir)?;
+    create_dir_all(output_dir)?;
 
     // Clone data so we can serialize it
     let data = PublicCircuitData {
         circuit: circuit.clone(),
         wire_keys: wire_keys.clone(),
-        garbled_gates: garbled_gates.clone(),
+        garbled_gates: garbled_gates.to_vec(),
+        hashing: hashing.clone(),
     };
 
     // Serialize data to pretty JSON format